@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
+#[cfg_attr(not(feature = "backend-async-std"), allow(dead_code))]
 struct MyData {
     a: i32,
     b: String,
@@ -22,7 +23,7 @@ async fn main() {
             b: "async".to_string(),
         })
         .overwrite_local()
-        .json(true)
+        .json()
         .build()
         .await
         .unwrap();
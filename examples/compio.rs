@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
+#[cfg_attr(not(feature = "backend-compio"), allow(dead_code))]
 struct MyData {
     a: i32,
     b: String,
@@ -12,14 +12,25 @@ impl VowFileAsync for File {
         Self: Sized,
     {
         OpenOptions::new()
+            .read(true)
             .write(true)
             .create(true)
             .truncate(false)
             .open(path)
     }
 
+    fn open_read_only(path: &Path) -> impl super::IoFut<Self>
+    where
+        Self: Sized,
+    {
+        OpenOptions::new().read(true).open(path)
+    }
+
     fn read(&mut self, mut buf: Vec<u8>) -> impl super::BufFut {
         async move {
+            if let Err(e) = self.seek(SeekFrom::Start(0)).await {
+                return (Err(e), buf);
+            }
             let res = self.read_to_end(&mut buf).await.map(|_| ());
             (res, buf)
         }
@@ -46,6 +57,40 @@ impl VowFileAsync for File {
             Ok(())
         }
     }
+
+    fn exists(path: &Path) -> impl super::IoFut<bool>
+    where
+        Self: Sized,
+    {
+        async move {
+            match async_std::fs::metadata(path).await {
+                Ok(_) => Ok(true),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+                Err(e) => Err(e),
+            }
+        }
+    }
+
+    fn remove(path: &Path) -> impl super::IoFut<()>
+    where
+        Self: Sized,
+    {
+        async_std::fs::remove_file(path)
+    }
+
+    fn rename(from: &Path, to: &Path) -> impl super::IoFut<()>
+    where
+        Self: Sized,
+    {
+        async_std::fs::rename(from, to)
+    }
+
+    fn write_new(path: &Path, buf: Vec<u8>) -> impl super::IoFut<()>
+    where
+        Self: Sized,
+    {
+        async_std::fs::write(path, buf)
+    }
 }
 
 #[cfg(test)]
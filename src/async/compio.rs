@@ -24,6 +24,13 @@ impl VowFileAsync for File {
         }
     }
 
+    fn open_read_only(path: &Path) -> impl IoFut<Self>
+    where
+        Self: Sized,
+    {
+        async move { compio_fs::OpenOptions::new().read(true).open(path).await }
+    }
+
     fn read(&mut self, buf: Vec<u8>) -> impl BufFut {
         async move {
             let res = self.read_to_end_at(buf, 0).await;
@@ -58,6 +65,40 @@ impl VowFileAsync for File {
             Ok(())
         }
     }
+
+    fn exists(path: &Path) -> impl IoFut<bool>
+    where
+        Self: Sized,
+    {
+        async move {
+            match compio_fs::metadata(path).await {
+                Ok(_) => Ok(true),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+                Err(e) => Err(e),
+            }
+        }
+    }
+
+    fn remove(path: &Path) -> impl IoFut<()>
+    where
+        Self: Sized,
+    {
+        compio_fs::remove_file(path)
+    }
+
+    fn rename(from: &Path, to: &Path) -> impl IoFut<()>
+    where
+        Self: Sized,
+    {
+        compio_fs::rename(from, to)
+    }
+
+    fn write_new(path: &Path, buf: Vec<u8>) -> impl IoFut<()>
+    where
+        Self: Sized,
+    {
+        async move { compio_fs::write(path, buf).await.0 }
+    }
 }
 
 #[cfg(test)]
@@ -2,7 +2,7 @@
 
 use std::{future::Future, io, path::Path};
 
-use crate::{format, Async, Data, Io, VowResult};
+use crate::{format, hooks::Hooks, Async, Data, Io, Operation, ResultExt, VowResult};
 
 mod_use::mod_use![maybe_send];
 
@@ -16,15 +16,13 @@ mod compio;
 mod async_std;
 
 macro_rules! tri {
-    ($self:ident, $expr:expr) => {{
+    ($self:ident, $expr:expr, $operation:expr) => {{
         let (res, buf) = $expr.await;
         $self.buf = buf;
-        res?
+        res.during($operation)?
     }};
 }
 
-use tri;
-
 /// Trait alias for futures returning `io::Result<T>`
 pub trait IoFut<T>: Future<Output = io::Result<T>> + MaybeSend {}
 
@@ -42,6 +40,11 @@ pub trait VowFileAsync: MaybeSend {
     where
         Self: Sized;
 
+    /// Open an existing file at the given path asynchronously, only to read it
+    fn open_read_only(path: &Path) -> impl IoFut<Self>
+    where
+        Self: Sized;
+
     /// Read **entire** file into a buffer
     fn read(&mut self, buf: Vec<u8>) -> impl BufFut;
 
@@ -53,6 +56,27 @@ pub trait VowFileAsync: MaybeSend {
 
     /// Set the length of the file
     fn set_len(&mut self, len: u64) -> impl IoFut<()>;
+
+    /// Check whether a file exists at the given path
+    fn exists(path: &Path) -> impl IoFut<bool>
+    where
+        Self: Sized;
+
+    /// Remove the file at the given path
+    fn remove(path: &Path) -> impl IoFut<()>
+    where
+        Self: Sized;
+
+    /// Rename the file at `from` to `to`, replacing `to` if it exists
+    fn rename(from: &Path, to: &Path) -> impl IoFut<()>
+    where
+        Self: Sized;
+
+    /// Write **entire** buffer into the file at the given path, creating it or replacing its
+    /// content
+    fn write_new(path: &Path, buf: Vec<u8>) -> impl IoFut<()>
+    where
+        Self: Sized;
 }
 
 impl<F: VowFileAsync> Io<F, Async> {
@@ -60,30 +84,43 @@ impl<F: VowFileAsync> Io<F, Async> {
         &mut self,
         current: Option<T>,
         overwrite: bool,
+        hooks: &Hooks<T>,
     ) -> VowResult<T> {
-        let mut buf = self.take_buf();
-
         if let Some(current) = current {
             let ret = if overwrite {
-                format::se(self.format, buf.as_mut(), &current)?;
-                self.file.set_len(0).await?;
-                tri!(self, self.file.write(buf));
+                hooks.saving(&current)?;
+                let buf = self.encode(&current).during(Operation::Encode)?;
+                self.file.set_len(0).await.during(Operation::Truncate)?;
+                tri!(self, self.file.write(buf), Operation::Write);
                 current
             } else {
-                tri!(self, self.file.read(buf));
-                match format::des(self.format, &self.buf) {
-                    Ok(value) => value,
+                let buf = self.take_buf();
+                tri!(self, self.file.read(buf), Operation::Read);
+                match self.decode() {
+                    Ok(mut decoded) => {
+                        hooks.loaded(&mut decoded.value)?;
+                        if decoded.stale {
+                            hooks.saving(&decoded.value)?;
+                            let buf = self.encode(&decoded.value).during(Operation::Encode)?;
+                            self.write_back(decoded.upgraded, buf).await?;
+                        }
+                        decoded.value
+                    }
                     Err(err) => {
+                        // Never mistake content meant for another key or type for a missing value
+                        if err.is_mismatch() {
+                            return Err(err);
+                        }
                         if err.is_invalid_data() {
                             if self.deny_invalid {
                                 return Err(err);
                             }
 
                             // Overwrite when invalid data is found
-                            let mut buf = self.take_buf();
-                            format::se(self.format, buf.as_mut(), &current)?;
-                            self.file.set_len(0).await?;
-                            tri!(self, self.file.write(buf));
+                            hooks.saving(&current)?;
+                            let buf = self.encode(&current).during(Operation::Encode)?;
+                            self.file.set_len(0).await.during(Operation::Truncate)?;
+                            tri!(self, self.file.write(buf), Operation::Write);
                         }
 
                         current
@@ -92,8 +129,52 @@ impl<F: VowFileAsync> Io<F, Async> {
             };
             Ok(ret)
         } else {
-            tri!(self, self.file.read(buf));
-            format::des(self.format, &self.buf)
+            let buf = self.take_buf();
+            tri!(self, self.file.read(buf), Operation::Read);
+            let mut decoded = self.decode()?;
+            hooks.loaded(&mut decoded.value)?;
+            if decoded.stale {
+                hooks.saving(&decoded.value)?;
+                let buf = self.encode(&decoded.value).during(Operation::Encode)?;
+                self.write_back(decoded.upgraded, buf).await?;
+            }
+            Ok(decoded.value)
         }
     }
+
+    /// Write back `buf`, the encoded value that was upgraded or completed. Content upgraded from
+    /// schema version `upgraded` is backed up first.
+    #[cfg_attr(not(feature = "versioning"), allow(unused_variables))]
+    async fn write_back(&mut self, upgraded: Option<u32>, buf: Vec<u8>) -> VowResult<()> {
+        #[cfg(feature = "versioning")]
+        if let Some(path) = upgraded.and_then(|from| self.versioning.as_ref()?.backup_path(from)) {
+            backup::<F>(&path, self.buf.clone())
+                .await
+                .in_file(Some(&path))?;
+        }
+        self.file.set_len(0).await.during(Operation::Truncate)?;
+        tri!(self, self.file.write(buf), Operation::Write);
+        Ok(())
+    }
+
+    /// Pick the format from the content of the file.
+    pub(crate) async fn sniff<T: Data>(&mut self, path: Option<&Path>) -> VowResult<()> {
+        let buf = self.take_buf();
+        tri!(self, self.file.read(buf), Operation::Read);
+        let format = format::auto::pick::<T>(&self.plain().during(Operation::Read)?, path)
+            .during(Operation::Parse)?;
+        self.format = format;
+        Ok(())
+    }
+}
+
+/// Write `buf`, the content before an upgrade, to the backup file at `path`.
+#[cfg(feature = "versioning")]
+async fn backup<F: VowFileAsync>(path: &Path, buf: Vec<u8>) -> VowResult<()> {
+    let mut backup = F::open(path).await.during(Operation::Open)?;
+    backup.set_len(0).await.during(Operation::Truncate)?;
+    let (res, _) = backup.write(buf).await;
+    res.during(Operation::Write)?;
+    backup.flush().await.during(Operation::Flush)?;
+    Ok(())
 }
@@ -26,8 +26,18 @@ impl VowFileAsync for File {
         }
     }
 
+    fn open_read_only(path: &Path) -> impl IoFut<Self>
+    where
+        Self: Sized,
+    {
+        async move { tokio::fs::OpenOptions::new().read(true).open(path).await }
+    }
+
     fn read(&mut self, mut buf: Vec<u8>) -> impl BufFut {
         async move {
+            if let Err(e) = self.seek(SeekFrom::Start(0)).await {
+                return (Err(e), buf);
+            }
             let res = AsyncReadExt::read_to_end(self, &mut buf).await.map(|_| ());
             (res, buf)
         }
@@ -55,6 +65,34 @@ impl VowFileAsync for File {
             Ok(())
         }
     }
+
+    fn exists(path: &Path) -> impl IoFut<bool>
+    where
+        Self: Sized,
+    {
+        tokio::fs::try_exists(path)
+    }
+
+    fn remove(path: &Path) -> impl IoFut<()>
+    where
+        Self: Sized,
+    {
+        tokio::fs::remove_file(path)
+    }
+
+    fn rename(from: &Path, to: &Path) -> impl IoFut<()>
+    where
+        Self: Sized,
+    {
+        tokio::fs::rename(from, to)
+    }
+
+    fn write_new(path: &Path, buf: Vec<u8>) -> impl IoFut<()>
+    where
+        Self: Sized,
+    {
+        tokio::fs::write(path, buf)
+    }
 }
 
 #[cfg(test)]
@@ -1,38 +1,50 @@
 use std::{
     fs::{File, OpenOptions},
-    io::{self, Read, Write},
+    io::{self, Read, Seek, SeekFrom, Write},
     path::Path,
 };
 
 use serde::{de::DeserializeOwned, Serialize};
 
-use crate::{format, marker::Blocking, Io, VowResult};
+use crate::{
+    format, hooks::Hooks, marker::Blocking, Decoded, Io, Operation, ResultExt, VowResult,
+};
 
 impl<F: VowFile> Io<F, Blocking> {
-    pub(crate) fn sync<T>(&mut self, current: Option<T>, overwrite: bool) -> VowResult<T>
+    pub(crate) fn sync<T>(
+        &mut self,
+        current: Option<T>,
+        overwrite: bool,
+        hooks: &Hooks<T>,
+    ) -> VowResult<T>
     where
         T: Serialize + DeserializeOwned,
     {
-        self.buf.clear();
-
         if let Some(mut current) = current {
             let ret = if overwrite {
-                format::se(self.format, &mut self.buf, &current)?;
-                self.file.set_len(0)?;
-                self.file.write_all(&self.buf)?;
+                hooks.saving(&current)?;
+                self.write(&current)?;
                 current
             } else {
-                self.file.read_to_end(&mut self.buf)?;
-                match format::des(self.format, &self.buf) {
-                    Ok(value) => value,
+                self.read()?;
+                match self.decode() {
+                    Ok(mut decoded) => {
+                        hooks.loaded(&mut decoded.value)?;
+                        self.write_back(&decoded, hooks)?;
+                        decoded.value
+                    }
                     Err(err) => {
+                        // Never mistake content meant for another key or type for a missing value
+                        if err.is_mismatch() {
+                            return Err(err);
+                        }
                         if err.is_invalid_data() {
                             if self.deny_invalid {
                                 return Err(err);
                             }
 
                             // Overwrite when invalid data is found
-                            current = self.sync(Some(current), true)?;
+                            current = self.sync(Some(current), true, hooks)?;
                         }
                         current
                     }
@@ -40,33 +52,99 @@ impl<F: VowFile> Io<F, Blocking> {
             };
             Ok(ret)
         } else {
-            self.file.read_to_end(&mut self.buf)?;
-            format::des(self.format, &self.buf)
+            self.read()?;
+            let mut decoded = self.decode()?;
+            hooks.loaded(&mut decoded.value)?;
+            self.write_back(&decoded, hooks)?;
+            Ok(decoded.value)
+        }
+    }
+
+    /// Write back the value if it was upgraded or completed. Content upgraded from an older
+    /// schema version is backed up first.
+    fn write_back<T: Serialize>(&mut self, decoded: &Decoded<T>, hooks: &Hooks<T>) -> VowResult<()> {
+        if !decoded.stale {
+            return Ok(());
+        }
+        hooks.saving(&decoded.value)?;
+        #[cfg(feature = "versioning")]
+        if let Some(path) = decoded
+            .upgraded
+            .and_then(|from| self.versioning.as_ref()?.backup_path(from))
+        {
+            backup::<F>(&path, &self.buf).in_file(Some(&path))?;
         }
+        self.write(&decoded.value)
     }
+
+    /// Pick the format from the content of the file.
+    pub(crate) fn sniff<T: DeserializeOwned>(&mut self, path: Option<&Path>) -> VowResult<()> {
+        self.read()?;
+        let format = format::auto::pick::<T>(&self.plain().during(Operation::Read)?, path)
+            .during(Operation::Parse)?;
+        self.format = format;
+        Ok(())
+    }
+
+    fn read(&mut self) -> VowResult<()> {
+        self.buf.clear();
+        self.file.rewind().during(Operation::Read)?;
+        self.file
+            .read_to_end(&mut self.buf)
+            .during(Operation::Read)?;
+        Ok(())
+    }
+
+    fn write<T: Serialize>(&mut self, value: &T) -> VowResult<()> {
+        self.buf = self.encode(value).during(Operation::Encode)?;
+        self.file.set_len(0).during(Operation::Truncate)?;
+        self.file.write_all(&self.buf).during(Operation::Write)?;
+        Ok(())
+    }
+}
+
+/// Write `buf`, the content before an upgrade, to the backup file at `path`.
+#[cfg(feature = "versioning")]
+fn backup<F: VowFile>(path: &Path, buf: &[u8]) -> VowResult<()> {
+    let mut backup = F::open(path).during(Operation::Open)?;
+    backup.set_len(0).during(Operation::Truncate)?;
+    backup.write_all(buf).during(Operation::Write)?;
+    backup.flush().during(Operation::Flush)?;
+    Ok(())
 }
 
 /// Low-level trait for synchronous file operations
-pub trait VowFile: Read + Write {
+pub trait VowFile: Read + Write + Seek {
     /// Open a new file at the given path
     fn open(path: impl AsRef<Path>) -> io::Result<Self>
     where
         Self: Sized;
 
-    /// Set the length of the file
+    /// Open an existing file at the given path, only to read it
+    fn open_read_only(path: impl AsRef<Path>) -> io::Result<Self>
+    where
+        Self: Sized;
+
+    /// Set the length of the file, moving the cursor to `len`
     fn set_len(&mut self, len: u64) -> io::Result<()>;
 }
 
 impl VowFile for File {
     fn open(path: impl AsRef<Path>) -> io::Result<Self> {
         OpenOptions::new()
+            .read(true)
             .write(true)
             .create(true)
             .truncate(false)
             .open(path)
     }
 
+    fn open_read_only(path: impl AsRef<Path>) -> io::Result<Self> {
+        OpenOptions::new().read(true).open(path)
+    }
+
     fn set_len(&mut self, len: u64) -> io::Result<()> {
+        self.seek(SeekFrom::Start(len))?;
         Self::set_len(self, len)
     }
 }
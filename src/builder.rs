@@ -7,16 +7,17 @@ use std::{
 use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
-    format::{self, DefaultFormat, ToFormat},
-    marker::{Async, Blocking, Just, Nothing, ToOption},
-    Io, Vow, VowAsync, VowFile, VowFileAsync, VowResult,
+    format::{self, DefaultFormat, Format, FormatOptions, Indent, ResolveFormat, ToFormat},
+    hooks::Hooks,
+    marker::{Async, Blocking, Hooked, Just, Nothing, ToOption, Typed},
+    Io, Operation, ResultExt, Vow, VowAsync, VowFile, VowFileAsync, VowResult,
 };
 
 /// Builder for [`Vow`].
 ///
 /// By default, it will:
 ///
-/// - use `JSON` format without pretty printing.
+/// - use `JSON` format without pretty printing (see [`FormatOptions`] for output options).
 /// - not overwrite the file if it already exists and has valid content.
 /// - not fail on (deny) invalid content (instead, overwrite it silently).
 ///
@@ -25,7 +26,7 @@ use crate::{
 /// # Example
 ///
 /// ```no_run
-/// use vow::VowBuilder;
+/// use vow::Vow;
 /// use serde::{Deserialize, Serialize};
 /// use std::fs::File;
 ///
@@ -35,7 +36,7 @@ use crate::{
 /// }
 ///
 /// let file = File::create("data.json").unwrap();
-/// let vow = VowBuilder::new(file)
+/// let vow = Vow::builder(file)
 ///     .json()
 ///     .pretty()
 ///     .default(Data { value: 42 })
@@ -43,12 +44,107 @@ use crate::{
 ///     .overwrite_local()
 ///     .build();
 /// ```
+#[allow(clippy::struct_excessive_bools)]
 pub struct VowBuilder<T, F, A, Fo> {
     file: FileBuilder<F, A>,
     default: T,
     overwrite: bool,
     deny_invalid: bool,
     format: Fo,
+    options: FormatOptions,
+    migrate: Option<Migration>,
+    #[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+    compression: crate::Compression,
+    #[cfg(feature = "encrypt")]
+    encrypt: Option<crate::Key>,
+    #[cfg(feature = "envelope")]
+    envelope: Option<crate::envelope::Schema>,
+    #[cfg(feature = "sign")]
+    sign: Option<crate::sign::Signer>,
+    #[cfg(feature = "versioning")]
+    versioning: Option<crate::versioning::Versioning>,
+    #[cfg(feature = "merge")]
+    merge: Option<crate::Merge>,
+    #[cfg(feature = "preserve")]
+    preserve: bool,
+    #[cfg(feature = "unknown-keys")]
+    deny_unknown: bool,
+    #[cfg(feature = "schema")]
+    schema: crate::schema::Options,
+}
+
+/// What to do with the legacy file once its content is migrated, see
+/// [`VowBuilder::migrate_from`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LegacyFile {
+    /// Leave the legacy file as it is.
+    Keep,
+    /// Delete the legacy file.
+    Remove,
+    /// Rename the legacy file, e.g. to keep a backup.
+    Rename(PathBuf),
+}
+
+struct Migration {
+    from: PathBuf,
+    legacy: LegacyFile,
+}
+
+impl Migration {
+    /// The format of the legacy file if the migration is due, i.e. the file at `path` is
+    /// missing and the legacy file exists.
+    fn pending(&self, path: Option<&Path>) -> VowResult<Option<Format>> {
+        let path = Self::target(path)?;
+        if path.try_exists()? || !self.from.try_exists()? {
+            return Ok(None);
+        }
+        self.format().map(Some)
+    }
+
+    /// Same as [`pending`](Self::pending), through the async file API of `F`.
+    async fn pending_async<F: VowFileAsync>(
+        &self,
+        path: Option<&Path>,
+    ) -> VowResult<Option<Format>> {
+        let path = Self::target(path)?;
+        if F::exists(path).await? || !F::exists(&self.from).await? {
+            return Ok(None);
+        }
+        self.format().map(Some)
+    }
+
+    /// The path of the file to migrate to, which must be opened by path.
+    fn target(path: Option<&Path>) -> VowResult<&Path> {
+        path.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "migrating requires the file to be opened by path",
+            )
+            .into()
+        })
+    }
+
+    fn format(&self) -> VowResult<Format> {
+        format::auto::from_path(&self.from)
+            .ok_or_else(|| format::auto::unknown(Some(&self.from), "unknown extension"))
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        match &self.legacy {
+            LegacyFile::Keep => Ok(()),
+            LegacyFile::Remove => std::fs::remove_file(&self.from),
+            LegacyFile::Rename(to) => std::fs::rename(&self.from, to),
+        }
+    }
+
+    /// Same as [`finish`](Self::finish), through the async file API of `F`.
+    async fn finish_async<F: VowFileAsync>(&self) -> io::Result<()> {
+        match &self.legacy {
+            LegacyFile::Keep => Ok(()),
+            LegacyFile::Remove => F::remove(&self.from).await,
+            LegacyFile::Rename(to) => F::rename(&self.from, to).await,
+        }
+    }
 }
 
 enum FileBuilder<F, A> {
@@ -56,19 +152,32 @@ enum FileBuilder<F, A> {
     Path(PathBuf, PhantomData<A>),
 }
 
+impl<F, A> FileBuilder<F, A> {
+    fn path(&self) -> Option<&Path> {
+        match self {
+            Self::File(_) => None,
+            Self::Path(path, _) => Some(path),
+        }
+    }
+}
+
 impl<F: VowFileAsync> FileBuilder<F, Async> {
-    async fn open(self) -> io::Result<F> {
+    /// Open the file, only to read it if `read_only`.
+    async fn open(self, read_only: bool) -> io::Result<F> {
         match self {
             Self::File(file) => Ok(file),
+            Self::Path(path, _) if read_only => F::open_read_only(&path).await,
             Self::Path(path, _) => F::open(&path).await,
         }
     }
 }
 
 impl<F: VowFile> FileBuilder<F, Blocking> {
-    fn open(self) -> io::Result<F> {
+    /// Open the file, only to read it if `read_only`.
+    fn open(self, read_only: bool) -> io::Result<F> {
         match self {
             Self::File(file) => Ok(file),
+            Self::Path(path, _) if read_only => F::open_read_only(&path),
             Self::Path(path, _) => F::open(&path),
         }
     }
@@ -82,6 +191,26 @@ impl<T, F> VowBuilder<Nothing<T>, F, Blocking, DefaultFormat> {
             default: Nothing(PhantomData),
             overwrite: false,
             deny_invalid: false,
+            options: FormatOptions::new(),
+            migrate: None,
+            #[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+            compression: crate::Compression::None,
+            #[cfg(feature = "encrypt")]
+            encrypt: None,
+            #[cfg(feature = "envelope")]
+            envelope: None,
+            #[cfg(feature = "sign")]
+            sign: None,
+            #[cfg(feature = "versioning")]
+            versioning: None,
+            #[cfg(feature = "merge")]
+            merge: None,
+            #[cfg(feature = "preserve")]
+            preserve: false,
+            #[cfg(feature = "unknown-keys")]
+            deny_unknown: false,
+            #[cfg(feature = "schema")]
+            schema: crate::schema::Options::new(),
             format: DefaultFormat::default(),
         }
     }
@@ -93,6 +222,26 @@ impl<T, F> VowBuilder<Nothing<T>, F, Blocking, DefaultFormat> {
             default: Nothing(PhantomData),
             overwrite: false,
             deny_invalid: false,
+            options: FormatOptions::new(),
+            migrate: None,
+            #[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+            compression: crate::Compression::None,
+            #[cfg(feature = "encrypt")]
+            encrypt: None,
+            #[cfg(feature = "envelope")]
+            envelope: None,
+            #[cfg(feature = "sign")]
+            sign: None,
+            #[cfg(feature = "versioning")]
+            versioning: None,
+            #[cfg(feature = "merge")]
+            merge: None,
+            #[cfg(feature = "preserve")]
+            preserve: false,
+            #[cfg(feature = "unknown-keys")]
+            deny_unknown: false,
+            #[cfg(feature = "schema")]
+            schema: crate::schema::Options::new(),
             format: DefaultFormat::default(),
         }
     }
@@ -106,6 +255,26 @@ impl<T, F> VowBuilder<Nothing<T>, F, Async, DefaultFormat> {
             default: Nothing(PhantomData),
             overwrite: false,
             deny_invalid: false,
+            options: FormatOptions::new(),
+            migrate: None,
+            #[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+            compression: crate::Compression::None,
+            #[cfg(feature = "encrypt")]
+            encrypt: None,
+            #[cfg(feature = "envelope")]
+            envelope: None,
+            #[cfg(feature = "sign")]
+            sign: None,
+            #[cfg(feature = "versioning")]
+            versioning: None,
+            #[cfg(feature = "merge")]
+            merge: None,
+            #[cfg(feature = "preserve")]
+            preserve: false,
+            #[cfg(feature = "unknown-keys")]
+            deny_unknown: false,
+            #[cfg(feature = "schema")]
+            schema: crate::schema::Options::new(),
             format: DefaultFormat::default(),
         }
     }
@@ -117,35 +286,84 @@ impl<T, F> VowBuilder<Nothing<T>, F, Async, DefaultFormat> {
             default: Nothing(PhantomData),
             overwrite: false,
             deny_invalid: false,
+            options: FormatOptions::new(),
+            migrate: None,
+            #[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+            compression: crate::Compression::None,
+            #[cfg(feature = "encrypt")]
+            encrypt: None,
+            #[cfg(feature = "envelope")]
+            envelope: None,
+            #[cfg(feature = "sign")]
+            sign: None,
+            #[cfg(feature = "versioning")]
+            versioning: None,
+            #[cfg(feature = "merge")]
+            merge: None,
+            #[cfg(feature = "preserve")]
+            preserve: false,
+            #[cfg(feature = "unknown-keys")]
+            deny_unknown: false,
+            #[cfg(feature = "schema")]
+            schema: crate::schema::Options::new(),
             format: DefaultFormat::default(),
         }
     }
 }
 
-impl<T, F, A, Fo> VowBuilder<Nothing<T>, F, A, Fo> {
-    /// Set the type of the value without providing anything concrete.
-    ///
-    /// This is useful when you know there's existsing value stored in file and its type, so
-    /// we can just retrieve it during initialization.
-    pub fn with_type<U>(self) -> VowBuilder<Nothing<U>, F, A, Fo> {
+impl<T, F, A, Fo> VowBuilder<T, F, A, Fo> {
+    /// Switch to the default state made from the current one, keeping the rest of the builder.
+    fn with_default<D>(self, f: impl FnOnce(T) -> D) -> VowBuilder<D, F, A, Fo> {
         VowBuilder {
+            default: f(self.default),
             file: self.file,
-            default: Nothing(PhantomData),
             overwrite: self.overwrite,
             deny_invalid: self.deny_invalid,
+            options: self.options,
+            migrate: self.migrate,
+            #[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+            compression: self.compression,
+            #[cfg(feature = "encrypt")]
+            encrypt: self.encrypt,
+            #[cfg(feature = "envelope")]
+            envelope: self.envelope,
+            #[cfg(feature = "sign")]
+            sign: self.sign,
+            #[cfg(feature = "versioning")]
+            versioning: self.versioning,
+            #[cfg(feature = "merge")]
+            merge: self.merge,
+            #[cfg(feature = "preserve")]
+            preserve: self.preserve,
+            #[cfg(feature = "unknown-keys")]
+            deny_unknown: self.deny_unknown,
+            #[cfg(feature = "schema")]
+            schema: self.schema,
             format: self.format,
         }
     }
+}
+
+impl<T, F, A, Fo> VowBuilder<Nothing<T>, F, A, Fo> {
+    /// Set the type of the value without providing anything concrete.
+    ///
+    /// This is useful when you know there's existsing value stored in file and its type, so
+    /// we can just retrieve it during initialization.
+    pub fn with_type<U>(self) -> VowBuilder<Typed<U>, F, A, Fo> {
+        self.with_default(|_| Typed(Hooks::new()))
+    }
 
     /// Set the default value in case the file is empty or has invalid content.
     pub fn default<U>(self, value: U) -> VowBuilder<Just<U>, F, A, Fo> {
-        VowBuilder {
-            default: Just(value),
-            file: self.file,
-            overwrite: self.overwrite,
-            deny_invalid: self.deny_invalid,
-            format: self.format,
-        }
+        self.with_default(|_| Just(value, Hooks::new()))
+    }
+}
+
+impl<T, F, A, Fo> VowBuilder<Typed<T>, F, A, Fo> {
+    /// Set the default value in case the file is empty or has invalid content, keeping the
+    /// hooks registered for the type.
+    pub fn default(self, value: T) -> VowBuilder<Just<T>, F, A, Fo> {
+        self.with_default(|Typed(hooks)| Just(value, hooks))
     }
 }
 
@@ -170,37 +388,703 @@ impl<T, F, A, Fo> VowBuilder<T, F, A, Fo> {
         self.overwrite = false;
         self
     }
+
+    /// Replace all output options at once.
+    #[must_use]
+    pub const fn format_options(mut self, options: FormatOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Pretty print the output, indented by two spaces.
+    ///
+    /// Supported by `json`, `xml`, `kdl` and `toml_edit`, which only indents arrays: building
+    /// fails with [`Error::UnsupportedOption`](crate::Error::UnsupportedOption) for other
+    /// formats, see [`FormatOptions`].
+    #[must_use]
+    pub const fn pretty(self) -> Self {
+        self.indent(Indent::Spaces(2))
+    }
+
+    /// Indent nested values with `indent`, for the formats listed in [`pretty`](Self::pretty).
+    #[must_use]
+    pub const fn indent(mut self, indent: Indent) -> Self {
+        self.options.indent = Some(indent);
+        self
+    }
+
+    /// Write keys in sorted order, so that the output is deterministic.
+    ///
+    /// Supported by `json`, `toml_edit`, `cbor`, `ini`, `properties`, `dotenv` and `kdl`:
+    /// building fails with [`Error::UnsupportedOption`](crate::Error::UnsupportedOption) for
+    /// other formats.
+    #[must_use]
+    pub const fn sort_keys(mut self) -> Self {
+        self.options.sort_keys = true;
+        self
+    }
+
+    /// End the output with a newline.
+    #[must_use]
+    pub const fn trailing_newline(mut self) -> Self {
+        self.options.trailing_newline = true;
+        self
+    }
+
+    /// Write TOML arrays with one element per line.
+    ///
+    /// Only supported by `toml_edit`: building fails with
+    /// [`Error::UnsupportedOption`](crate::Error::UnsupportedOption) for other formats.
+    #[must_use]
+    pub const fn pretty_arrays(mut self) -> Self {
+        self.options.pretty_arrays = true;
+        self
+    }
+
+    /// Compress the file with `compression`, on top of the format.
+    #[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+    #[must_use]
+    pub const fn compress(mut self, compression: crate::Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Encrypt the file with XChaCha20-Poly1305, after compression if any.
+    ///
+    /// A random nonce is used for every write. Content that can't be decrypted, because of a
+    /// wrong key, tampering or missing encryption, is reported as
+    /// [`Error::Decrypt`](crate::Error::Decrypt) and never overwritten with the default value.
+    #[cfg(feature = "encrypt")]
+    #[must_use]
+    pub fn encrypt(mut self, key: crate::Key) -> Self {
+        self.encrypt = Some(key);
+        self
+    }
+
+    /// Wrap the content in an envelope recording a fingerprint of `T`, the length of the content
+    /// and its CRC-32 checksum.
+    ///
+    /// Truncated or corrupted content is reported as invalid data, instead of a generic parse
+    /// error or, with formats that are not self-describing, a wrong value. A file written for
+    /// another type, or without an envelope, is reported as
+    /// [`EnvelopeError::Fingerprint`](crate::EnvelopeError::Fingerprint) or
+    /// [`EnvelopeError::Missing`](crate::EnvelopeError::Missing) and never overwritten.
+    ///
+    /// The fingerprint is taken from [`std::any::type_name`], which changes when the type is
+    /// renamed or moved, and is not guaranteed to be stable across compiler versions: use
+    /// [`envelope_schema`](Self::envelope_schema) for files that must outlive those.
+    #[cfg(feature = "envelope")]
+    #[must_use]
+    pub fn envelope(mut self) -> Self {
+        self.envelope = Some(crate::envelope::Schema::Type);
+        self
+    }
+
+    /// Like [`envelope`](Self::envelope), with the fingerprint taken from `id` instead of the
+    /// name of the type.
+    #[cfg(feature = "envelope")]
+    #[must_use]
+    pub fn envelope_schema(mut self, id: impl Into<String>) -> Self {
+        self.envelope = Some(crate::envelope::Schema::Id(id.into()));
+        self
+    }
+
+    /// Sign the file with ed25519, as the outermost layer.
+    ///
+    /// Content whose signature doesn't verify, including unsigned content, is reported as
+    /// [`Error::Verify`](crate::Error::Verify) and never overwritten with the default value.
+    #[cfg(feature = "sign")]
+    #[must_use]
+    pub fn sign(mut self, key: crate::SigningKey) -> Self {
+        self.sign = Some(crate::sign::Signer::Sign(key));
+        self
+    }
+
+    /// Only verify the signature of the file, see [`sign`](Self::sign).
+    ///
+    /// Without the signing key, the file is read-only: a file opened by path is opened for
+    /// reading only, so a missing file fails to open instead of receiving the default value,
+    /// and every write fails with [`Error::ReadOnly`](crate::Error::ReadOnly).
+    #[cfg(feature = "sign")]
+    #[must_use]
+    pub fn verify(mut self, key: crate::VerifyingKey) -> Self {
+        self.sign = Some(crate::sign::Signer::Verify(key));
+        self
+    }
 }
 
-#[cfg(feature = "format-json")]
+#[cfg(feature = "versioning")]
 impl<T, F, A, Fo> VowBuilder<T, F, A, Fo> {
-    /// Output the data in JSON format.
+    fn versioning(&mut self) -> &mut crate::versioning::Versioning {
+        self.versioning
+            .get_or_insert_with(crate::versioning::Versioning::new)
+    }
+
+    /// Declare the current version of the schema, stored under the
+    /// [`VERSION_KEY`](crate::VERSION_KEY) key of the file.
+    ///
+    /// Files from an older version, including files without a version which are version `0`,
+    /// are upgraded with the [migrations](Self::migration) registered for each version, then
+    /// written back. Files that can't be upgraded, e.g. from a newer version or with a failing
+    /// migration, are reported as [`Error::Migrate`](crate::Error::Migrate) and never
+    /// overwritten with the default value.
+    ///
+    /// The value is written as a map starting with the version, so it must be a struct or a
+    /// map, without `#[serde(deny_unknown_fields)]`. Versions are read from an untyped tree,
+    /// which formats that are not self-describing like postcard don't support.
     #[must_use]
-    #[cfg(feature = "format-json")]
-    pub fn json(self, pretty: bool) -> VowBuilder<T, F, A, format::Json> {
+    pub fn schema_version(mut self, version: u32) -> Self {
+        self.versioning().version = version;
+        self
+    }
+
+    /// Store the schema version under `key` instead of [`VERSION_KEY`](crate::VERSION_KEY).
+    #[must_use]
+    pub fn version_key(mut self, key: &'static str) -> Self {
+        self.versioning().key = key;
+        self
+    }
+
+    /// Register the migration from version `from` to `from + 1`, on the untyped tree of the
+    /// content without the version key.
+    #[must_use]
+    pub fn migration<M, E>(mut self, from: u32, migration: M) -> Self
+    where
+        M: Fn(crate::Value) -> Result<crate::Value, E> + Send + Sync + 'static,
+        E: std::fmt::Display,
+    {
+        self.versioning().step(from, migration);
+        self
+    }
+
+    /// Register the migration from version `from` to `from + 1`, from the struct `Old` of
+    /// version `from` to the struct `New` of the next one.
+    #[must_use]
+    pub fn typed_migration<Old, New, M>(mut self, from: u32, migration: M) -> Self
+    where
+        Old: serde::de::DeserializeOwned,
+        New: serde::Serialize,
+        M: Fn(Old) -> New + Send + Sync + 'static,
+    {
+        self.versioning().step(from, move |tree: crate::Value| {
+            let old = tree.deserialize_into().map_err(|e| e.to_string())?;
+            serde_value::to_value(migration(old)).map_err(|e| e.to_string())
+        });
+        self
+    }
+
+    /// Keep a copy of the file next to it before migrating, named after the version it's
+    /// from, e.g. `config.json.v1.bak`. Requires the file to be opened by path.
+    #[must_use]
+    pub fn migration_backup(mut self) -> Self {
+        self.versioning().backup = true;
+        self
+    }
+}
+
+#[cfg(feature = "merge")]
+impl<T, F, A, Fo> VowBuilder<T, F, A, Fo> {
+    /// Deep-merge the file over the default value when loading, so that fields the file lacks,
+    /// e.g. added to `T` since it was written, come from the default instead of failing and
+    /// replacing the whole file. Values in the file win.
+    ///
+    /// The file is merged as an untyped tree, which formats that are not self-describing like
+    /// postcard don't support. Values of formats that only have strings, like ini, are still
+    /// parsed by type.
+    #[must_use]
+    pub const fn merge_defaults(mut self) -> Self {
+        self.merge = Some(crate::Merge::InMemory);
+        self
+    }
+
+    /// Like [`merge_defaults`](Self::merge_defaults), also writing the completed file back so
+    /// that users can see the new keys.
+    #[must_use]
+    pub const fn merge_defaults_and_write(mut self) -> Self {
+        self.merge = Some(crate::Merge::WriteBack);
+        self
+    }
+}
+
+#[cfg(feature = "preserve")]
+impl<T, F, A, Fo> VowBuilder<T, F, A, Fo> {
+    /// Keep the keys of the file that `T` doesn't know, e.g. written by a newer version of the
+    /// application, when writing: they are appended to the maps they were found in.
+    ///
+    /// The keys preserved are those ignored when the file was last loaded, so fields skipped
+    /// when serializing, e.g. with `#[serde(skip_serializing_if)]`, are not. The file is read as
+    /// an untyped tree, which formats that are not self-describing like postcard don't support.
+    #[must_use]
+    pub const fn preserve_unknown(mut self) -> Self {
+        self.preserve = true;
+        self
+    }
+}
+
+impl<T: Hooked, F, A, Fo> VowBuilder<T, F, A, Fo> {
+    /// Check the value once loaded and before every write. A value failing any check is
+    /// rejected with [`Error::Validate`](crate::Error::Validate) and the file is left untouched:
+    /// building or reloading fails, and [`Vow::set`], [`Vow::map`] and [`Vow::update`] keep
+    /// the previous value. Changes are made on a clone of the value, which only replaces it
+    /// once checked.
+    ///
+    /// Hooks are registered for the type of the value, so only once it's set by
+    /// [`default`](Self::default) or [`with_type`](Self::with_type). Hooks registered after
+    /// `with_type` are kept by a later `default`.
+    #[must_use]
+    pub fn validate<V, E>(mut self, f: V) -> Self
+    where
+        T::Some: Clone,
+        V: Fn(&T::Some) -> Result<(), E> + Send + Sync + 'static,
+        E: std::fmt::Display,
+    {
+        self.default.hooks().validate(f);
+        self
+    }
+
+    /// Normalize the value once loaded, e.g. to resolve relative paths, before it's validated.
+    #[must_use]
+    pub fn after_load(mut self, f: impl Fn(&mut T::Some) + Send + Sync + 'static) -> Self {
+        self.default.hooks().after_load(f);
+        self
+    }
+
+    /// Observe the value before every write, once validated.
+    #[must_use]
+    pub fn before_save(mut self, f: impl Fn(&T::Some) + Send + Sync + 'static) -> Self {
+        self.default.hooks().before_save(f);
+        self
+    }
+}
+
+#[cfg(feature = "unknown-keys")]
+impl<T, F, A, Fo> VowBuilder<T, F, A, Fo> {
+    /// Fail to load content with keys that `T` ignores, e.g. misspelled settings, instead of
+    /// only reporting them in [`Vow::unknown_keys`]. The error lists their paths, and the file
+    /// is left untouched.
+    #[must_use]
+    pub const fn deny_unknown_keys(mut self) -> Self {
+        self.deny_unknown = true;
+        self
+    }
+}
+
+#[cfg(feature = "schema")]
+impl<T: ToOption, F, A, Fo> VowBuilder<T, F, A, Fo>
+where
+    T::Some: crate::JsonSchema,
+{
+    /// Validate the content against the JSON Schema of the value before deserializing it. Every
+    /// value that doesn't match is reported in [`Error::Schema`](crate::Error::Schema), and the
+    /// file is left untouched.
+    ///
+    /// The content is read as an untyped tree, so formats whose values are all strings, like
+    /// ini, only match schemas of strings. Content from an older schema version is validated by
+    /// deserializing it once migrated.
+    #[must_use]
+    pub fn validate_schema(mut self) -> Self {
+        self.schema.validate = Some(crate::json_schema::<T::Some>());
+        self
+    }
+
+    /// Write the JSON Schema of the value to `path` when building, e.g. `config.schema.json`
+    /// next to the file, so that editors can complete and check it. See
+    /// [`schema_reference`](Self::schema_reference) to point the file to it.
+    #[must_use]
+    pub fn schema_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.schema.file = Some((
+            path.as_ref().to_path_buf(),
+            crate::json_schema::<T::Some>(),
+        ));
+        self
+    }
+}
+
+#[cfg(feature = "schema")]
+impl<T, F, A, Fo> VowBuilder<T, F, A, Fo> {
+    /// Write `reference`, the URL or path of the JSON Schema of the file, as its first key
+    /// [`$schema`](crate::SCHEMA_KEY), which editors use for completion. The value must be a
+    /// struct or a map.
+    #[must_use]
+    pub fn schema_reference(mut self, reference: impl Into<String>) -> Self {
+        self.schema.reference = Some(reference.into());
+        self
+    }
+}
+
+impl<T, F, A, Fo> VowBuilder<T, F, A, Fo> {
+    /// Migrate from a legacy file, e.g. `config.json` when moving to `config.toml`.
+    ///
+    /// If the file is missing and the legacy file exists, the value is read from the legacy
+    /// file, in the format picked from its extension as in [`auto_format`](Self::auto_format),
+    /// and written to the file in its own format. `legacy` is then applied to the legacy file.
+    /// Building fails if the legacy file has invalid content, and leaves it untouched.
+    ///
+    /// The file must be opened by path.
+    #[must_use]
+    pub fn migrate_from(mut self, path: impl AsRef<Path>, legacy: LegacyFile) -> Self {
+        self.migrate = Some(Migration {
+            from: path.as_ref().to_path_buf(),
+            legacy,
+        });
+        self
+    }
+
+    /// Switch to `format`, keeping the rest of the builder.
+    fn with_format<To>(self, format: To) -> VowBuilder<T, F, A, To> {
         VowBuilder {
-            format: format::Json { pretty },
+            format,
             file: self.file,
             default: self.default,
             overwrite: self.overwrite,
             deny_invalid: self.deny_invalid,
+            options: self.options,
+            migrate: self.migrate,
+            #[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+            compression: self.compression,
+            #[cfg(feature = "encrypt")]
+            encrypt: self.encrypt,
+            #[cfg(feature = "envelope")]
+            envelope: self.envelope,
+            #[cfg(feature = "sign")]
+            sign: self.sign,
+            #[cfg(feature = "versioning")]
+            versioning: self.versioning,
+            #[cfg(feature = "merge")]
+            merge: self.merge,
+            #[cfg(feature = "preserve")]
+            preserve: self.preserve,
+            #[cfg(feature = "unknown-keys")]
+            deny_unknown: self.deny_unknown,
+            #[cfg(feature = "schema")]
+            schema: self.schema,
         }
     }
+
+    /// Pick the format at runtime from the extension of the file, among the enabled formats.
+    ///
+    /// Formats are used with their default options. `.jsonc` reads JSON with comments, `.tsv`
+    /// is CSV separated by tabs, `.env` and `.env.*` files are dotenv, and `.toml` preserves
+    /// the layout of the file when `format-toml-edit` is enabled. Building fails with
+    /// [`Error::UnknownFormat`](crate::Error::UnknownFormat) for other extensions, unless
+    /// [`sniff`](Self::sniff) is set.
+    #[must_use]
+    pub fn auto_format(self) -> VowBuilder<T, F, A, format::Auto> {
+        self.with_format(format::Auto { sniff: false })
+    }
 }
 
-#[cfg(feature = "format-toml")]
+impl<T, F, A> VowBuilder<T, F, A, format::Auto> {
+    /// When the extension is unknown or missing, pick the first enabled format the content of
+    /// the file can be read from, trying the strictest syntaxes first.
+    ///
+    /// Binary formats are only recognized by their checksum (postcard) or self-describe tag
+    /// (CBOR). An empty or missing file has nothing to sniff, so it is written in the format a
+    /// builder starts with (`JSON` when enabled).
+    #[must_use]
+    pub const fn sniff(mut self) -> Self {
+        self.format.sniff = true;
+        self
+    }
+}
+
+#[cfg(feature = "format-json")]
 impl<T, F, A, Fo> VowBuilder<T, F, A, Fo> {
     /// Output the data in JSON format.
     #[must_use]
+    #[cfg(feature = "format-json")]
+    pub fn json(self) -> VowBuilder<T, F, A, format::Json> {
+        self.with_format(format::Json { lenient: false })
+    }
+}
+
+#[cfg(feature = "format-json")]
+impl<T, F, A> VowBuilder<T, F, A, format::Json> {
+    /// Accept JSONC when reading: `//` and `/* */` comments and trailing commas are ignored.
+    ///
+    /// Since the file is still valid, it won't be overwritten on load. Note that comments are
+    /// not preserved when the value is written back.
+    #[must_use]
+    pub const fn lenient(mut self) -> Self {
+        self.format.lenient = true;
+        self
+    }
+}
+
+#[cfg(feature = "format-toml")]
+impl<T, F, A, Fo> VowBuilder<T, F, A, Fo> {
+    /// Output the data in TOML format.
+    ///
+    /// `indent`, `sort_keys` and `pretty_arrays` aren't supported and make building fail with
+    /// [`Error::UnsupportedOption`](crate::Error::UnsupportedOption), see [`FormatOptions`].
+    #[must_use]
     #[cfg(feature = "format-toml")]
     pub fn toml(self) -> VowBuilder<T, F, A, format::Toml> {
-        VowBuilder {
-            format: format::Toml {},
-            file: self.file,
-            default: self.default,
-            overwrite: self.overwrite,
-            deny_invalid: self.deny_invalid,
-        }
+        self.with_format(format::Toml {})
+    }
+}
+
+#[cfg(feature = "format-toml-edit")]
+impl<T, F, A, Fo> VowBuilder<T, F, A, Fo> {
+    /// Output the data in TOML format, preserving the layout of the file.
+    ///
+    /// Only keys whose value changed are rewritten: comments, ordering and whitespace of the
+    /// rest of the document are kept. TOML datetimes are supported through
+    /// [`toml_edit::Datetime`](https://docs.rs/toml_edit/latest/toml_edit/struct.Datetime.html).
+    #[must_use]
+    #[cfg(feature = "format-toml-edit")]
+    pub fn toml_edit(self) -> VowBuilder<T, F, A, format::TomlEdit> {
+        self.with_format(format::TomlEdit {})
+    }
+}
+
+#[cfg(feature = "format-xml")]
+impl<T, F, A, Fo> VowBuilder<T, F, A, Fo> {
+    /// Output the data in XML format.
+    ///
+    /// By default, the root element is named after the type, and only fields renamed with a `@`
+    /// prefix (e.g. `#[serde(rename = "@id")]`) are written as attributes.
+    #[must_use]
+    #[cfg(feature = "format-xml")]
+    pub fn xml(self) -> VowBuilder<T, F, A, format::Xml> {
+        self.with_format(format::Xml {
+            root: None,
+            scalar_attributes: false,
+        })
+    }
+}
+
+#[cfg(feature = "format-xml")]
+impl<T, F, A> VowBuilder<T, F, A, format::Xml> {
+    /// Name the root element `root` instead of using the name of the type.
+    ///
+    /// The name of the root element is not checked when reading.
+    #[must_use]
+    pub const fn root(mut self, root: &'static str) -> Self {
+        self.format.root = Some(root);
+        self
+    }
+
+    /// Write every field holding a single value as an attribute, without needing a `@` rename.
+    ///
+    /// Fields are accepted both as attributes and as child elements when reading, so `@` renames
+    /// should not be used in this mode.
+    #[must_use]
+    pub const fn scalar_attributes(mut self) -> Self {
+        self.format.scalar_attributes = true;
+        self
+    }
+}
+
+#[cfg(feature = "format-ini")]
+impl<T, F, A, Fo> VowBuilder<T, F, A, Fo> {
+    /// Output the data in INI format.
+    ///
+    /// The value must be a struct or map: its scalar fields are written as `key = value` at the
+    /// top, and each nested struct or map as a `[section]` of scalars. Sequences and deeper
+    /// nesting can't be represented and fail to serialize.
+    #[must_use]
+    #[cfg(feature = "format-ini")]
+    pub fn ini(self) -> VowBuilder<T, F, A, format::Ini> {
+        self.with_format(format::Ini {})
+    }
+}
+
+#[cfg(feature = "format-properties")]
+impl<T, F, A, Fo> VowBuilder<T, F, A, Fo> {
+    /// Output the data as Java `.properties`.
+    ///
+    /// The value must be a struct or map. Nested structs and maps are flattened into dotted
+    /// keys (`server.port=80`), so keys themselves can't contain `.`. Sequences can't be
+    /// represented and fail to serialize.
+    #[must_use]
+    #[cfg(feature = "format-properties")]
+    pub fn properties(self) -> VowBuilder<T, F, A, format::Properties> {
+        self.with_format(format::Properties {})
+    }
+}
+
+#[cfg(feature = "format-dotenv")]
+impl<T, F, A, Fo> VowBuilder<T, F, A, Fo> {
+    /// Output the data as a `.env` file of `NAME=value` lines.
+    ///
+    /// The value must be a struct or map of scalars. Values are quoted and escaped as needed,
+    /// and variables in them are never expanded.
+    #[must_use]
+    #[cfg(feature = "format-dotenv")]
+    pub fn dotenv(self) -> VowBuilder<T, F, A, format::Dotenv> {
+        self.with_format(format::Dotenv {
+            prefix: None,
+            uppercase: false,
+        })
+    }
+}
+
+#[cfg(feature = "format-dotenv")]
+impl<T, F, A> VowBuilder<T, F, A, format::Dotenv> {
+    /// Prepend `prefix` to every name, e.g. `APP_`.
+    ///
+    /// When reading, names that don't start with `prefix` are ignored, so the file can be
+    /// shared with other variables.
+    #[must_use]
+    pub const fn prefix(mut self, prefix: &'static str) -> Self {
+        self.format.prefix = Some(prefix);
+        self
+    }
+
+    /// Write names in upper case, e.g. `database_url` as `DATABASE_URL`.
+    ///
+    /// Names are lowercased when reading, so field names must be lower case for this to round
+    /// trip.
+    #[must_use]
+    pub const fn uppercase(mut self) -> Self {
+        self.format.uppercase = true;
+        self
+    }
+}
+
+#[cfg(feature = "format-csv")]
+impl<T, F, A, Fo> VowBuilder<T, F, A, Fo> {
+    /// Output the data as CSV with a header row, for a `Vec` (or any sequence) of rows.
+    ///
+    /// Columns are named after the fields of the rows and matched by name when reading, and
+    /// `None` is written as an empty field. Fields must be scalars. Every malformed row is
+    /// reported with its line in [`Error::CsvDecode`](crate::Error::CsvDecode).
+    #[must_use]
+    #[cfg(feature = "format-csv")]
+    pub fn csv(self) -> VowBuilder<T, F, A, format::Csv> {
+        self.with_format(format::Csv {
+            delimiter: b',',
+            quote: b'"',
+            quoting: format::Quoting::Necessary,
+        })
+    }
+}
+
+#[cfg(feature = "format-kdl")]
+impl<T, F, A, Fo> VowBuilder<T, F, A, Fo> {
+    /// Output the data as a KDL document, for a struct, map or sequence.
+    ///
+    /// Each field is a node named after it:
+    ///
+    /// - scalars are the single argument of the node (`port 8080`), and `None` is `#null`;
+    /// - sequences of scalars are the arguments of the node (`tags a b`), other sequences are
+    ///   children nodes named `-`;
+    /// - nested structs and maps are children (`server { port 8080 }`);
+    /// - unit enum variants are strings, other variants a single child named after the
+    ///   variant (`shape { circle 1.5 }`).
+    ///
+    /// Properties (`server port=8080`) are also accepted when reading a struct or map.
+    #[must_use]
+    #[cfg(feature = "format-kdl")]
+    pub fn kdl(self) -> VowBuilder<T, F, A, format::Kdl> {
+        self.with_format(format::Kdl {})
+    }
+}
+
+#[cfg(feature = "format-raw")]
+impl<T, F, A, Fo> VowBuilder<T, F, A, Fo> {
+    /// Store the value verbatim as text, for a `String` (e.g. a token or a license key) or a
+    /// scalar (e.g. a PID), which is parsed ignoring surrounding whitespace.
+    ///
+    /// The file must be valid UTF-8. An empty file is treated as missing, so the default value
+    /// is written in its place.
+    #[must_use]
+    #[cfg(feature = "format-raw")]
+    pub fn text(self) -> VowBuilder<T, F, A, format::Text> {
+        self.with_format(format::Text {
+            line_ending: format::LineEnding::Preserve,
+            strip_newline: false,
+        })
+    }
+
+    /// Store the value verbatim as bytes, for a `Vec<u8>`, `[u8; N]` or a string.
+    ///
+    /// An empty file is treated as missing, so the default value is written in its place.
+    #[must_use]
+    #[cfg(feature = "format-raw")]
+    pub fn bytes(self) -> VowBuilder<T, F, A, format::Bytes> {
+        self.with_format(format::Bytes {})
+    }
+}
+
+#[cfg(feature = "format-raw")]
+impl<T, F, A> VowBuilder<T, F, A, format::Text> {
+    /// Drop one trailing line ending when reading, e.g. from `echo token > file`.
+    ///
+    /// Combine with [`trailing_newline`](Self::trailing_newline) to write it back.
+    #[must_use]
+    pub const fn strip_newline(mut self) -> Self {
+        self.format.strip_newline = true;
+        self
+    }
+
+    /// Convert line endings. Defaults to [`LineEnding::Preserve`](crate::LineEnding::Preserve).
+    #[must_use]
+    pub const fn line_ending(mut self, line_ending: format::LineEnding) -> Self {
+        self.format.line_ending = line_ending;
+        self
+    }
+}
+
+#[cfg(feature = "format-csv")]
+impl<T, F, A> VowBuilder<T, F, A, format::Csv> {
+    /// Separate fields with `delimiter` instead of `,`, e.g. `b';'` or `b'\t'`.
+    #[must_use]
+    pub const fn delimiter(mut self, delimiter: u8) -> Self {
+        self.format.delimiter = delimiter;
+        self
+    }
+
+    /// Quote fields with `quote` instead of `"`.
+    #[must_use]
+    pub const fn quote(mut self, quote: u8) -> Self {
+        self.format.quote = quote;
+        self
+    }
+
+    /// Choose which fields are quoted when writing. Defaults to [`Quoting::Necessary`](crate::Quoting::Necessary).
+    #[must_use]
+    pub const fn quoting(mut self, quoting: format::Quoting) -> Self {
+        self.format.quoting = quoting;
+        self
+    }
+}
+
+#[cfg(feature = "format-cbor")]
+impl<T, F, A, Fo> VowBuilder<T, F, A, Fo> {
+    /// Output the data in CBOR format.
+    #[must_use]
+    #[cfg(feature = "format-cbor")]
+    pub fn cbor(self) -> VowBuilder<T, F, A, format::Cbor> {
+        self.with_format(format::Cbor {
+            self_describe: false,
+        })
+    }
+}
+
+#[cfg(feature = "format-cbor")]
+impl<T, F, A> VowBuilder<T, F, A, format::Cbor> {
+    /// Prefix the CBOR output with the self-describe tag (55799).
+    ///
+    /// The prefix is always accepted when reading, regardless of this option.
+    #[must_use]
+    pub const fn self_describe(mut self) -> Self {
+        self.format.self_describe = true;
+        self
+    }
+}
+
+#[cfg(feature = "format-postcard")]
+impl<T, F, A, Fo> VowBuilder<T, F, A, Fo> {
+    /// Output the data in postcard format.
+    ///
+    /// The payload is prefixed with its length and CRC-32 checksum, so truncated or corrupted
+    /// files are reported as invalid data instead of decoding into a wrong value.
+    #[must_use]
+    #[cfg(feature = "format-postcard")]
+    pub fn postcard(self) -> VowBuilder<T, F, A, format::Postcard> {
+        self.with_format(format::Postcard {})
     }
 }
 
@@ -209,21 +1093,112 @@ where
     T: ToOption,
     F: VowFileAsync,
     T::Some: Serialize + DeserializeOwned,
-    Fo: ToFormat,
+    Fo: ResolveFormat,
 {
     /// Build the [`VowAsync`] instance.
     pub async fn build(self) -> VowResult<VowAsync<T::Some, F>> {
+        let path = self.file.path().map(Path::to_path_buf);
+        self.bind(path.as_deref()).await.in_file(path.as_deref())
+    }
+
+    async fn bind(self, path: Option<&Path>) -> VowResult<VowAsync<T::Some, F>> {
+        let format = self.format.resolve(path)?;
+        if let Some(format) = format {
+            self.options.check(format)?;
+        }
+        let (default, hooks) = self.default.into_parts();
+        let migration = match &self.migrate {
+            Some(migration) => migration
+                .pending_async::<F>(path)
+                .await?
+                .map(|format| (migration, format)),
+            None => None,
+        };
+        let legacy = match migration {
+            Some((migration, format)) => {
+                let file = F::open(&migration.from)
+                    .await.during(Operation::Open)
+                    .in_file(Some(&migration.from))?;
+                let mut legacy = Io::<F, Async>::new(file, format, self.options, true);
+                legacy.path = Some(migration.from.clone());
+                let value = legacy.sync(None, false, &hooks).await;
+                Some(value.in_file(legacy.path.as_deref())?)
+            }
+            None => None,
+        };
+        // Only verified, so never written
+        #[cfg(feature = "sign")]
+        let read_only = matches!(self.sign, Some(crate::sign::Signer::Verify(_)));
+        #[cfg(not(feature = "sign"))]
+        let read_only = false;
         let mut io = Io::new(
-            self.file.open().await?,
-            self.format.to_format(),
+            self.file
+                .open(read_only)
+                .await
+                .during(Operation::Open)?,
+            // Replaced by the sniffed format below
+            format.unwrap_or_else(|| DefaultFormat::default().to_format()),
+            self.options,
             self.deny_invalid,
         );
-        let default = self.default.maybe();
-        let value = io.sync(default, self.overwrite).await?;
+        io.path = path.map(Path::to_path_buf);
+        #[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+        {
+            io.compression = self.compression;
+        }
+        #[cfg(feature = "encrypt")]
+        {
+            io.cipher = self.encrypt.map(crate::encrypt::Cipher::new);
+        }
+        #[cfg(feature = "envelope")]
+        {
+            io.envelope = self
+                .envelope
+                .as_ref()
+                .map(crate::envelope::Envelope::new::<T::Some>);
+        }
+        #[cfg(feature = "sign")]
+        {
+            io.signer = self.sign;
+        }
+        #[cfg(feature = "versioning")]
+        if let Some(mut versioning) = self.versioning {
+            versioning.resolve_backup(path)?;
+            io.versioning = Some(std::sync::Arc::new(versioning));
+        }
+        #[cfg(feature = "merge")]
+        {
+            io.merge = self.merge.zip(default.as_ref()).and_then(|(merge, default)| {
+                Some((merge, std::sync::Arc::new(serde_value::to_value(default).ok()?)))
+            });
+        }
+        #[cfg(feature = "preserve")]
+        {
+            io.preserve = self.preserve.then(Vec::new);
+        }
+        #[cfg(feature = "unknown-keys")]
+        {
+            io.deny_unknown = self.deny_unknown;
+        }
+        #[cfg(feature = "schema")]
+        {
+            self.schema.write_file_async::<F>().await?;
+            io.schema = self.schema.bind()?;
+        }
+        if format.is_none() {
+            io.sniff::<T::Some>(path).await?;
+            self.options.check(io.format)?;
+        }
+        let overwrite = self.overwrite || legacy.is_some();
+        let value = io.sync(legacy.or(default), overwrite, &hooks).await?;
+        if let Some((migration, _)) = migration {
+            migration.finish_async::<F>().await?;
+        }
 
         Ok(VowAsync {
             value: Some(value),
             io,
+            hooks,
         })
     }
 }
@@ -233,21 +1208,239 @@ where
     T: ToOption,
     F: VowFile,
     T::Some: Serialize + DeserializeOwned,
-    Fo: ToFormat,
+    Fo: ResolveFormat,
 {
     /// Build the [`Vow`] instance.
     pub fn build(self) -> VowResult<Vow<T::Some, F>> {
+        let path = self.file.path().map(Path::to_path_buf);
+        self.bind(path.as_deref()).in_file(path.as_deref())
+    }
+
+    fn bind(self, path: Option<&Path>) -> VowResult<Vow<T::Some, F>> {
+        let format = self.format.resolve(path)?;
+        if let Some(format) = format {
+            self.options.check(format)?;
+        }
+        let (default, hooks) = self.default.into_parts();
+        let migration = match &self.migrate {
+            Some(migration) => migration
+                .pending(path)?
+                .map(|format| (migration, format)),
+            None => None,
+        };
+        let legacy = match migration {
+            Some((migration, format)) => {
+                let file = F::open(&migration.from)
+                    .during(Operation::Open)
+                    .in_file(Some(&migration.from))?;
+                let mut legacy = Io::<F, Blocking>::new(file, format, self.options, true);
+                legacy.path = Some(migration.from.clone());
+                let value = legacy.sync(None, false, &hooks);
+                Some(value.in_file(legacy.path.as_deref())?)
+            }
+            None => None,
+        };
+        // Only verified, so never written
+        #[cfg(feature = "sign")]
+        let read_only = matches!(self.sign, Some(crate::sign::Signer::Verify(_)));
+        #[cfg(not(feature = "sign"))]
+        let read_only = false;
         let mut io = Io::new(
-            self.file.open()?,
-            self.format.to_format(),
+            self.file
+                .open(read_only)
+                .during(Operation::Open)?,
+            // Replaced by the sniffed format below
+            format.unwrap_or_else(|| DefaultFormat::default().to_format()),
+            self.options,
             self.deny_invalid,
         );
-        let default = self.default.maybe();
-        let value = io.sync(default, self.overwrite)?;
+        io.path = path.map(Path::to_path_buf);
+        #[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+        {
+            io.compression = self.compression;
+        }
+        #[cfg(feature = "encrypt")]
+        {
+            io.cipher = self.encrypt.map(crate::encrypt::Cipher::new);
+        }
+        #[cfg(feature = "envelope")]
+        {
+            io.envelope = self
+                .envelope
+                .as_ref()
+                .map(crate::envelope::Envelope::new::<T::Some>);
+        }
+        #[cfg(feature = "sign")]
+        {
+            io.signer = self.sign;
+        }
+        #[cfg(feature = "versioning")]
+        if let Some(mut versioning) = self.versioning {
+            versioning.resolve_backup(path)?;
+            io.versioning = Some(std::sync::Arc::new(versioning));
+        }
+        #[cfg(feature = "merge")]
+        {
+            io.merge = self.merge.zip(default.as_ref()).and_then(|(merge, default)| {
+                Some((merge, std::sync::Arc::new(serde_value::to_value(default).ok()?)))
+            });
+        }
+        #[cfg(feature = "preserve")]
+        {
+            io.preserve = self.preserve.then(Vec::new);
+        }
+        #[cfg(feature = "unknown-keys")]
+        {
+            io.deny_unknown = self.deny_unknown;
+        }
+        #[cfg(feature = "schema")]
+        {
+            self.schema.write_file()?;
+            io.schema = self.schema.bind()?;
+        }
+        if format.is_none() {
+            io.sniff::<T::Some>(path)?;
+            self.options.check(io.format)?;
+        }
+        let overwrite = self.overwrite || legacy.is_some();
+        let value = io.sync(legacy.or(default), overwrite, &hooks)?;
+        if let Some((migration, _)) = migration {
+            migration.finish()?;
+        }
 
         Ok(Vow {
             value: Some(value),
             io,
+            hooks,
         })
     }
 }
+
+#[cfg(all(test, feature = "format-json", feature = "format-toml"))]
+mod test {
+    use serde::{Deserialize, Serialize};
+
+    use crate::{ErrorKind, LegacyFile, Vow};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Data {
+        value: u8,
+    }
+
+    #[test]
+    fn test_migrate_from() {
+        let dir = std::env::temp_dir().join("vow-migrate");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let (path, legacy, backup) = (
+            dir.join("config.toml"),
+            dir.join("config.json"),
+            dir.join("config.json.bak"),
+        );
+        let open = |legacy_file| {
+            Vow::open(&path)
+                .toml()
+                .default(Data { value: 0 })
+                .migrate_from(&legacy, legacy_file)
+                .build()
+        };
+
+        std::fs::write(&legacy, b"{\"value\":1}").unwrap();
+        assert_eq!(*open(LegacyFile::Rename(backup.clone())).unwrap(), Data { value: 1 });
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "value = 1\n");
+        assert!(!legacy.exists());
+        assert!(backup.exists());
+
+        // Only migrated while the file is missing
+        std::fs::write(&legacy, b"{\"value\":2}").unwrap();
+        assert_eq!(*open(LegacyFile::Remove).unwrap(), Data { value: 1 });
+        assert!(legacy.exists());
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(*open(LegacyFile::Remove).unwrap(), Data { value: 2 });
+        assert!(!legacy.exists());
+
+        // Invalid legacy content is reported, and nothing is touched
+        std::fs::remove_file(&path).unwrap();
+        std::fs::write(&legacy, b"{\"value\":").unwrap();
+        assert!(open(LegacyFile::Remove).err().unwrap().is_invalid_data());
+        assert!(legacy.exists());
+        assert!(!path.exists());
+    }
+
+    #[cfg(feature = "backend-tokio")]
+    #[tokio::test]
+    async fn test_migrate_from_async() {
+        let dir = std::env::temp_dir().join("vow-migrate-async");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let (path, legacy, backup) = (
+            dir.join("config.toml"),
+            dir.join("config.json"),
+            dir.join("config.json.bak"),
+        );
+
+        std::fs::write(&legacy, b"{\"value\":1}").unwrap();
+        let mut vow = crate::VowAsync::open_tokio(&path)
+            .toml()
+            .default(Data { value: 0 })
+            .migrate_from(&legacy, LegacyFile::Rename(backup.clone()))
+            .build()
+            .await
+            .unwrap();
+        vow.flush().await.unwrap();
+        assert_eq!(*vow, Data { value: 1 });
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "value = 1\n");
+        assert!(!legacy.exists());
+        assert!(backup.exists());
+    }
+
+    #[test]
+    fn test_toml_options() {
+        let path = std::env::temp_dir().join("vow-toml-options.toml");
+        let _ = std::fs::remove_file(&path);
+        let open = || Vow::open(&path).toml().default(Data { value: 0 });
+
+        for vow in [open().pretty(), open().sort_keys(), open().pretty_arrays()] {
+            let err = vow.build().err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::UnsupportedOption);
+            assert!(!path.exists());
+        }
+        assert_eq!(*open().trailing_newline().build().unwrap(), Data { value: 0 });
+    }
+
+    #[test]
+    fn test_unsupported_options() {
+        let path = std::env::temp_dir().join("vow-unsupported-options.json");
+        let _ = std::fs::remove_file(&path);
+        let err = Vow::open(&path)
+            .json()
+            .pretty_arrays()
+            .default(Data { value: 0 })
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::UnsupportedOption);
+        assert_eq!(
+            err.inner().to_string(),
+            "Unsupported option: `json` doesn't support `pretty_arrays`"
+        );
+        assert!(!path.exists());
+
+        // Picked at runtime
+        #[cfg(feature = "format-ini")]
+        {
+            let path = path.with_extension("ini");
+            let _ = std::fs::remove_file(&path);
+            let err = Vow::open(&path)
+                .auto_format()
+                .pretty()
+                .default(Data { value: 0 })
+                .build()
+                .err()
+                .unwrap();
+            assert_eq!(err.kind(), ErrorKind::UnsupportedOption);
+            assert!(!path.exists());
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use std::{borrow::Cow, io};
+
+use crate::VowResult;
+
+/// Compression of the file, applied on top of the format.
+///
+/// When reading, compressed content is detected from its magic bytes, so any enabled
+/// compression is accepted, as well as uncompressed content. This allows turning compression
+/// on, or switching algorithms, for existing files. With [`Compression::None`], the content is
+/// always read as is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+pub enum Compression {
+    /// Store the content as is.
+    #[default]
+    None,
+    /// Gzip, with a level from 0 (none) to 9 (best).
+    #[cfg(feature = "compress-gzip")]
+    Gzip {
+        /// Compression level
+        level: u32,
+    },
+    /// Zstandard, with a level from 1 to 22, or 0 for the default level.
+    #[cfg(feature = "compress-zstd")]
+    Zstd {
+        /// Compression level
+        level: i32,
+    },
+}
+
+impl Compression {
+    /// Gzip with the default level (6).
+    #[cfg(feature = "compress-gzip")]
+    #[must_use]
+    pub const fn gzip() -> Self {
+        Self::Gzip { level: 6 }
+    }
+
+    /// Zstandard with the default level (3).
+    #[cfg(feature = "compress-zstd")]
+    #[must_use]
+    pub const fn zstd() -> Self {
+        Self::Zstd { level: 3 }
+    }
+}
+
+#[cfg(feature = "compress-gzip")]
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+#[cfg(feature = "compress-zstd")]
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Decompress `buf` if it starts with the magic bytes of an enabled compression.
+pub fn decompress(buf: &[u8], compression: Compression) -> VowResult<Cow<'_, [u8]>> {
+    if compression == Compression::None {
+        return Ok(Cow::Borrowed(buf));
+    }
+    #[cfg(feature = "compress-gzip")]
+    if buf.starts_with(&GZIP_MAGIC) {
+        let mut out = Vec::with_capacity(buf.len() * 4);
+        io::Read::read_to_end(&mut flate2::read::GzDecoder::new(buf), &mut out)
+            .map_err(crate::Error::Decompress)?;
+        return Ok(Cow::Owned(out));
+    }
+    #[cfg(feature = "compress-zstd")]
+    if buf.starts_with(&ZSTD_MAGIC) {
+        let out = zstd::decode_all(buf).map_err(crate::Error::Decompress)?;
+        return Ok(Cow::Owned(out));
+    }
+    Ok(Cow::Borrowed(buf))
+}
+
+/// Compress `buf` with `compression`.
+pub fn compress(buf: Vec<u8>, compression: Compression) -> io::Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(buf),
+        #[cfg(feature = "compress-gzip")]
+        Compression::Gzip { level } => {
+            let out = Vec::with_capacity(buf.len() / 4);
+            let mut encoder = flate2::write::GzEncoder::new(out, flate2::Compression::new(level));
+            io::Write::write_all(&mut encoder, &buf)?;
+            encoder.finish()
+        }
+        #[cfg(feature = "compress-zstd")]
+        Compression::Zstd { level } => zstd::encode_all(buf.as_slice(), level),
+    }
+}
+
+#[cfg(all(test, feature = "format-json"))]
+mod test {
+    use serde::{Deserialize, Serialize};
+
+    use super::{compress, decompress, Compression};
+    use crate::Vow;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Data {
+        values: Vec<u32>,
+    }
+
+    fn data() -> Data {
+        Data {
+            values: (0..1000).collect(),
+        }
+    }
+
+    fn compressions() -> Vec<Compression> {
+        vec![
+            #[cfg(feature = "compress-gzip")]
+            Compression::gzip(),
+            #[cfg(feature = "compress-gzip")]
+            Compression::Gzip { level: 9 },
+            #[cfg(feature = "compress-zstd")]
+            Compression::zstd(),
+            #[cfg(feature = "compress-zstd")]
+            Compression::Zstd { level: 19 },
+        ]
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let plain = serde_json::to_vec(&data()).unwrap();
+        for compression in compressions() {
+            let compressed = compress(plain.clone(), compression).unwrap();
+            assert!(compressed.len() * 2 < plain.len(), "{compression:?}");
+            // Every enabled compression is detected, whichever is configured
+            for other in compressions() {
+                assert_eq!(decompress(&compressed, other).unwrap(), plain);
+            }
+            assert_eq!(decompress(&plain, compression).unwrap(), plain);
+            assert_eq!(
+                decompress(&compressed, Compression::None).unwrap(),
+                compressed
+            );
+        }
+    }
+
+    #[test]
+    fn test_vow() {
+        let path = std::env::temp_dir().join("vow-compress.json");
+        for compression in compressions() {
+            std::fs::write(&path, serde_json::to_vec(&data()).unwrap()).unwrap();
+            let mut vow = Vow::open(&path)
+                .compress(compression)
+                .default(Data { values: vec![] })
+                .build()
+                .unwrap();
+            // The plain file is read, and compressed on the next write
+            assert_eq!(*vow, data());
+            vow.update(|data| data.values.push(0)).unwrap();
+            let file = std::fs::read(&path).unwrap();
+            assert_ne!(file.first(), Some(&b'{'));
+
+            vow.force_reload().unwrap();
+            assert_eq!(vow.values.len(), 1001);
+
+            // Corrupted content is reported as invalid data
+            let mut file = file;
+            let len = file.len();
+            file.truncate(len / 2);
+            std::fs::write(&path, &file).unwrap();
+            let err = vow.force_reload().unwrap_err();
+            assert!(err.is_invalid_data(), "{compression:?}: {err}");
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use std::fmt;
+
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+
+use crate::{Error, VowResult};
+
+/// Key used to encrypt the file, see [`VowBuilder::encrypt`](crate::VowBuilder::encrypt).
+#[derive(Clone)]
+pub struct Key(Secret);
+
+#[derive(Clone)]
+enum Secret {
+    Raw([u8; KEY_LEN]),
+    Password(String),
+}
+
+impl Key {
+    /// Use a random 32 bytes key, e.g. from a key management system.
+    #[must_use]
+    pub const fn new(key: [u8; KEY_LEN]) -> Self {
+        Self(Secret::Raw(key))
+    }
+
+    /// Derive the key from a password with Argon2id and a random salt stored in the file.
+    #[must_use]
+    pub fn from_password(password: impl Into<String>) -> Self {
+        Self(Secret::Password(password.into()))
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Secret::Raw(_) => f.write_str("Key(..)"),
+            Secret::Password(_) => f.write_str("Key(password)"),
+        }
+    }
+}
+
+const MAGIC: &[u8; 4] = b"VOWE";
+const VERSION: u8 = 1;
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// How the key is obtained, stored in the header.
+const KDF_NONE: u8 = 0;
+const KDF_ARGON2ID: u8 = 1;
+
+/// Encrypts the content with XChaCha20-Poly1305, behind a header made of:
+///
+/// - the magic bytes `VOWE` and a version byte;
+/// - the key derivation (`0` for a raw key, `1` for Argon2id), followed by the salt for
+///   Argon2id;
+/// - a random nonce, new for every write.
+///
+/// The header is authenticated along with the content.
+#[derive(Clone)]
+pub(crate) struct Cipher {
+    key: Key,
+    /// Salt and key last derived from the password, reused for writes
+    derived: Option<([u8; SALT_LEN], [u8; KEY_LEN])>,
+}
+
+impl fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cipher")
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
+
+fn decrypt_error(msg: &str) -> Error {
+    Error::Decrypt(msg.to_owned())
+}
+
+impl Cipher {
+    pub const fn new(key: Key) -> Self {
+        Self { key, derived: None }
+    }
+
+    /// The key for `salt` when decrypting, or for a new random salt if `None` when encrypting.
+    fn derive(
+        &mut self,
+        salt: Option<[u8; SALT_LEN]>,
+    ) -> VowResult<([u8; SALT_LEN], [u8; KEY_LEN])> {
+        let Secret::Password(password) = &self.key.0 else {
+            unreachable!("only passwords are derived")
+        };
+        match (self.derived, salt) {
+            (Some(derived), None) => return Ok(derived),
+            (Some(derived), Some(salt)) if derived.0 == salt => return Ok(derived),
+            _ => {}
+        }
+        let encrypting = salt.is_none();
+        let salt = salt.unwrap_or_else(|| {
+            let mut salt = [0; SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+            salt
+        });
+        let mut key = [0; KEY_LEN];
+        argon2::Argon2::default()
+            .hash_password_into(password.as_bytes(), &salt, &mut key)
+            .map_err(|err| {
+                let msg = format!("can't derive the key: {err}");
+                if encrypting {
+                    Error::Encrypt(msg)
+                } else {
+                    Error::Decrypt(msg)
+                }
+            })?;
+        self.derived = Some((salt, key));
+        Ok((salt, key))
+    }
+
+    pub fn encrypt(&mut self, plain: &[u8]) -> VowResult<Vec<u8>> {
+        let mut out = Vec::with_capacity(plain.len() + 64);
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        let key = match self.key.0 {
+            Secret::Raw(key) => {
+                out.push(KDF_NONE);
+                key
+            }
+            Secret::Password(_) => {
+                let (salt, key) = self.derive(None)?;
+                out.push(KDF_ARGON2ID);
+                out.extend_from_slice(&salt);
+                key
+            }
+        };
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        out.extend_from_slice(&nonce);
+
+        let encrypted = XChaCha20Poly1305::new(&key.into())
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plain,
+                    aad: &out,
+                },
+            )
+            .map_err(|_| Error::Encrypt("the content is too large".to_owned()))?;
+        out.extend_from_slice(&encrypted);
+        Ok(out)
+    }
+
+    /// Decrypt `buf`. Empty content is returned as is, so that a new file is reported as
+    /// invalid data by the format and receives the default value.
+    pub fn decrypt(&mut self, buf: &[u8]) -> VowResult<Vec<u8>> {
+        if buf.is_empty() {
+            return Ok(vec![]);
+        }
+        let rest = buf
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| decrypt_error("the file is not encrypted"))?;
+        let (&version, rest) = rest
+            .split_first()
+            .ok_or_else(|| decrypt_error("truncated header"))?;
+        if version != VERSION {
+            return Err(Error::Decrypt(format!("unsupported version {version}")));
+        }
+        let (&kdf, rest) = rest
+            .split_first()
+            .ok_or_else(|| decrypt_error("truncated header"))?;
+
+        let (key, rest) = match (kdf, &self.key.0) {
+            (KDF_NONE, Secret::Raw(key)) => (*key, rest),
+            (KDF_ARGON2ID, Secret::Password(_)) => {
+                let (salt, rest) = rest
+                    .split_first_chunk::<SALT_LEN>()
+                    .ok_or_else(|| decrypt_error("truncated header"))?;
+                (self.derive(Some(*salt))?.1, rest)
+            }
+            (KDF_NONE, Secret::Password(_)) => {
+                return Err(decrypt_error(
+                    "the file is encrypted with a key, not a password",
+                ))
+            }
+            (KDF_ARGON2ID, Secret::Raw(_)) => {
+                return Err(decrypt_error(
+                    "the file is encrypted with a password, not a key",
+                ))
+            }
+            (kdf, _) => return Err(Error::Decrypt(format!("unsupported key derivation {kdf}"))),
+        };
+        let (nonce, encrypted) = rest
+            .split_first_chunk::<NONCE_LEN>()
+            .ok_or_else(|| decrypt_error("truncated header"))?;
+        let header = &buf[..buf.len() - encrypted.len()];
+
+        XChaCha20Poly1305::new(&key.into())
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: encrypted,
+                    aad: header,
+                },
+            )
+            .map_err(|_| decrypt_error("wrong key, or the file was tampered with"))
+    }
+}
+
+#[cfg(all(test, feature = "format-json"))]
+mod test {
+    use serde::{Deserialize, Serialize};
+
+    use super::{Cipher, Key};
+    use crate::{ErrorKind, Vow};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Secrets {
+        token: String,
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for key in [Key::new([7; 32]), Key::from_password("hunter2")] {
+            let mut cipher = Cipher::new(key.clone());
+            let first = cipher.encrypt(b"secret").unwrap();
+            let second = cipher.encrypt(b"secret").unwrap();
+            // A new nonce for every write
+            assert_ne!(first, second);
+            assert!(!first.windows(6).any(|w| w == b"secret"));
+
+            // A fresh cipher reads it, deriving the key from the salt in the header
+            let mut cipher = Cipher::new(key);
+            assert_eq!(cipher.decrypt(&first).unwrap(), b"secret");
+            assert_eq!(cipher.decrypt(&second).unwrap(), b"secret");
+            assert_eq!(cipher.decrypt(b"").unwrap(), b"");
+        }
+    }
+
+    #[test]
+    fn test_decrypt_errors() {
+        let mut cipher = Cipher::new(Key::new([7; 32]));
+        let encrypted = cipher.encrypt(b"secret").unwrap();
+
+        let mut wrong_key = Cipher::new(Key::new([8; 32]));
+        let mut tampered = encrypted.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        let mut tampered_header = encrypted.clone();
+        tampered_header[10] ^= 1;
+
+        for (err, msg) in [
+            (
+                wrong_key.decrypt(&encrypted),
+                "wrong key, or the file was tampered with",
+            ),
+            (
+                cipher.decrypt(&tampered),
+                "wrong key, or the file was tampered with",
+            ),
+            (
+                cipher.decrypt(&tampered_header),
+                "wrong key, or the file was tampered with",
+            ),
+            (cipher.decrypt(b"{}"), "the file is not encrypted"),
+            (cipher.decrypt(&encrypted[..8]), "truncated header"),
+            (
+                Cipher::new(Key::from_password("p")).decrypt(&encrypted),
+                "the file is encrypted with a key, not a password",
+            ),
+        ] {
+            let err = err.unwrap_err();
+            assert!(!err.is_invalid_data());
+            assert_eq!(err.to_string(), format!("Decryption error: {msg}"));
+        }
+    }
+
+    #[test]
+    fn test_vow() {
+        let path = std::env::temp_dir().join("vow-encrypt.json");
+        let _ = std::fs::remove_file(&path);
+        let open = |key| {
+            Vow::open(&path)
+                .encrypt(key)
+                .default(Secrets {
+                    token: "default".to_owned(),
+                })
+                .build()
+        };
+
+        let mut vow = open(Key::new([1; 32])).unwrap();
+        vow.set(Secrets {
+            token: "s3cr3t".to_owned(),
+        })
+        .unwrap();
+        assert_eq!(open(Key::new([1; 32])).unwrap().token, "s3cr3t");
+
+        // Never overwritten with the default value when it can't be decrypted
+        let encrypted = std::fs::read(&path).unwrap();
+        let err = open(Key::new([2; 32])).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::Decrypt);
+        std::fs::write(&path, b"{\"token\":\"plain\"}").unwrap();
+        assert_eq!(
+            open(Key::new([1; 32])).err().unwrap().kind(),
+            ErrorKind::Decrypt
+        );
+        assert_eq!(std::fs::read(&path).unwrap(), b"{\"token\":\"plain\"}");
+
+        std::fs::write(&path, &encrypted).unwrap();
+        assert_eq!(open(Key::new([1; 32])).unwrap().token, "s3cr3t");
+    }
+
+    #[test]
+    fn test_redacted() {
+        #[derive(Debug, Serialize, Deserialize)]
+        struct Server {
+            port: u16,
+        }
+
+        let key = Key::new([2; 32]);
+        for (name, plain) in [
+            ("json", &b"{\"port\": \"hunter2\"}"[..]),
+            #[cfg(feature = "format-toml")]
+            ("toml", b"port = \"hunter2\"\n"),
+            #[cfg(feature = "format-toml")]
+            ("toml", b"port = hunter2\n"),
+        ] {
+            let path = std::env::temp_dir().join(format!("vow-encrypt-redacted.{name}"));
+            let content = Cipher::new(key.clone()).encrypt(plain).unwrap();
+            std::fs::write(&path, content).unwrap();
+            let err = Vow::open(&path)
+                .auto_format()
+                .encrypt(key.clone())
+                .deny_invalid()
+                .default(Server { port: 80 })
+                .build()
+                .err()
+                .unwrap();
+
+            assert_eq!(err.kind(), ErrorKind::Decode);
+            assert!(err.is_invalid_data());
+            assert_eq!(err.line(), Some(1));
+            assert!(!err.to_string().contains("hunter2"), "{err}");
+            assert!(!format!("{err:?}").contains("hunter2"), "{err:?}");
+        }
+    }
+
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    fn test_render() {
+        let path = std::env::temp_dir().join("vow-encrypt-render.json");
+        let key = Key::new([1; 32]);
+        let content = Cipher::new(key.clone())
+            .encrypt(b"{\n  \"token\": \"s3cr3t\",\n  \"token\": 1\n}")
+            .unwrap();
+        std::fs::write(&path, content).unwrap();
+        let err = Vow::open(&path)
+            .encrypt(key)
+            .deny_invalid()
+            .default(Secrets {
+                token: "default".to_owned(),
+            })
+            .build()
+            .err()
+            .unwrap();
+
+        // Located, but the decrypted content is not shown
+        assert_eq!(err.line(), Some(3));
+        assert!(!err.render().contains("s3cr3t"), "{}", err.render());
+    }
+}
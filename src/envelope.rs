@@ -0,0 +1,297 @@
+use std::fmt;
+
+use thiserror::Error;
+
+/// Why the envelope around the content was rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum EnvelopeError {
+    /// The file doesn't start with the envelope magic bytes, e.g. it was written without one
+    #[error("the file has no envelope")]
+    Missing,
+    /// The envelope was written by a newer version of this crate
+    #[error("unsupported envelope version {0}")]
+    Version(u8),
+    /// The file is too short to hold the envelope header
+    #[error("truncated header")]
+    Truncated,
+    /// The payload is not as long as recorded in the header
+    #[error("the payload is {found} bytes long, expected {expected}")]
+    Length {
+        /// Length recorded in the header
+        expected: u64,
+        /// Length found in the file
+        found: u64,
+    },
+    /// The payload doesn't match the checksum recorded in the header
+    #[error("checksum mismatch: found {found:08x}, expected {expected:08x}")]
+    Checksum {
+        /// Checksum recorded in the header
+        expected: u32,
+        /// Checksum of the payload found in the file
+        found: u32,
+    },
+    /// The file was written for another type or schema id
+    #[error("the file was written for another type (fingerprint {found:016x}, expected {expected:016x})")]
+    Fingerprint {
+        /// Fingerprint of the type or schema id being read
+        expected: u64,
+        /// Fingerprint recorded in the header
+        found: u64,
+    },
+}
+
+impl EnvelopeError {
+    /// Whether the content is damaged, as opposed to well-formed but not meant for this type.
+    pub(crate) const fn is_corrupted(&self) -> bool {
+        matches!(
+            self,
+            Self::Truncated | Self::Length { .. } | Self::Checksum { .. }
+        )
+    }
+}
+
+/// What identifies the content of the file, see [`VowBuilder::envelope`].
+///
+/// [`VowBuilder::envelope`]: crate::VowBuilder::envelope
+#[derive(Debug, Clone)]
+pub(crate) enum Schema {
+    /// The name of the Rust type
+    Type,
+    /// An id chosen by the user
+    Id(String),
+}
+
+const MAGIC: &[u8; 4] = b"VOW\0";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 25;
+
+/// Wraps the serialized content behind a header made of:
+///
+/// - the magic bytes `VOW\0` and a version byte;
+/// - little-endian `u64` fingerprint of the type or schema id (64-bit FNV-1a);
+/// - little-endian `u64` payload length;
+/// - little-endian `u32` CRC-32 of the payload.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Envelope {
+    fingerprint: u64,
+}
+
+impl fmt::Debug for Envelope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Envelope({:016x})", self.fingerprint)
+    }
+}
+
+const fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+        i += 1;
+    }
+    hash
+}
+
+impl Envelope {
+    pub fn new<T>(schema: &Schema) -> Self {
+        let id = match schema {
+            Schema::Type => std::any::type_name::<T>(),
+            Schema::Id(id) => id,
+        };
+        Self {
+            fingerprint: fnv1a(id.as_bytes()),
+        }
+    }
+
+    /// Wrap `payload` in the envelope.
+    pub fn seal(self, payload: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(HEADER_LEN + payload.len());
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.extend_from_slice(&self.fingerprint.to_le_bytes());
+        out.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        out.extend_from_slice(&crc32fast::hash(payload).to_le_bytes());
+        out.extend_from_slice(payload);
+        out
+    }
+
+    /// Check the envelope of `buf`, returning where the payload starts. Empty content has no
+    /// envelope, so that a new file is reported as invalid data by the format and receives the
+    /// default value.
+    pub fn open(self, buf: &[u8]) -> Result<usize, EnvelopeError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let rest = buf.strip_prefix(MAGIC).ok_or(EnvelopeError::Missing)?;
+        let (&version, rest) = rest.split_first().ok_or(EnvelopeError::Truncated)?;
+        if version != VERSION {
+            return Err(EnvelopeError::Version(version));
+        }
+        let (fingerprint, rest) = rest
+            .split_first_chunk::<8>()
+            .ok_or(EnvelopeError::Truncated)?;
+        let (len, rest) = rest
+            .split_first_chunk::<8>()
+            .ok_or(EnvelopeError::Truncated)?;
+        let (crc, payload) = rest
+            .split_first_chunk::<4>()
+            .ok_or(EnvelopeError::Truncated)?;
+
+        let (len, found) = (u64::from_le_bytes(*len), payload.len() as u64);
+        if len != found {
+            return Err(EnvelopeError::Length {
+                expected: len,
+                found,
+            });
+        }
+        let (crc, found) = (u32::from_le_bytes(*crc), crc32fast::hash(payload));
+        if crc != found {
+            return Err(EnvelopeError::Checksum {
+                expected: crc,
+                found,
+            });
+        }
+        // Checked last, so that damaged content is not mistaken for another type
+        let fingerprint = u64::from_le_bytes(*fingerprint);
+        if fingerprint != self.fingerprint {
+            return Err(EnvelopeError::Fingerprint {
+                expected: self.fingerprint,
+                found: fingerprint,
+            });
+        }
+        Ok(HEADER_LEN)
+    }
+}
+
+#[cfg(all(test, feature = "format-json"))]
+mod test {
+    use serde::{Deserialize, Serialize};
+
+    use super::{Envelope, EnvelopeError, Schema};
+    use crate::{Error, Vow};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Data {
+        value: u8,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Other {
+        value: u8,
+    }
+
+    #[test]
+    fn test_open() {
+        let envelope = Envelope::new::<Data>(&Schema::Type);
+        let sealed = envelope.seal(b"{\"value\":1}");
+        assert_eq!(&sealed[envelope.open(&sealed).unwrap()..], b"{\"value\":1}");
+        assert_eq!(envelope.open(b""), Ok(0));
+
+        let mut flipped = sealed.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+        let mut longer = sealed.clone();
+        longer.push(b' ');
+        let other = Envelope::new::<Other>(&Schema::Type);
+        let (expected, found) = (other.fingerprint, envelope.fingerprint);
+
+        for (buf, open, err) in [
+            (&b"{\"value\":1}"[..], envelope, EnvelopeError::Missing),
+            (&sealed[..10], envelope, EnvelopeError::Truncated),
+            (
+                &sealed[..sealed.len() - 1],
+                envelope,
+                EnvelopeError::Length {
+                    expected: 11,
+                    found: 10,
+                },
+            ),
+            (
+                &longer,
+                envelope,
+                EnvelopeError::Length {
+                    expected: 11,
+                    found: 12,
+                },
+            ),
+            (
+                &sealed,
+                other,
+                EnvelopeError::Fingerprint { expected, found },
+            ),
+        ] {
+            assert_eq!(open.open(buf), Err(err));
+        }
+        assert!(matches!(
+            envelope.open(&flipped),
+            Err(EnvelopeError::Checksum { .. })
+        ));
+
+        // A schema id replaces the type name
+        let schema = Envelope::new::<Data>(&Schema::Id("data-v1".to_owned()));
+        assert_eq!(
+            schema,
+            Envelope::new::<Other>(&Schema::Id("data-v1".to_owned()))
+        );
+        assert_ne!(schema, envelope);
+    }
+
+    #[test]
+    fn test_vow() {
+        let path = std::env::temp_dir().join("vow-envelope.json");
+        let _ = std::fs::remove_file(&path);
+
+        let mut vow = Vow::open(&path)
+            .envelope()
+            .default(Data { value: 0 })
+            .build()
+            .unwrap();
+        vow.set(Data { value: 1 }).unwrap();
+        let sealed = std::fs::read(&path).unwrap();
+        assert!(sealed.ends_with(b"{\"value\":1}"));
+
+        // Written for another type: reported, and left untouched
+        let err = Vow::open(&path)
+            .envelope()
+            .default(Other { value: 0 })
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.inner(),
+            Error::Envelope(EnvelopeError::Fingerprint { .. })
+        ));
+        assert!(!err.is_invalid_data());
+        assert_eq!(std::fs::read(&path).unwrap(), sealed);
+
+        // Truncated: invalid data
+        std::fs::write(&path, &sealed[..sealed.len() - 2]).unwrap();
+        let err = Vow::open(&path)
+            .envelope()
+            .deny_invalid()
+            .default(Data { value: 0 })
+            .build()
+            .err()
+            .unwrap();
+        assert!(err.is_invalid_data());
+        assert_eq!(
+            err.inner().to_string(),
+            "Envelope error: the payload is 9 bytes long, expected 11"
+        );
+
+        // Same schema id, different types
+        Vow::open(&path)
+            .envelope_schema("data")
+            .overwrite_local()
+            .default(Data { value: 2 })
+            .build()
+            .unwrap();
+        let vow = Vow::open(&path)
+            .envelope_schema("data")
+            .default(Other { value: 0 })
+            .build()
+            .unwrap();
+        assert_eq!(*vow, Other { value: 2 });
+    }
+}
@@ -1,4 +1,7 @@
-use std::io;
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
 
 #[cfg(feature = "format-json")]
 use serde_json::error::Category;
@@ -8,12 +11,114 @@ use thiserror::Error;
 ///
 /// [`Vow`]: super::Vow
 /// [`VowAsync`]: super::VowAsync
+///
+/// Errors met while working on a file carry their [`Context`]: the path of the file, the
+/// [`Operation`], the key of the failing value and its line and column, when known. Match on
+/// [`Error::kind`] rather than on the variants, which depend on the enabled features.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum Error {
+    /// An error, with where and when it occurred
+    #[error(transparent)]
+    Context(Box<Context>),
+
     /// Io error
     #[error("Io error: {0}")]
     Io(#[from] io::Error),
 
+    /// The content is compressed, but corrupted
+    #[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+    #[error("Decompression error: {0}")]
+    Decompress(io::Error),
+
+    /// The file can't be decrypted: wrong key, tampered or unencrypted content
+    ///
+    /// This is not considered invalid data, so the file is never overwritten with the default
+    /// value.
+    #[cfg(feature = "encrypt")]
+    #[error("Decryption error: {0}")]
+    Decrypt(String),
+
+    /// The content of an encrypted file can't be read
+    ///
+    /// The error of the format is replaced, since its message may quote the decrypted content:
+    /// only its kind is kept, along with the key, line and column of the [`Context`].
+    #[cfg(feature = "encrypt")]
+    #[error("{0}")]
+    Redacted(Redacted),
+
+    /// The content can't be encrypted, or the key can't be derived from the password to do so
+    #[cfg(feature = "encrypt")]
+    #[error("Encryption error: {0}")]
+    Encrypt(String),
+
+    /// The signature of the file doesn't verify: wrong key, changed or unsigned content
+    ///
+    /// This is not considered invalid data, so the file is never overwritten with the default
+    /// value.
+    #[cfg(feature = "sign")]
+    #[error("Verification error: {0}")]
+    Verify(String),
+
+    /// The file is only verified, and can't be written without the signing key
+    #[cfg(feature = "sign")]
+    #[error("Read-only: the file can't be written without the signing key")]
+    ReadOnly,
+
+    /// The file can't be upgraded to the current schema version
+    ///
+    /// This is not considered invalid data, so the file is never overwritten with the default
+    /// value.
+    #[cfg(feature = "versioning")]
+    #[error("Migration error: {0}")]
+    Migrate(String),
+
+    /// The envelope around the content is damaged, or was written for another type
+    ///
+    /// Only damaged envelopes are considered invalid data: a file written for another type is
+    /// never overwritten with the default value.
+    #[cfg(feature = "envelope")]
+    #[error("Envelope error: {0}")]
+    Envelope(#[from] crate::EnvelopeError),
+
+    /// The content has keys that the value ignored, in strict mode, listed by path
+    ///
+    /// This is not considered invalid data, so the file is never overwritten with the default
+    /// value.
+    #[cfg(feature = "unknown-keys")]
+    #[error("Unknown keys: {}", .0.join(", "))]
+    UnknownKeys(Vec<String>),
+
+    /// The value was rejected by a validation hook, see
+    /// [`VowBuilder::validate`](crate::VowBuilder::validate). The file is left untouched.
+    #[error("Validation error: {0}")]
+    Validate(String),
+
+    /// The content doesn't match the JSON Schema of the value, listing every mismatch
+    ///
+    /// This is not considered invalid data, so the file is never overwritten with the default
+    /// value.
+    #[cfg(feature = "schema")]
+    #[error(
+        "Schema error: {}",
+        .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+    )]
+    Schema(Vec<crate::SchemaError>),
+
+    /// The format couldn't be picked from the extension or the content of the file
+    #[error("Unknown format: {0}")]
+    UnknownFormat(String),
+
+    /// An output option was set for a format that doesn't support it, see
+    /// [`FormatOptions`](crate::FormatOptions)
+    #[error("Unsupported option: `{format}` doesn't support `{option}`")]
+    UnsupportedOption {
+        /// Name of the format, e.g. `toml`
+        format: &'static str,
+        /// Name of the option, e.g. `indent`
+        option: &'static str,
+    },
+
     /// Serde json error
     #[cfg(feature = "format-json")]
     #[error("Json error: {0}")]
@@ -23,14 +128,584 @@ pub enum Error {
     #[cfg(feature = "format-toml")]
     #[error("Toml error: {0}")]
     Toml(#[from] basic_toml::Error),
+
+    /// Ciborium decode error
+    #[cfg(feature = "format-cbor")]
+    #[error("Cbor decode error: {0}")]
+    CborDecode(#[from] ciborium::de::Error<io::Error>),
+
+    /// Ciborium encode error
+    #[cfg(feature = "format-cbor")]
+    #[error("Cbor encode error: {0}")]
+    CborEncode(#[from] ciborium::ser::Error<io::Error>),
+
+    /// Postcard error
+    #[cfg(feature = "format-postcard")]
+    #[error("Postcard error: {0}")]
+    Postcard(#[from] postcard::Error),
+
+    /// Toml edit decode error
+    #[cfg(feature = "format-toml-edit")]
+    #[error("Toml error: {0}")]
+    TomlEditDecode(#[from] toml_edit::de::Error),
+
+    /// Toml edit encode error
+    #[cfg(feature = "format-toml-edit")]
+    #[error("Toml error: {0}")]
+    TomlEditEncode(#[from] toml_edit::ser::Error),
+
+    /// Quick xml error
+    #[cfg(feature = "format-xml")]
+    #[error("Xml error: {0}")]
+    Xml(#[from] quick_xml::DeError),
+
+    /// Ini, properties or dotenv decode error, with the line it was found on when known
+    #[cfg(any(
+        feature = "format-ini",
+        feature = "format-properties",
+        feature = "format-dotenv"
+    ))]
+    #[error("Decode error: {0}")]
+    KeyValueDecode(#[from] crate::format::key_value::DeError),
+
+    /// Ini, properties or dotenv encode error, usually a value the format can't represent
+    #[cfg(any(
+        feature = "format-ini",
+        feature = "format-properties",
+        feature = "format-dotenv"
+    ))]
+    #[error("Encode error: {0}")]
+    KeyValueEncode(#[from] crate::format::key_value::SerError),
+
+    /// Csv decode error, listing every malformed row
+    #[cfg(feature = "format-csv")]
+    #[error("Csv decode error: {0}")]
+    CsvDecode(#[from] crate::format::csv::DeError),
+
+    /// Csv encode error
+    #[cfg(feature = "format-csv")]
+    #[error("Csv encode error: {0}")]
+    CsvEncode(#[from] csv::Error),
+
+    /// Kdl decode error, with the line it was found on
+    #[cfg(feature = "format-kdl")]
+    #[error("Kdl decode error: {0}")]
+    KdlDecode(#[from] crate::format::kdl::DeError),
+
+    /// Kdl encode error, usually a value the format can't represent
+    #[cfg(feature = "format-kdl")]
+    #[error("Kdl encode error: {0}")]
+    KdlEncode(#[from] crate::format::kdl::SerError),
+
+    /// Raw text or bytes decode error, e.g. invalid UTF-8 or an empty file
+    #[cfg(feature = "format-raw")]
+    #[error("Decode error: {0}")]
+    RawDecode(#[from] crate::format::raw::DeError),
+
+    /// Raw text or bytes encode error, when the value is not a string, scalar or buffer
+    #[cfg(feature = "format-raw")]
+    #[error("Encode error: {0}")]
+    RawEncode(#[from] crate::format::raw::SerError),
+}
+
+/// Stands for an error met reading an encrypted file, see [`Error::Redacted`].
+#[cfg(feature = "encrypt")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Redacted {
+    kind: ErrorKind,
+    line: Option<usize>,
+    column: Option<usize>,
+    invalid_data: bool,
+    mismatch: bool,
+}
+
+#[cfg(feature = "encrypt")]
+impl Redacted {
+    /// Kind of the hidden error.
+    #[must_use]
+    pub const fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+}
+
+#[cfg(feature = "encrypt")]
+impl fmt::Display for Redacted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} error", self.kind)?;
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, " at line {line} column {column}")?,
+            (Some(line), None) => write!(f, " at line {line}")?,
+            _ => {}
+        }
+        f.write_str(", details hidden since the content is encrypted")
+    }
+}
+
+/// What was being done with the file when an [`Error`](enum@Error) occurred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Operation {
+    /// Opening the file
+    Open,
+    /// Reading the content, then verifying, decrypting and decompressing it
+    Read,
+    /// Deserializing the content into the value
+    Parse,
+    /// Serializing the value into the content, then compressing, encrypting and signing it
+    Encode,
+    /// Truncating the file before writing
+    Truncate,
+    /// Writing the content
+    Write,
+    /// Flushing the file down to disk
+    Flush,
+}
+
+impl Operation {
+    const fn verb(self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Read => "read",
+            Self::Parse => "parse",
+            Self::Encode => "encode",
+            Self::Truncate => "truncate",
+            Self::Write => "write",
+            Self::Flush => "flush",
+        }
+    }
+}
+
+/// Kind of an [`Error`](enum@Error), whatever the enabled features, see [`Error::kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The file can't be opened, read or written
+    Io,
+    /// The content is malformed, corrupted, or doesn't match the type of the value
+    Decode,
+    /// The value can't be represented in the format, compressed or encrypted
+    Encode,
+    /// The file can't be decrypted
+    Decrypt,
+    /// The signature of the file doesn't verify
+    Verify,
+    /// The file can't be written without the signing key
+    ReadOnly,
+    /// The file can't be upgraded to the current schema version
+    Migrate,
+    /// The envelope around the content is damaged, or was written for another type
+    Envelope,
+    /// The content has unknown keys, in strict mode
+    UnknownKeys,
+    /// The content doesn't match the JSON Schema of the value
+    Schema,
+    /// The value was rejected by a validation hook
+    Validate,
+    /// The format of the file couldn't be picked
+    UnknownFormat,
+    /// An output option was set for a format that doesn't support it
+    UnsupportedOption,
+}
+
+/// Where and when an [`Error`](enum@Error) occurred.
+///
+/// With the `diagnostics` feature, it's a [`miette::Diagnostic`] pointing at the failing line of
+/// the file. The content of encrypted files is never kept, and the errors met parsing it are
+/// [redacted](Error::Redacted), so it doesn't leak into logs.
+#[non_exhaustive]
+pub struct Context {
+    /// Path of the file, when it was opened by path
+    pub path: Option<PathBuf>,
+    /// What was being done with the file
+    pub operation: Option<Operation>,
+    /// Dotted path of the failing value, e.g. `server.port` or `mirrors.1.url`. Not known with
+    /// plain `toml` and `cbor`
+    pub key: Option<String>,
+    /// Line of the content the error was found on, from `1`
+    pub line: Option<usize>,
+    /// Column of the line the error was found on, from `1`
+    pub column: Option<usize>,
+    /// The error itself
+    pub error: Error,
+    /// Content of the file, to show the failing line
+    #[cfg(feature = "diagnostics")]
+    content: Option<String>,
+}
+
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Context")
+            .field("path", &self.path)
+            .field("operation", &self.operation)
+            .field("key", &self.key)
+            .field("line", &self.line)
+            .field("column", &self.column)
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The line and column are left to the error, most formats already report them
+        match (self.operation, &self.path) {
+            (Some(operation), Some(path)) => {
+                write!(f, "Failed to {} `{}`", operation.verb(), path.display())?;
+            }
+            (Some(operation), None) => write!(f, "Failed to {}", operation.verb())?,
+            (None, Some(path)) => write!(f, "`{}`", path.display())?,
+            (None, None) => write!(f, "Error")?,
+        }
+        if let Some(key) = &self.key {
+            write!(f, " at `{key}`")?;
+        }
+        write!(f, ": {}", self.error)
+    }
+}
+
+impl std::error::Error for Context {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        std::error::Error::source(&self.error)
+    }
+}
+
+#[cfg(feature = "diagnostics")]
+impl Context {
+    /// Span of the failing character, or of the failing line when the column is unknown.
+    fn span(&self) -> Option<miette::SourceSpan> {
+        let content = self.content.as_deref()?;
+        let start = content
+            .split_inclusive('\n')
+            .take(self.line?.saturating_sub(1))
+            .map(str::len)
+            .sum::<usize>();
+        let line = content[start..].split('\n').next().unwrap_or_default();
+        let Some(column) = self.column else {
+            return Some((start, line.len()).into());
+        };
+        let offset = line
+            .char_indices()
+            .nth(column.saturating_sub(1))
+            .map_or(line.len(), |(offset, _)| offset);
+        let len = line[offset..].chars().next().map_or(0, char::len_utf8);
+        Some((start + offset, len).into())
+    }
+}
+
+#[cfg(feature = "diagnostics")]
+impl miette::SourceCode for Context {
+    fn read_span<'a>(
+        &'a self,
+        span: &miette::SourceSpan,
+        context_lines_before: usize,
+        context_lines_after: usize,
+    ) -> Result<Box<dyn miette::SpanContents<'a> + 'a>, miette::MietteError> {
+        let content = self.content.as_deref().unwrap_or_default();
+        let contents = content.read_span(span, context_lines_before, context_lines_after)?;
+        let Some(path) = &self.path else {
+            return Ok(contents);
+        };
+        Ok(Box::new(miette::MietteSpanContents::new_named(
+            path.display().to_string(),
+            contents.data(),
+            *contents.span(),
+            contents.line(),
+            contents.column(),
+            contents.line_count(),
+        )))
+    }
+}
+
+#[cfg(feature = "diagnostics")]
+impl miette::Diagnostic for Context {
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        self.content.as_ref()?;
+        Some(self)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        let label = self.key.as_ref().map(|key| format!("at `{key}`"));
+        let span = miette::LabeledSpan::new_primary_with_span(label, self.span()?);
+        Some(Box::new(std::iter::once(span)))
+    }
+}
+
+#[cfg(feature = "diagnostics")]
+impl miette::Diagnostic for Error {
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        self.context()?.source_code()
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        self.context()?.labels()
+    }
+}
+
+/// Line and column, from `1`, of the byte at `offset` in `buf`.
+#[cfg(feature = "format-toml-edit")]
+fn line_column(buf: &[u8], offset: usize) -> (usize, usize) {
+    let before = &buf[..offset.min(buf.len())];
+    let line_start = before.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+    let line = before.split(|&b| b == b'\n').count();
+    let column = String::from_utf8_lossy(&before[line_start..]).chars().count() + 1;
+    (line, column)
 }
 
 impl Error {
+    /// The error without its context.
+    #[must_use]
+    pub fn inner(&self) -> &Self {
+        match self {
+            Self::Context(context) => &context.error,
+            _ => self,
+        }
+    }
+
+    /// Where and when the error occurred, if known.
+    #[must_use]
+    pub fn context(&self) -> Option<&Context> {
+        match self {
+            Self::Context(context) => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Path of the file the error occurred on, when it was opened by path.
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        self.context()?.path.as_deref()
+    }
+
+    /// What was being done with the file when the error occurred.
+    #[must_use]
+    pub fn operation(&self) -> Option<Operation> {
+        self.context()?.operation
+    }
+
+    /// Dotted path of the value that failed to deserialize, e.g. `server.port`.
+    #[must_use]
+    pub fn key(&self) -> Option<&str> {
+        self.context()?.key.as_deref()
+    }
+
+    /// Line of the content the error was found on, from `1`.
+    #[must_use]
+    pub fn line(&self) -> Option<usize> {
+        self.context()?.line
+    }
+
+    /// Column of the line the error was found on, from `1`.
+    #[must_use]
+    pub fn column(&self) -> Option<usize> {
+        self.context()?.column
+    }
+
+    /// Render the error as a diagnostic: the message, then the failing line of the file with its
+    /// neighbours, marked under the failing value. Colored when printed to a terminal, unless
+    /// `NO_COLOR` is set.
+    #[cfg(feature = "diagnostics")]
+    #[must_use]
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        // Writing to a string can't fail
+        // The message already ends with the cause
+        let _ = miette::GraphicalReportHandler::new()
+            .without_cause_chain()
+            .render_report(&mut out, self);
+        out
+    }
+
+    /// Kind of the error, to match on whatever the enabled features.
+    ///
+    /// This is the stable way to tell errors apart: the variants of [`Error`](enum@Error) come
+    /// and go with the enabled features, and a variant's inner error may change with the
+    /// library it comes from.
+    #[must_use]
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            // Errors of some formats are the same when reading and writing
+            Self::Context(context) => match (context.error.kind(), context.operation) {
+                (ErrorKind::Decode, Some(Operation::Encode)) => ErrorKind::Encode,
+                (kind, _) => kind,
+            },
+            Self::Io(_) => ErrorKind::Io,
+            #[cfg(feature = "format-json")]
+            Self::Json(err) if err.classify() == Category::Io => ErrorKind::Io,
+            #[cfg(feature = "encrypt")]
+            Self::Decrypt(_) => ErrorKind::Decrypt,
+            #[cfg(feature = "encrypt")]
+            Self::Encrypt(_) => ErrorKind::Encode,
+            #[cfg(feature = "encrypt")]
+            Self::Redacted(redacted) => redacted.kind,
+            #[cfg(feature = "sign")]
+            Self::Verify(_) => ErrorKind::Verify,
+            #[cfg(feature = "sign")]
+            Self::ReadOnly => ErrorKind::ReadOnly,
+            #[cfg(feature = "versioning")]
+            Self::Migrate(_) => ErrorKind::Migrate,
+            #[cfg(feature = "envelope")]
+            Self::Envelope(_) => ErrorKind::Envelope,
+            #[cfg(feature = "unknown-keys")]
+            Self::UnknownKeys(_) => ErrorKind::UnknownKeys,
+            Self::Validate(_) => ErrorKind::Validate,
+            #[cfg(feature = "schema")]
+            Self::Schema(_) => ErrorKind::Schema,
+            Self::UnknownFormat(_) => ErrorKind::UnknownFormat,
+            Self::UnsupportedOption { .. } => ErrorKind::UnsupportedOption,
+            #[cfg(feature = "format-cbor")]
+            Self::CborEncode(_) => ErrorKind::Encode,
+            #[cfg(feature = "format-toml-edit")]
+            Self::TomlEditEncode(_) => ErrorKind::Encode,
+            #[cfg(any(
+                feature = "format-ini",
+                feature = "format-properties",
+                feature = "format-dotenv"
+            ))]
+            Self::KeyValueEncode(_) => ErrorKind::Encode,
+            #[cfg(feature = "format-csv")]
+            Self::CsvEncode(_) => ErrorKind::Encode,
+            #[cfg(feature = "format-kdl")]
+            Self::KdlEncode(_) => ErrorKind::Encode,
+            #[cfg(feature = "format-raw")]
+            Self::RawEncode(_) => ErrorKind::Encode,
+            #[allow(unreachable_patterns)]
+            _ => ErrorKind::Decode,
+        }
+    }
+
+    fn with_context(self, f: impl FnOnce(&mut Context)) -> Self {
+        let mut context = match self {
+            Self::Context(context) => context,
+            error => Box::new(Context {
+                path: None,
+                operation: None,
+                key: None,
+                line: None,
+                column: None,
+                error,
+                #[cfg(feature = "diagnostics")]
+                content: None,
+            }),
+        };
+        f(&mut context);
+        Self::Context(context)
+    }
+
+    /// Record the operation the error occurred during, unless already known.
+    pub(crate) fn during(self, operation: Operation) -> Self {
+        self.with_context(|context| {
+            context.operation.get_or_insert(operation);
+        })
+    }
+
+    /// Record the path of the file the error occurred on, unless already known.
+    pub(crate) fn in_file(self, path: Option<&Path>) -> Self {
+        let Some(path) = path else {
+            return self;
+        };
+        self.with_context(|context| {
+            context.path.get_or_insert_with(|| path.to_path_buf());
+        })
+    }
+
+    /// Record the path of the value that failed to deserialize, unless empty or already known.
+    pub(crate) fn at_key(self, path: &serde_path_to_error::Path) -> Self {
+        let segments: Vec<_> = path
+            .iter()
+            .map(|segment| match segment {
+                serde_path_to_error::Segment::Seq { index } => index.to_string(),
+                serde_path_to_error::Segment::Map { key } => key.clone(),
+                serde_path_to_error::Segment::Enum { variant } => variant.clone(),
+                serde_path_to_error::Segment::Unknown => "?".to_owned(),
+            })
+            .collect();
+        if segments.is_empty() {
+            return self;
+        }
+        self.with_context(|context| {
+            context.key.get_or_insert_with(|| segments.join("."));
+        })
+    }
+
+    /// Record where deserializing `buf` failed: the line and column reported by the format.
+    /// `buf` is kept to be rendered unless it's `secret`, e.g. decrypted, in which case the
+    /// error itself is redacted too.
+    #[cfg_attr(
+        not(any(feature = "diagnostics", feature = "encrypt")),
+        allow(unused_variables)
+    )]
+    pub(crate) fn parsing(self, buf: &[u8], secret: bool) -> Self {
+        let (line, column) = self.inner().location(buf);
+        #[cfg(feature = "encrypt")]
+        let this = if secret {
+            self.redact(line, column)
+        } else {
+            self
+        };
+        #[cfg(not(feature = "encrypt"))]
+        let this = self;
+        this.during(Operation::Parse).with_context(|context| {
+            context.line = context.line.or(line);
+            context.column = context.column.or(column);
+            #[cfg(feature = "diagnostics")]
+            if context.line.is_some() && !secret {
+                context.content = Some(String::from_utf8_lossy(buf).into_owned());
+            }
+        })
+    }
+
+    /// Replace the error by its kind, keeping its context.
+    #[cfg(feature = "encrypt")]
+    fn redact(self, line: Option<usize>, column: Option<usize>) -> Self {
+        let redacted = Redacted {
+            kind: self.inner().kind(),
+            line,
+            column,
+            invalid_data: self.is_invalid_data(),
+            mismatch: self.is_mismatch(),
+        };
+        self.with_context(|context| context.error = Self::Redacted(redacted))
+    }
+
+    /// Line and column reported by the format, from `1`.
+    #[cfg_attr(not(feature = "format-toml-edit"), allow(unused_variables))]
+    #[allow(clippy::missing_const_for_fn)]
+    fn location(&self, buf: &[u8]) -> (Option<usize>, Option<usize>) {
+        match self {
+            #[cfg(feature = "format-json")]
+            Self::Json(err) if err.line() > 0 => (Some(err.line()), Some(err.column())),
+            #[cfg(feature = "format-toml")]
+            Self::Toml(err) => err
+                .line_col()
+                .map_or((None, None), |(line, column)| (Some(line + 1), Some(column + 1))),
+            #[cfg(feature = "format-toml-edit")]
+            Self::TomlEditDecode(err) => err.span().map_or((None, None), |span| {
+                let (line, column) = line_column(buf, span.start);
+                (Some(line), Some(column))
+            }),
+            #[cfg(any(
+                feature = "format-ini",
+                feature = "format-properties",
+                feature = "format-dotenv"
+            ))]
+            Self::KeyValueDecode(err) => (err.line(), None),
+            #[cfg(feature = "format-csv")]
+            Self::CsvDecode(err) => (err
+                .errors()
+                .first()
+                .and_then(crate::format::key_value::DeError::line), None),
+            #[cfg(feature = "format-kdl")]
+            Self::KdlDecode(err) => (err.line(), None),
+            _ => (None, None),
+        }
+    }
+
     /// Check if the error is caused by invalid data (e.g. bad syntax, unexpected EOF, etc.)
     #[must_use]
     #[allow(clippy::missing_const_for_fn)]
     pub fn is_invalid_data(&self) -> bool {
         match self {
+            Self::Context(context) => context.error.is_invalid_data(),
             #[cfg(feature = "format-json")]
             Self::Json(err) => matches!(
                 err.classify(),
@@ -38,6 +713,68 @@ impl Error {
             ),
             #[cfg(feature = "format-toml")]
             Self::Toml(_) => true,
+            #[cfg(feature = "encrypt")]
+            Self::Redacted(redacted) => redacted.invalid_data,
+            #[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+            Self::Decompress(_) => true,
+            #[cfg(feature = "envelope")]
+            Self::Envelope(err) => err.is_corrupted(),
+            #[cfg(feature = "format-cbor")]
+            Self::CborDecode(err) => match err {
+                ciborium::de::Error::Io(err) => err.kind() == io::ErrorKind::UnexpectedEof,
+                _ => true,
+            },
+            #[cfg(feature = "format-toml-edit")]
+            Self::TomlEditDecode(_) => true,
+            #[cfg(any(
+                feature = "format-ini",
+                feature = "format-properties",
+                feature = "format-dotenv"
+            ))]
+            Self::KeyValueDecode(_) => true,
+            #[cfg(feature = "format-csv")]
+            Self::CsvDecode(_) => true,
+            #[cfg(feature = "format-kdl")]
+            Self::KdlDecode(_) => true,
+            #[cfg(feature = "format-raw")]
+            Self::RawDecode(_) => true,
+            #[cfg(feature = "format-xml")]
+            Self::Xml(err) => !matches!(err, quick_xml::DeError::Unsupported(_)),
+            #[cfg(feature = "format-postcard")]
+            Self::Postcard(err) => !matches!(
+                err,
+                postcard::Error::SerializeBufferFull
+                    | postcard::Error::SerializeSeqLengthUnknown
+                    | postcard::Error::SerdeSerCustom
+            ),
+            _ => false,
+        }
+    }
+}
+
+impl Error {
+    /// Whether the content is well-formed but not meant for this [`Vow`](crate::Vow), e.g.
+    /// encrypted or signed with another key, written for another type, from a schema version
+    /// that can't be upgraded, not matching the JSON Schema, or with unknown keys in strict mode.
+    /// Such content is never overwritten, and always reported.
+    #[allow(clippy::missing_const_for_fn)]
+    pub(crate) fn is_mismatch(&self) -> bool {
+        match self {
+            Self::Context(context) => context.error.is_mismatch(),
+            #[cfg(feature = "encrypt")]
+            Self::Decrypt(_) => true,
+            #[cfg(feature = "encrypt")]
+            Self::Redacted(redacted) => redacted.mismatch,
+            #[cfg(feature = "envelope")]
+            Self::Envelope(err) => !err.is_corrupted(),
+            #[cfg(feature = "sign")]
+            Self::Verify(_) => true,
+            #[cfg(feature = "versioning")]
+            Self::Migrate(_) => true,
+            #[cfg(feature = "unknown-keys")]
+            Self::UnknownKeys(_) => true,
+            #[cfg(feature = "schema")]
+            Self::Schema(_) => true,
             _ => false,
         }
     }
@@ -45,3 +782,114 @@ impl Error {
 
 /// Result type for vow operations.
 pub type VowResult<T, E = Error> = Result<T, E>;
+
+/// Record the context of the error of a result.
+pub(crate) trait ResultExt<T> {
+    /// See [`Error::during`].
+    fn during(self, operation: Operation) -> VowResult<T>;
+
+    /// See [`Error::in_file`].
+    fn in_file(self, path: Option<&Path>) -> VowResult<T>;
+}
+
+impl<T, E: Into<Error>> ResultExt<T> for Result<T, E> {
+    fn during(self, operation: Operation) -> VowResult<T> {
+        self.map_err(|err| err.into().during(operation))
+    }
+
+    fn in_file(self, path: Option<&Path>) -> VowResult<T> {
+        self.map_err(|err| err.into().in_file(path))
+    }
+}
+
+#[cfg(all(test, feature = "format-json"))]
+mod test {
+    use serde::{Deserialize, Serialize};
+
+    use crate::{ErrorKind, Operation, Vow};
+
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        mirrors: Vec<Mirror>,
+    }
+
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct Mirror {
+        url: String,
+        weight: u8,
+    }
+
+    const CONTENT: &str = r#"{
+  "name": "vow",
+  "mirrors": [
+    {"url": "a", "weight": 1},
+    {"url": "b", "weight": 300}
+  ]
+}"#;
+
+    #[test]
+    fn test_context() {
+        let path = std::env::temp_dir().join("vow-error.json");
+        std::fs::write(&path, CONTENT).unwrap();
+        let err = Vow::open(&path)
+            .deny_invalid()
+            .default(Config::default())
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::Decode);
+        assert_eq!(err.path(), Some(path.as_path()));
+        assert_eq!(err.operation(), Some(Operation::Parse));
+        assert_eq!(err.key(), Some("mirrors.1.weight"));
+        assert_eq!((err.line(), err.column()), (Some(5), Some(30)));
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Failed to parse `{}` at `mirrors.1.weight`: Json error: invalid value: integer \
+                 `300`, expected u8 at line 5 column 30",
+                path.display()
+            )
+        );
+
+        // Nothing left from the previous failure
+        std::fs::write(&path, "[]").unwrap();
+        let err = Vow::open(&path)
+            .deny_invalid()
+            .default(Config::default())
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::Decode);
+        assert_eq!(err.key(), None);
+
+        let path = std::env::temp_dir().join("vow-missing").join("config.json");
+        let err = Vow::open(&path)
+            .default(Config::default())
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::Io);
+        assert_eq!(err.path(), Some(path.as_path()));
+        assert_eq!(err.operation(), Some(Operation::Open));
+        assert_eq!(err.key(), None);
+    }
+
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    fn test_render() {
+        let path = std::env::temp_dir().join("vow-error-render.json");
+        std::fs::write(&path, CONTENT).unwrap();
+        let err = Vow::open(&path)
+            .deny_invalid()
+            .default(Config::default())
+            .build()
+            .err()
+            .unwrap();
+        let rendered = err.render();
+        assert!(rendered.contains(&format!("[{}:5:30]", path.display())));
+        assert!(rendered.contains(r#"5 │     {"url": "b", "weight": 300}"#)
+            || rendered.contains(r#"5 |     {"url": "b", "weight": 300}"#));
+        assert!(rendered.contains("at `mirrors.1.weight`"));
+    }
+}
@@ -0,0 +1,293 @@
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+
+use super::{des, Auto, DefaultFormat, Format, ResolveFormat, ToFormat};
+use crate::{Error, VowResult};
+
+/// Formats picked from the (lowercased) extension of the file. Formats with options use their
+/// defaults, and `toml` preserves the layout of the file when `format-toml-edit` is enabled.
+const EXTENSIONS: &[(&str, Format)] = &[
+    #[cfg(feature = "format-json")]
+    ("json", Format::Json { lenient: false }),
+    #[cfg(feature = "format-json")]
+    ("jsonc", Format::Json { lenient: true }),
+    #[cfg(feature = "format-toml-edit")]
+    ("toml", Format::TomlEdit),
+    #[cfg(all(feature = "format-toml", not(feature = "format-toml-edit")))]
+    ("toml", Format::Toml),
+    #[cfg(feature = "format-cbor")]
+    (
+        "cbor",
+        Format::Cbor {
+            self_describe: false,
+        },
+    ),
+    #[cfg(feature = "format-postcard")]
+    ("postcard", Format::Postcard),
+    #[cfg(feature = "format-xml")]
+    (
+        "xml",
+        Format::Xml {
+            root: None,
+            scalar_attributes: false,
+        },
+    ),
+    #[cfg(feature = "format-ini")]
+    ("ini", Format::Ini),
+    #[cfg(feature = "format-properties")]
+    ("properties", Format::Properties),
+    #[cfg(feature = "format-dotenv")]
+    (
+        "env",
+        Format::Dotenv {
+            prefix: None,
+            uppercase: false,
+        },
+    ),
+    #[cfg(feature = "format-csv")]
+    (
+        "csv",
+        Format::Csv {
+            delimiter: b',',
+            quote: b'"',
+            quoting: super::Quoting::Necessary,
+        },
+    ),
+    #[cfg(feature = "format-csv")]
+    (
+        "tsv",
+        Format::Csv {
+            delimiter: b'\t',
+            quote: b'"',
+            quoting: super::Quoting::Necessary,
+        },
+    ),
+    #[cfg(feature = "format-kdl")]
+    ("kdl", Format::Kdl),
+    #[cfg(feature = "format-raw")]
+    (
+        "txt",
+        Format::Text {
+            line_ending: super::LineEnding::Preserve,
+            strip_newline: false,
+        },
+    ),
+];
+
+/// Formats tried in turn when sniffing, from the most to the least strict syntax.
+const SNIFF: &[Format] = &[
+    #[cfg(feature = "format-postcard")]
+    Format::Postcard,
+    #[cfg(feature = "format-cbor")]
+    Format::Cbor {
+        self_describe: true,
+    },
+    #[cfg(feature = "format-json")]
+    Format::Json { lenient: true },
+    #[cfg(feature = "format-toml-edit")]
+    Format::TomlEdit,
+    #[cfg(all(feature = "format-toml", not(feature = "format-toml-edit")))]
+    Format::Toml,
+    #[cfg(feature = "format-xml")]
+    Format::Xml {
+        root: None,
+        scalar_attributes: false,
+    },
+    #[cfg(feature = "format-kdl")]
+    Format::Kdl,
+    #[cfg(feature = "format-ini")]
+    Format::Ini,
+    #[cfg(feature = "format-dotenv")]
+    Format::Dotenv {
+        prefix: None,
+        uppercase: false,
+    },
+    #[cfg(feature = "format-properties")]
+    Format::Properties,
+    #[cfg(feature = "format-raw")]
+    Format::Text {
+        line_ending: super::LineEnding::Preserve,
+        strip_newline: false,
+    },
+];
+
+/// CBOR self-describe tag, required to sniff CBOR since almost anything is a CBOR prefix.
+#[cfg(feature = "format-cbor")]
+const CBOR_TAG: [u8; 3] = [0xd9, 0xd9, 0xf7];
+
+/// Pick the format from the name of the file: its extension, or `.env` and `.env.*` for
+/// dotenv.
+pub fn from_path(path: &Path) -> Option<Format> {
+    #[cfg(feature = "format-dotenv")]
+    {
+        let name = path.file_name()?.to_str()?;
+        if name == ".env" || name.starts_with(".env.") {
+            return Some(Format::Dotenv {
+                prefix: None,
+                uppercase: false,
+            });
+        }
+    }
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    EXTENSIONS
+        .iter()
+        .find(|(e, _)| *e == ext)
+        .map(|&(_, format)| format)
+}
+
+/// Pick the first format `buf` can be read from as a `T`.
+pub fn sniff<T: DeserializeOwned>(buf: &[u8]) -> Option<Format> {
+    SNIFF.iter().copied().find(|&format| {
+        #[cfg(feature = "format-cbor")]
+        if matches!(format, Format::Cbor { .. }) && !buf.starts_with(&CBOR_TAG) {
+            return false;
+        }
+        des::<T>(format, buf).is_ok()
+    })
+}
+
+/// Pick the format of `buf`, the content of the file at `path`. An empty or new file has
+/// nothing to sniff, so it gets the default format, like any file opened without a format.
+pub fn pick<T: DeserializeOwned>(buf: &[u8], path: Option<&Path>) -> VowResult<Format> {
+    if buf.is_empty() {
+        return Ok(DefaultFormat::default().to_format());
+    }
+    sniff::<T>(buf).ok_or_else(|| unknown(path, "unknown extension and unrecognized content"))
+}
+
+/// Error for a file whose format can't be picked.
+pub fn unknown(path: Option<&Path>, reason: &str) -> Error {
+    let extensions: Vec<_> = EXTENSIONS.iter().map(|(ext, _)| *ext).collect();
+    let file = path.map_or_else(|| "file".to_owned(), |path| format!("`{}`", path.display()));
+    Error::UnknownFormat(format!(
+        "{file}: {reason} (supported extensions: {})",
+        extensions.join(", ")
+    ))
+}
+
+impl ResolveFormat for Auto {
+    fn resolve(self, path: Option<&Path>) -> VowResult<Option<Format>> {
+        match path.and_then(from_path) {
+            Some(format) => Ok(Some(format)),
+            None if self.sniff => Ok(None),
+            None if path.is_none() => Err(unknown(path, "not opened by path")),
+            None => Err(unknown(path, "unknown extension")),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "format-json"))]
+mod test {
+    use std::path::Path;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::{from_path, sniff};
+    use crate::{format::Format, ErrorKind, Vow};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Data {
+        value: u8,
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(
+            from_path(Path::new("dir.d/Config.JSON")),
+            Some(Format::Json { lenient: false })
+        );
+        assert_eq!(
+            from_path(Path::new("config.jsonc")),
+            Some(Format::Json { lenient: true })
+        );
+        assert_eq!(from_path(Path::new("config.yaml")), None);
+        assert_eq!(from_path(Path::new("dir.json/config")), None);
+        #[cfg(feature = "format-dotenv")]
+        assert!(matches!(
+            from_path(Path::new("app/.env.local")),
+            Some(Format::Dotenv { .. })
+        ));
+    }
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(
+            sniff::<Data>(b"// comment\n{\"value\": 1,}"),
+            Some(Format::Json { lenient: true })
+        );
+        #[cfg(feature = "format-toml-edit")]
+        assert_eq!(sniff::<Data>(b"value = 1\n"), Some(Format::TomlEdit));
+        #[cfg(feature = "format-cbor")]
+        assert_eq!(
+            sniff::<Data>(b"\xd9\xd9\xf7\xa1\x65value\x01"),
+            Some(Format::Cbor {
+                self_describe: true
+            })
+        );
+        assert_eq!(sniff::<Data>(b"not a config"), None);
+    }
+
+    #[test]
+    fn test_build() {
+        let dir = std::env::temp_dir().join("vow-auto-format");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("config.json");
+        std::fs::write(&path, b"{\"value\":1}").unwrap();
+        let vow = Vow::open(&path)
+            .auto_format()
+            .default(Data { value: 0 })
+            .build()
+            .unwrap();
+        assert_eq!(*vow, Data { value: 1 });
+
+        let path = dir.join("config.yaml");
+        let err = Vow::open(&path)
+            .auto_format()
+            .default(Data { value: 0 })
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::UnknownFormat);
+        assert!(err.inner().to_string().starts_with(&format!(
+            "Unknown format: `{}`: unknown extension (supported extensions: json, jsonc",
+            path.display()
+        )));
+
+        // Sniffed, then written back in the same format
+        let path = dir.join("config");
+        std::fs::write(&path, b"{\"value\": 2} // edited").unwrap();
+        let mut vow = Vow::open(&path)
+            .auto_format()
+            .sniff()
+            .default(Data { value: 0 })
+            .build()
+            .unwrap();
+        assert_eq!(*vow, Data { value: 2 });
+        vow.set(Data { value: 3 }).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"{\"value\":3}");
+
+        // Nothing to sniff, so the default is written in the default format
+        std::fs::write(&path, b"").unwrap();
+        let vow = Vow::open(&path)
+            .auto_format()
+            .sniff()
+            .default(Data { value: 4 })
+            .build()
+            .unwrap();
+        assert_eq!(*vow, Data { value: 4 });
+        assert_eq!(std::fs::read(&path).unwrap(), b"{\"value\":4}");
+
+        let path = dir.join("new-config");
+        let _ = std::fs::remove_file(&path);
+        let vow = Vow::open(&path)
+            .auto_format()
+            .sniff()
+            .default(Data { value: 5 })
+            .build()
+            .unwrap();
+        assert_eq!(*vow, Data { value: 5 });
+        assert_eq!(std::fs::read(&path).unwrap(), b"{\"value\":5}");
+    }
+}
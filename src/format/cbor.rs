@@ -0,0 +1,205 @@
+use std::io;
+
+use ciborium::{de::Error as DeError, ser::Error as SerError, Value};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Encoded form of the self-describe tag (55799), see RFC 8949 section 3.4.6.
+const SELF_DESCRIBE: [u8; 3] = [0xd9, 0xd9, 0xf7];
+
+pub fn des<T: DeserializeOwned>(buf: &[u8]) -> Result<T, DeError<io::Error>> {
+    let mut rest = buf.strip_prefix(&SELF_DESCRIBE).unwrap_or(buf);
+    let value = ciborium::from_reader(&mut rest)?;
+
+    if !rest.is_empty() {
+        return Err(DeError::semantic(buf.len() - rest.len(), "trailing data"));
+    }
+
+    Ok(value)
+}
+
+pub fn se<T: Serialize>(
+    writer: &mut Vec<u8>,
+    value: &T,
+    self_describe: bool,
+    sort_keys: bool,
+) -> Result<(), SerError<io::Error>> {
+    if self_describe {
+        writer.extend_from_slice(&SELF_DESCRIBE);
+    }
+    if sort_keys {
+        let mut value = Value::serialized(value).map_err(|e| SerError::Value(e.to_string()))?;
+        sort(&mut value)?;
+        return ciborium::into_writer(&value, writer);
+    }
+    ciborium::into_writer(value, writer)
+}
+
+/// Sort map entries by their encoded keys, the deterministic order of RFC 8949 section 4.2.1.
+fn sort(value: &mut Value) -> Result<(), SerError<io::Error>> {
+    match value {
+        Value::Array(values) => values.iter_mut().try_for_each(sort),
+        Value::Tag(_, value) => sort(value),
+        Value::Map(entries) => {
+            let mut keyed = Vec::with_capacity(entries.len());
+            for (mut key, mut value) in std::mem::take(entries) {
+                sort(&mut key)?;
+                sort(&mut value)?;
+                let mut encoded = vec![];
+                ciborium::into_writer(&key, &mut encoded)?;
+                keyed.push((encoded, key, value));
+            }
+            keyed.sort_by(|(a, ..), (b, ..)| a.cmp(b));
+            entries.extend(keyed.into_iter().map(|(_, key, value)| (key, value)));
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+    use crate::format::{self, Format, FormatOptions};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Unit,
+        Newtype(u8),
+        Tuple(i32, i32),
+        Struct { w: f64, h: f64 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Unit;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Data {
+        flag: bool,
+        small: i8,
+        big: u64,
+        neg: i128,
+        float: f32,
+        ch: char,
+        text: String,
+        none: Option<String>,
+        some: Option<u16>,
+        unit: (),
+        unit_struct: Unit,
+        list: Vec<u32>,
+        tuple: (u8, String),
+        map: BTreeMap<String, i64>,
+        shapes: Vec<Shape>,
+    }
+
+    fn round_trip<T>(value: &T, self_describe: bool)
+    where
+        T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug,
+    {
+        let format = Format::Cbor { self_describe };
+        let mut buf = vec![];
+        format::se(format, FormatOptions::new(), &mut buf, value).unwrap();
+        assert_eq!(buf.starts_with(&super::SELF_DESCRIBE), self_describe);
+        assert_eq!(&format::des::<T>(format, &buf).unwrap(), value);
+    }
+
+    fn data() -> Data {
+        Data {
+            flag: true,
+            small: -8,
+            big: u64::MAX,
+            neg: i128::from(i64::MIN) - 1,
+            float: 1.5,
+            ch: 'v',
+            text: "vow".to_owned(),
+            none: None,
+            some: Some(42),
+            unit: (),
+            unit_struct: Unit,
+            list: vec![1, 2, 3],
+            tuple: (7, "seven".to_owned()),
+            map: [("a".to_owned(), 1), ("b".to_owned(), -1)].into(),
+            shapes: vec![
+                Shape::Unit,
+                Shape::Newtype(1),
+                Shape::Tuple(-1, 1),
+                Shape::Struct { w: 0.5, h: 2.0 },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip(&data(), false);
+        round_trip(&data(), true);
+        round_trip(&Shape::Struct { w: 1.0, h: 1.0 }, false);
+        round_trip(&vec![Some(1u8), None], true);
+        round_trip(&"plain".to_owned(), false);
+    }
+
+    #[test]
+    fn test_tags() {
+        // 55799(1(1_700_000_000)): self-describe prefix followed by an epoch timestamp tag
+        let buf = [0xd9, 0xd9, 0xf7, 0xc1, 0x1a, 0x65, 0x53, 0xf1, 0x00];
+        let value: u64 = format::des(
+            Format::Cbor {
+                self_describe: false,
+            },
+            &buf,
+        )
+        .unwrap();
+        assert_eq!(value, 1_700_000_000);
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        let format = Format::Cbor {
+            self_describe: false,
+        };
+        let mut buf = vec![];
+        format::se(format, FormatOptions::new(), &mut buf, &data()).unwrap();
+
+        let truncated = format::des::<Data>(format, &buf[..buf.len() - 1]).unwrap_err();
+        assert!(truncated.is_invalid_data());
+
+        buf.push(0x00);
+        let trailing = format::des::<Data>(format, &buf).unwrap_err();
+        assert!(trailing.is_invalid_data());
+
+        let empty = format::des::<Data>(format, &[]).unwrap_err();
+        assert!(empty.is_invalid_data());
+
+        let mismatch = format::des::<Data>(format, &[0x01]).unwrap_err();
+        assert!(mismatch.is_invalid_data());
+
+        let syntax = format::des::<Data>(format, &[0xff]).unwrap_err();
+        assert!(syntax.is_invalid_data());
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        #[derive(Serialize)]
+        struct Keys {
+            bb: u8,
+            a: u8,
+            c: u8,
+        }
+
+        let options = FormatOptions {
+            sort_keys: true,
+            ..FormatOptions::new()
+        };
+        let mut buf = vec![];
+        let format = Format::Cbor {
+            self_describe: false,
+        };
+        format::se(format, options, &mut buf, &Keys { bb: 1, a: 2, c: 3 }).unwrap();
+        // Shorter keys first, then bytewise
+        assert_eq!(
+            buf,
+            [0xa3, 0x61, b'a', 0x02, 0x61, b'c', 0x03, 0x62, b'b', b'b', 0x01]
+        );
+    }
+}
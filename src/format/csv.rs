@@ -0,0 +1,474 @@
+use std::fmt::{self, Display};
+
+use ::csv::{Error as CsvError, ErrorKind, QuoteStyle, ReaderBuilder, Writer, WriterBuilder};
+use serde::{
+    de::DeserializeOwned,
+    ser::{self, Impossible, Serialize},
+};
+use serde_path_to_error::Track;
+
+use super::{
+    key_value::{self, Node},
+    Quoting,
+};
+
+/// Error while reading a CSV file. Every malformed row is reported, each with its line.
+#[derive(Debug)]
+pub struct DeError {
+    errors: Vec<key_value::DeError>,
+}
+
+impl DeError {
+    /// Errors found in the file, in order.
+    pub fn errors(&self) -> &[key_value::DeError] {
+        &self.errors
+    }
+}
+
+impl From<key_value::DeError> for DeError {
+    fn from(err: key_value::DeError) -> Self {
+        Self { errors: vec![err] }
+    }
+}
+
+impl Display for DeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, err) in self.errors.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{err}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DeError {}
+
+/// Read the rows of a CSV file with a header row, each row being deserialized from a map of
+/// column names to fields. Empty fields are read as `None`, and an empty file as no rows.
+pub fn des<T: DeserializeOwned>(
+    buf: &[u8],
+    delimiter: u8,
+    quote: u8,
+    track: &mut Track,
+) -> Result<T, DeError> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(delimiter)
+        .quote(quote)
+        .from_reader(buf);
+    // An empty file has no header row, and no rows either
+    let headers = reader.headers().map_err(|err| convert(&err))?.clone();
+
+    let mut rows = vec![];
+    let mut errors = vec![];
+    for record in reader.records() {
+        match record {
+            Ok(record) => {
+                let line = record
+                    .position()
+                    .map_or(0, |pos| usize::try_from(pos.line()).unwrap_or(usize::MAX));
+                let fields = headers
+                    .iter()
+                    .zip(&record)
+                    .map(|(name, field)| (name.to_owned(), Node::Value(field.to_owned(), line)));
+                rows.push((line, Node::Map(fields.collect())));
+            }
+            Err(err) => errors.push(convert(&err)),
+        }
+    }
+
+    match key_value::from_rows(rows, track) {
+        Ok(value) if errors.is_empty() => Ok(value),
+        Ok(_) => Err(DeError { errors }),
+        Err(rows) => {
+            errors.extend(rows);
+            errors.sort_by_key(key_value::DeError::line);
+            Err(DeError { errors })
+        }
+    }
+}
+
+fn convert(err: &CsvError) -> key_value::DeError {
+    let line = err
+        .position()
+        .map_or(0, |pos| usize::try_from(pos.line()).unwrap_or(usize::MAX));
+    let message = match err.kind() {
+        ErrorKind::UnequalLengths {
+            expected_len, len, ..
+        } => format!("expected {expected_len} fields, found {len}"),
+        ErrorKind::Utf8 { err, .. } => format!("invalid UTF-8 in field {}", err.field() + 1),
+        _ => err.to_string(),
+    };
+    key_value::DeError::new(line, message)
+}
+
+/// Write `value`, which must be a sequence of rows, with a header row taken from the field
+/// names of the first row. Nothing is written for an empty sequence.
+pub fn se<T: Serialize>(
+    writer: &mut Vec<u8>,
+    value: &T,
+    delimiter: u8,
+    quote: u8,
+    quoting: Quoting,
+) -> Result<(), CsvError> {
+    let style = match quoting {
+        Quoting::Necessary => QuoteStyle::Necessary,
+        Quoting::Always => QuoteStyle::Always,
+        Quoting::NonNumeric => QuoteStyle::NonNumeric,
+        Quoting::Never => QuoteStyle::Never,
+    };
+    let mut csv = WriterBuilder::new()
+        .delimiter(delimiter)
+        .quote(quote)
+        .quote_style(style)
+        .from_writer(writer);
+    value.serialize(Rows(&mut csv))?;
+    csv.flush()?;
+    Ok(())
+}
+
+fn not_rows() -> CsvError {
+    ser::Error::custom("csv requires a sequence of rows")
+}
+
+/// Serializer accepting a sequence only, writing each element as a row.
+struct Rows<'a, 'w>(&'a mut Writer<&'w mut Vec<u8>>);
+
+impl ser::Serializer for Rows<'_, '_> {
+    type Ok = ();
+    type Error = CsvError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Impossible<(), CsvError>;
+    type SerializeTupleVariant = Impossible<(), CsvError>;
+    type SerializeMap = Impossible<(), CsvError>;
+    type SerializeStruct = Impossible<(), CsvError>;
+    type SerializeStructVariant = Impossible<(), CsvError>;
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self, CsvError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self, CsvError> {
+        Ok(self)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), CsvError> {
+        value.serialize(self)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), CsvError> {
+        value.serialize(self)
+    }
+
+    fn serialize_none(self) -> Result<(), CsvError> {
+        Err(not_rows())
+    }
+
+    fn serialize_bool(self, _: bool) -> Result<(), CsvError> {
+        Err(not_rows())
+    }
+
+    fn serialize_i64(self, _: i64) -> Result<(), CsvError> {
+        Err(not_rows())
+    }
+
+    fn serialize_u64(self, _: u64) -> Result<(), CsvError> {
+        Err(not_rows())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), CsvError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), CsvError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), CsvError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), CsvError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), CsvError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), CsvError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_f32(self, _: f32) -> Result<(), CsvError> {
+        Err(not_rows())
+    }
+
+    fn serialize_f64(self, _: f64) -> Result<(), CsvError> {
+        Err(not_rows())
+    }
+
+    fn serialize_char(self, _: char) -> Result<(), CsvError> {
+        Err(not_rows())
+    }
+
+    fn serialize_str(self, _: &str) -> Result<(), CsvError> {
+        Err(not_rows())
+    }
+
+    fn serialize_bytes(self, _: &[u8]) -> Result<(), CsvError> {
+        Err(not_rows())
+    }
+
+    fn serialize_unit(self) -> Result<(), CsvError> {
+        Err(not_rows())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), CsvError> {
+        Err(not_rows())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+    ) -> Result<(), CsvError> {
+        Err(not_rows())
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), CsvError> {
+        Err(not_rows())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, CsvError> {
+        Err(not_rows())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, CsvError> {
+        Err(not_rows())
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, CsvError> {
+        Err(not_rows())
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, CsvError> {
+        Err(not_rows())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, CsvError> {
+        Err(not_rows())
+    }
+}
+
+impl ser::SerializeSeq for Rows<'_, '_> {
+    type Ok = ();
+    type Error = CsvError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, row: &T) -> Result<(), CsvError> {
+        self.0.serialize(row)
+    }
+
+    fn end(self) -> Result<(), CsvError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for Rows<'_, '_> {
+    type Ok = ();
+    type Error = CsvError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, row: &T) -> Result<(), CsvError> {
+        self.0.serialize(row)
+    }
+
+    fn end(self) -> Result<(), CsvError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde::{Deserialize, Serialize};
+
+    use crate::format::{self, key_value, Format, FormatOptions, Quoting};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    enum Status {
+        Queued,
+        Done,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Job {
+        id: u32,
+        name: String,
+        status: Status,
+        retries: Option<u8>,
+    }
+
+    const CSV: Format = Format::Csv {
+        delimiter: b',',
+        quote: b'"',
+        quoting: Quoting::Necessary,
+    };
+
+    fn jobs() -> Vec<Job> {
+        vec![
+            Job {
+                id: 1,
+                name: "build, test".to_owned(),
+                status: Status::Done,
+                retries: Some(2),
+            },
+            Job {
+                id: 2,
+                name: "say \"hi\"".to_owned(),
+                status: Status::Queued,
+                retries: None,
+            },
+        ]
+    }
+
+    fn write(format: Format, jobs: &[Job]) -> String {
+        let mut buf = vec![];
+        format::se(format, FormatOptions::new(), &mut buf, &jobs).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let csv = write(CSV, &jobs());
+        assert_eq!(
+            csv,
+            "id,name,status,retries\n1,\"build, test\",Done,2\n2,\"say \"\"hi\"\"\",Queued,\n"
+        );
+        assert_eq!(
+            format::des::<Vec<Job>>(CSV, csv.as_bytes()).unwrap(),
+            jobs()
+        );
+    }
+
+    #[test]
+    fn test_delimiter_and_quoting() {
+        let format = Format::Csv {
+            delimiter: b';',
+            quote: b'\'',
+            quoting: Quoting::NonNumeric,
+        };
+        let csv = write(format, &jobs()[..1]);
+        assert_eq!(
+            csv,
+            "'id';'name';'status';'retries'\n1;'build, test';'Done';2\n"
+        );
+        assert_eq!(
+            format::des::<Vec<Job>>(format, csv.as_bytes()).unwrap(),
+            jobs()[..1]
+        );
+
+        // Columns are matched by name
+        let csv = "status;name;id;retries\nDone;'a;b';3;\n";
+        let read: Vec<Job> = format::des(format, csv.as_bytes()).unwrap();
+        assert_eq!(read[0].name, "a;b");
+        assert_eq!(read[0].id, 3);
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(write(CSV, &[]), "");
+        let read: Vec<Job> = format::des(CSV, b"id,name,status,retries\n").unwrap();
+        assert!(read.is_empty());
+        let read: Vec<Job> = format::des(CSV, b"").unwrap();
+        assert!(read.is_empty());
+    }
+
+    #[test]
+    fn test_vow_empty() {
+        let path = std::env::temp_dir().join("vow-csv-empty.csv");
+        let _ = std::fs::remove_file(&path);
+        let mut vow = crate::Vow::open(&path)
+            .csv()
+            .default(jobs())
+            .build()
+            .unwrap();
+        vow.set(vec![]).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"");
+
+        let vow = crate::Vow::open(&path)
+            .csv()
+            .default(jobs())
+            .build()
+            .unwrap();
+        assert!(vow.is_empty());
+    }
+
+    #[test]
+    fn test_malformed_rows() {
+        let csv = "id,name,status,retries\n1,a,Done,\n2,b\n3,c,Done,1,extra\n4,d,Done,\n";
+        let err = format::des::<Vec<Job>>(CSV, csv.as_bytes()).unwrap_err();
+        assert!(err.is_invalid_data());
+        let crate::Error::CsvDecode(err) = err else {
+            panic!("expected a csv error");
+        };
+        let lines: Vec<_> = err.errors().iter().map(key_value::DeError::line).collect();
+        assert_eq!(lines, [Some(3), Some(4)]);
+        assert_eq!(
+            err.to_string(),
+            "line 3: expected 4 fields, found 2; line 4: expected 4 fields, found 5"
+        );
+
+        // Bad values don't stop the next rows from being checked
+        let csv = "id,name,status,retries\n1,a,Done,\n2,b,Lost,\n3,c\nx,d,Done,\n5,e,Done,1\n";
+        let err = format::des::<Vec<Job>>(CSV, csv.as_bytes()).unwrap_err();
+        assert!(err.is_invalid_data());
+        let crate::Error::CsvDecode(err) = err else {
+            panic!("expected a csv error");
+        };
+        let lines: Vec<_> = err.errors().iter().map(key_value::DeError::line).collect();
+        assert_eq!(lines, [Some(3), Some(4), Some(5)]);
+        let messages: Vec<_> = err.errors().iter().map(ToString::to_string).collect();
+        assert!(messages[0].starts_with("line 3: unknown variant `Lost`"));
+        assert_eq!(messages[1], "line 4: expected 4 fields, found 2");
+        assert!(messages[2].starts_with("line 5: invalid value: string \"x\""));
+    }
+
+    #[test]
+    fn test_not_rows() {
+        let mut buf = vec![];
+        let err = format::se(CSV, FormatOptions::new(), &mut buf, &jobs()[0]).unwrap_err();
+        assert!(!err.is_invalid_data());
+    }
+}
@@ -0,0 +1,342 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_path_to_error::Track;
+
+use super::{
+    key_value::{self, DeError, Node, SerError},
+    FormatOptions,
+};
+
+/// Parse a `.env` file.
+///
+/// - Lines starting with `#` are comments, and a leading `export ` is ignored.
+/// - Single-quoted values are taken literally.
+/// - Double-quoted values may span several lines, and `\n`, `\r`, `\t`, `\\`, `\"` and `\$` are
+///   unescaped.
+/// - Unquoted values end at ` #`.
+///
+/// Variables are never expanded. With a `prefix`, names that don't start with it are ignored
+/// and it is stripped from the others; with `uppercase`, names are lowercased to match fields.
+pub fn des<T: DeserializeOwned>(
+    buf: &[u8],
+    prefix: Option<&str>,
+    uppercase: bool,
+    track: &mut Track,
+) -> Result<T, DeError> {
+    let text = key_value::utf8(buf)?;
+    let mut entries: Vec<(String, Node)> = vec![];
+    let mut lines = text.lines().enumerate();
+
+    while let Some((index, line)) = lines.next() {
+        let n = index + 1;
+        // Only trimmed at the start: the end of a quoted value's first line belongs to the value
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").map_or(line, str::trim_start);
+
+        let Some((name, rest)) = line.split_once('=') else {
+            return Err(DeError::new(n, "expected `NAME=value`"));
+        };
+        let name = name.trim_end();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(DeError::new(n, format!("invalid name `{name}`")));
+        }
+
+        let rest = rest.trim_start();
+        let value = if let Some(quoted) = rest.strip_prefix('\'') {
+            let Some((value, after)) = quoted.split_once('\'') else {
+                return Err(DeError::new(n, "missing closing quote"));
+            };
+            end_of_value(after).map_err(|msg| DeError::new(n, msg))?;
+            value.to_owned()
+        } else if let Some(quoted) = rest.strip_prefix('"') {
+            let mut raw = quoted.to_owned();
+            loop {
+                if let Some(value) = double_quoted(&raw).map_err(|msg| DeError::new(n, msg))? {
+                    break value;
+                }
+                let Some((_, next)) = lines.next() else {
+                    return Err(DeError::new(n, "missing closing quote"));
+                };
+                raw.push('\n');
+                raw.push_str(next);
+            }
+        } else {
+            let end = [" #", "\t#"]
+                .iter()
+                .filter_map(|comment| rest.find(comment))
+                .min()
+                .unwrap_or(rest.len());
+            rest[..end].trim_end().to_owned()
+        };
+
+        let Some(key) = prefix.map_or(Some(name), |prefix| name.strip_prefix(prefix)) else {
+            continue;
+        };
+        let key = if uppercase {
+            key.to_lowercase()
+        } else {
+            key.to_owned()
+        };
+        if entries.iter().any(|(k, _)| *k == key) {
+            return Err(DeError::new(n, format!("duplicate name `{name}`")));
+        }
+        entries.push((key, Node::Value(value, n)));
+    }
+
+    key_value::from_node(Node::Map(entries), track)
+}
+
+/// Only whitespace or a comment may follow a quoted value.
+fn end_of_value(after: &str) -> Result<(), &'static str> {
+    let after = after.trim_start();
+    if after.is_empty() || after.starts_with('#') {
+        Ok(())
+    } else {
+        Err("unexpected characters after the closing quote")
+    }
+}
+
+/// Unescape a double-quoted value, or return `None` if the closing quote is not in `raw` yet.
+fn double_quoted(raw: &str) -> Result<Option<String>, &'static str> {
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => {
+                end_of_value(chars.as_str())?;
+                return Ok(Some(out));
+            }
+            '\\' => match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('r') => out.push('\r'),
+                Some('t') => out.push('\t'),
+                Some(ch @ ('\\' | '"' | '$')) => out.push(ch),
+                Some(ch) => {
+                    out.push('\\');
+                    out.push(ch);
+                }
+                None => return Ok(None),
+            },
+            ch => out.push(ch),
+        }
+    }
+    Ok(None)
+}
+
+/// Write `value`, which must be a struct or map of scalars, as `NAME=value` lines.
+///
+/// Values made of safe characters only are written as is, values without `'` or control
+/// characters are single-quoted, and the others are double-quoted with escapes.
+pub fn se<T: Serialize>(
+    writer: &mut Vec<u8>,
+    value: &T,
+    prefix: Option<&str>,
+    uppercase: bool,
+    options: FormatOptions,
+) -> Result<(), SerError> {
+    let Some(mut node) = key_value::to_node(value)? else {
+        return Ok(());
+    };
+    if options.sort_keys {
+        node.sort();
+    }
+    let Node::Map(entries) = node else {
+        return Err(SerError::new(
+            "dotenv requires a struct or map at the top level",
+        ));
+    };
+
+    let mut out = String::new();
+    for (key, node) in entries {
+        let Node::Value(value, _) = node else {
+            return Err(SerError::new("nested values are not supported in dotenv").at(&key));
+        };
+
+        let mut name = prefix.unwrap_or_default().to_owned();
+        if uppercase {
+            name.push_str(&key.to_uppercase());
+        } else {
+            name.push_str(&key);
+        }
+        let invalid = name.is_empty()
+            || name.starts_with('#')
+            || name.contains(|ch: char| ch == '=' || ch.is_whitespace() || ch.is_control());
+        if invalid {
+            return Err(SerError::new(format!("invalid name `{name}`")).at(&key));
+        }
+
+        out.push_str(&name);
+        out.push('=');
+        quote(&mut out, &value);
+        out.push('\n');
+    }
+
+    writer.extend_from_slice(out.as_bytes());
+    Ok(())
+}
+
+fn quote(out: &mut String, value: &str) {
+    let safe = |ch: char| ch.is_ascii_alphanumeric() || "_-./:@%+,".contains(ch);
+    if value.chars().all(safe) {
+        out.push_str(value);
+    } else if !value.contains(|ch: char| ch == '\'' || ch.is_control()) {
+        out.push('\'');
+        out.push_str(value);
+        out.push('\'');
+    } else {
+        out.push('"');
+        for ch in value.chars() {
+            match ch {
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                '\\' | '"' | '$' => {
+                    out.push('\\');
+                    out.push(ch);
+                }
+                ch => out.push(ch),
+            }
+        }
+        out.push('"');
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde::{Deserialize, Serialize};
+
+    use crate::format::{self, Format, FormatOptions};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Settings {
+        database_url: String,
+        port: u16,
+        motd: String,
+        quote: String,
+        debug: Option<bool>,
+    }
+
+    fn settings() -> Settings {
+        Settings {
+            database_url: "postgres://db:5432/app".to_owned(),
+            port: 8080,
+            motd: "hello $USER # not a comment".to_owned(),
+            quote: "it's\n\"quoted\" $HOME".to_owned(),
+            debug: None,
+        }
+    }
+
+    fn write(format: Format, value: &Settings) -> String {
+        let mut buf = vec![];
+        format::se(format, FormatOptions::new(), &mut buf, value).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let format = Format::Dotenv {
+            prefix: None,
+            uppercase: false,
+        };
+        let env = write(format, &settings());
+        assert_eq!(
+            env,
+            "database_url=postgres://db:5432/app\nport=8080\nmotd='hello $USER # not a comment'\nquote=\"it's\\n\\\"quoted\\\" \\$HOME\"\n"
+        );
+        assert_eq!(
+            format::des::<Settings>(format, env.as_bytes()).unwrap(),
+            settings()
+        );
+    }
+
+    #[test]
+    fn test_prefix_and_uppercase() {
+        let format = Format::Dotenv {
+            prefix: Some("APP_"),
+            uppercase: true,
+        };
+        let env = write(format, &settings());
+        assert!(env.starts_with("APP_DATABASE_URL=postgres://db:5432/app\nAPP_PORT=8080\n"));
+
+        let env = format!("# generated\nPATH=/bin\nexport APP_DEBUG = true # inline\n{env}");
+        let read: Settings = format::des(format, env.as_bytes()).unwrap();
+        assert_eq!(read.debug, Some(true));
+        assert_eq!(read.quote, settings().quote);
+    }
+
+    #[test]
+    fn test_multiline() {
+        let format = Format::Dotenv {
+            prefix: None,
+            uppercase: false,
+        };
+        let env = "database_url = \"a\nb\" # two lines\nport=1\nmotd=plain text\nquote='\\n'\n";
+        let read: Settings = format::des(format, env.as_bytes()).unwrap();
+        assert_eq!(read.database_url, "a\nb");
+        assert_eq!(read.motd, "plain text");
+        assert_eq!(read.quote, "\\n");
+    }
+
+    #[test]
+    fn test_multiline_whitespace() {
+        let format = Format::Dotenv {
+            prefix: None,
+            uppercase: false,
+        };
+        let env = "port=1\nmotd=\"  first  \n  second\n\" \ndatabase_url=a  \nquote=' b ' \n";
+        let read: Settings = format::des(format, env.as_bytes()).unwrap();
+        assert_eq!(read.motd, "  first  \n  second\n");
+        assert_eq!(read.database_url, "a");
+        assert_eq!(read.quote, " b ");
+    }
+
+    #[test]
+    fn test_unsupported() {
+        #[derive(Serialize)]
+        struct Nested {
+            inner: Settings,
+        }
+
+        let format = Format::Dotenv {
+            prefix: None,
+            uppercase: false,
+        };
+        let mut buf = vec![];
+        let err = format::se(
+            format,
+            FormatOptions::new(),
+            &mut buf,
+            &Nested { inner: settings() },
+        )
+        .unwrap_err();
+        assert!(!err.is_invalid_data());
+        assert_eq!(
+            err.to_string(),
+            "Encode error: `inner`: nested values are not supported in dotenv"
+        );
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        let format = Format::Dotenv {
+            prefix: None,
+            uppercase: false,
+        };
+        for (env, line) in [
+            ("port=1\nnot a pair\n", 2),
+            ("port=1\nmotd=\"open\n", 2),
+            ("port=1\nmotd='a' b\n", 2),
+            ("port=1\nport=2\n", 2),
+            ("database_url=a\nmotd=b\nquote=c\nport=x\n", 4),
+        ] {
+            let err = format::des::<Settings>(format, env.as_bytes()).unwrap_err();
+            assert!(err.is_invalid_data());
+            let crate::Error::KeyValueDecode(err) = err else {
+                panic!("expected a decode error");
+            };
+            assert_eq!(err.line(), Some(line), "{env}");
+        }
+    }
+}
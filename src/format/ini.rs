@@ -0,0 +1,298 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_path_to_error::Track;
+
+use super::{
+    key_value::{self, DeError, Node, SerError},
+    FormatOptions,
+};
+
+/// Parse an INI document.
+///
+/// - `key = value` pairs before the first `[section]` belong to the top level.
+/// - Lines starting with `;` or `#` are comments, as is anything after ` ;` or ` #` in an
+///   unquoted value.
+/// - Values may be quoted with `"`, in which case `\\`, `\"`, `\n`, `\r` and `\t` are unescaped.
+/// - Duplicate sections and keys are rejected.
+pub fn des<T: DeserializeOwned>(buf: &[u8], track: &mut Track) -> Result<T, DeError> {
+    let text = key_value::utf8(buf)?;
+    let mut root: Vec<(String, Node)> = vec![];
+    let mut section: Option<usize> = None;
+
+    for (index, line) in text.lines().enumerate() {
+        let n = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with([';', '#']) {
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix('[') {
+            let Some(name) = rest.strip_suffix(']') else {
+                return Err(DeError::new(n, "expected `]` at the end of the section"));
+            };
+            let name = name.trim();
+            if root.iter().any(|(key, _)| key == name) {
+                return Err(DeError::new(n, format!("duplicate section `{name}`")));
+            }
+            root.push((name.to_owned(), Node::Map(vec![])));
+            section = Some(root.len() - 1);
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(DeError::new(n, "expected `key = value`"));
+        };
+        let key = key.trim_end();
+        if key.is_empty() {
+            return Err(DeError::new(n, "empty key"));
+        }
+        let value = parse_value(value.trim_start()).map_err(|msg| DeError::new(n, msg))?;
+
+        let entries = match section {
+            Some(index) => match &mut root[index].1 {
+                Node::Map(entries) => entries,
+                Node::Value(..) => unreachable!("sections are maps"),
+            },
+            None => &mut root,
+        };
+        if entries.iter().any(|(k, _)| k == key) {
+            return Err(DeError::new(n, format!("duplicate key `{key}`")));
+        }
+        entries.push((key.to_owned(), Node::Value(value, n)));
+    }
+
+    key_value::from_node(Node::Map(root), track)
+}
+
+fn parse_value(value: &str) -> Result<String, &'static str> {
+    let Some(quoted) = value.strip_prefix('"') else {
+        let end = [" ;", " #", "\t;", "\t#"]
+            .iter()
+            .filter_map(|comment| value.find(comment))
+            .min()
+            .unwrap_or(value.len());
+        return Ok(value[..end].trim_end().to_owned());
+    };
+
+    let mut out = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => {
+                let rest = chars.as_str().trim_start();
+                if rest.is_empty() || rest.starts_with([';', '#']) {
+                    return Ok(out);
+                }
+                return Err("unexpected characters after the closing quote");
+            }
+            '\\' => out.push(match chars.next() {
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('t') => '\t',
+                Some(ch @ ('\\' | '"')) => ch,
+                _ => return Err("invalid escape sequence"),
+            }),
+            ch => out.push(ch),
+        }
+    }
+    Err("missing closing quote")
+}
+
+/// Write `value`, which must be a struct or map. Scalar fields are written first, then each
+/// nested struct or map as a `[section]` of scalars.
+pub fn se<T: Serialize>(
+    writer: &mut Vec<u8>,
+    value: &T,
+    options: FormatOptions,
+) -> Result<(), SerError> {
+    let Some(mut node) = key_value::to_node(value)? else {
+        return Ok(());
+    };
+    if options.sort_keys {
+        node.sort();
+    }
+    let Node::Map(entries) = node else {
+        return Err(SerError::new(
+            "ini requires a struct or map at the top level",
+        ));
+    };
+
+    let mut out = String::new();
+    let (values, sections): (Vec<_>, Vec<_>) = entries
+        .into_iter()
+        .partition(|(_, node)| matches!(node, Node::Value(..)));
+
+    for (key, node) in values {
+        write_entry(&mut out, &key, node)?;
+    }
+    for (name, node) in sections {
+        if name.is_empty() || name.contains([']', '\n', '\r']) {
+            return Err(SerError::new("invalid section name").at(&name));
+        }
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push('[');
+        out.push_str(&name);
+        out.push_str("]\n");
+
+        let Node::Map(entries) = node else {
+            unreachable!("values are written above")
+        };
+        for (key, node) in entries {
+            if matches!(node, Node::Map(_)) {
+                return Err(SerError::new("nested sections are not supported in ini")
+                    .at(&key)
+                    .at(&name));
+            }
+            write_entry(&mut out, &key, node).map_err(|e| e.at(&name))?;
+        }
+    }
+
+    writer.extend_from_slice(out.as_bytes());
+    Ok(())
+}
+
+fn write_entry(out: &mut String, key: &str, node: Node) -> Result<(), SerError> {
+    let Node::Value(value, _) = node else {
+        unreachable!("sections are handled by the caller")
+    };
+    let invalid = key.is_empty()
+        || key.trim() != key
+        || key.starts_with([';', '#', '['])
+        || key.contains(['=', '\n', '\r']);
+    if invalid {
+        return Err(SerError::new("invalid key").at(key));
+    }
+
+    out.push_str(key);
+    out.push_str(" = ");
+    let quote = value.trim() != value
+        || value.starts_with('"')
+        || value.contains([';', '#', '\\', '\n', '\r', '\t']);
+    if quote {
+        out.push('"');
+        for ch in value.chars() {
+            match ch {
+                '\\' => out.push_str("\\\\"),
+                '"' => out.push_str("\\\""),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                ch => out.push(ch),
+            }
+        }
+        out.push('"');
+    } else {
+        out.push_str(&value);
+    }
+    out.push('\n');
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::format::{self, Format, FormatOptions};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        debug: bool,
+        server: Server,
+        env: BTreeMap<String, String>,
+    }
+
+    const DOC: &str = "name = vow\ndebug = false\n\n[server]\nhost = \"  spaced; \\\"quoted\\\"  \"\nport = 8080\n\n[env]\nPATH = /bin\n";
+
+    fn config() -> Config {
+        Config {
+            name: "vow".to_owned(),
+            debug: false,
+            server: Server {
+                host: "  spaced; \"quoted\"  ".to_owned(),
+                port: 8080,
+            },
+            env: [("PATH".to_owned(), "/bin".to_owned())].into(),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut buf = vec![];
+        format::se(Format::Ini, FormatOptions::new(), &mut buf, &config()).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), DOC);
+        assert_eq!(
+            format::des::<Config>(Format::Ini, DOC.as_bytes()).unwrap(),
+            config()
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        let doc = "; leading comment\r\n  name=vow ; trailing\r\ndebug =false\r\n# another\r\n[ server ]\r\nport= 8080\r\nhost =\"  spaced; \\\"quoted\\\"  \" # after quotes\r\n[env]\r\nPATH = /bin\r\n";
+        assert_eq!(
+            format::des::<Config>(Format::Ini, doc.as_bytes()).unwrap(),
+            config()
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        #[derive(Serialize)]
+        struct Deep {
+            server: BTreeMap<String, Server>,
+        }
+
+        let deep = Deep {
+            server: [(
+                "a".to_owned(),
+                Server {
+                    host: "h".to_owned(),
+                    port: 1,
+                },
+            )]
+            .into(),
+        };
+        let mut buf = vec![];
+        let err = format::se(Format::Ini, FormatOptions::new(), &mut buf, &deep).unwrap_err();
+        assert!(!err.is_invalid_data());
+        assert_eq!(
+            err.to_string(),
+            "Encode error: `server.a`: nested sections are not supported in ini"
+        );
+
+        let err = format::se(Format::Ini, FormatOptions::new(), &mut buf, &vec![1]).unwrap_err();
+        assert_eq!(err.to_string(), "Encode error: sequences are not supported");
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        for (doc, line) in [
+            ("name = vow\n[server\n", 2),
+            ("name = vow\njust a line\n", 2),
+            ("name = vow\nname = again\n", 2),
+            ("[env]\n[env]\n", 2),
+            ("a = \"open\n", 1),
+            ("name = vow\ndebug = maybe\n", 2),
+        ] {
+            let err = format::des::<Config>(Format::Ini, doc.as_bytes()).unwrap_err();
+            assert!(err.is_invalid_data());
+            let crate::Error::KeyValueDecode(err) = err else {
+                panic!("expected a decode error");
+            };
+            assert_eq!(err.line(), Some(line), "{doc}");
+        }
+
+        let err = format::des::<Config>(Format::Ini, b"name = vow\n").unwrap_err();
+        assert!(err.is_invalid_data());
+    }
+}
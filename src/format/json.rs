@@ -0,0 +1,126 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{ser::PrettyFormatter, Deserializer, Serializer, Value};
+use serde_path_to_error::Track;
+
+use super::FormatOptions;
+
+pub fn des<T: DeserializeOwned>(buf: &[u8], track: &mut Track) -> serde_json::Result<T> {
+    let mut de = Deserializer::from_slice(buf);
+    let value = T::deserialize(serde_path_to_error::Deserializer::new(&mut de, track))?;
+    de.end()?;
+    Ok(value)
+}
+
+pub fn se<T: Serialize>(
+    writer: &mut Vec<u8>,
+    value: &T,
+    options: FormatOptions,
+) -> serde_json::Result<()> {
+    if options.sort_keys {
+        let mut value = serde_json::to_value(value)?;
+        sort(&mut value);
+        return se(
+            writer,
+            &value,
+            FormatOptions {
+                sort_keys: false,
+                ..options
+            },
+        );
+    }
+
+    match options.indent {
+        Some(indent) => {
+            let indent = indent.render();
+            let formatter = PrettyFormatter::with_indent(indent.as_bytes());
+            value.serialize(&mut Serializer::with_formatter(writer, formatter))
+        }
+        None => serde_json::to_writer(writer, value),
+    }
+}
+
+/// Sort objects by key, regardless of whether `serde_json/preserve_order` is enabled.
+fn sort(value: &mut Value) {
+    match value {
+        Value::Array(values) => values.iter_mut().for_each(sort),
+        Value::Object(map) => {
+            let mut entries = std::mem::take(map).into_iter().collect::<Vec<_>>();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            for (key, mut value) in entries {
+                sort(&mut value);
+                map.insert(key, value);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde::Serialize;
+
+    use crate::format::{self, Format, FormatOptions, Indent};
+
+    #[derive(Serialize)]
+    struct Data {
+        name: &'static str,
+        list: Vec<u8>,
+        nested: Nested,
+    }
+
+    #[derive(Serialize)]
+    struct Nested {
+        z: bool,
+        a: bool,
+    }
+
+    fn write(options: FormatOptions) -> String {
+        let data = Data {
+            name: "vow",
+            list: vec![1, 2],
+            nested: Nested { z: true, a: false },
+        };
+        let mut buf = vec![];
+        format::se(Format::Json { lenient: false }, options, &mut buf, &data).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_compact() {
+        assert_eq!(
+            write(FormatOptions::new()),
+            r#"{"name":"vow","list":[1,2],"nested":{"z":true,"a":false}}"#
+        );
+    }
+
+    #[test]
+    fn test_indent() {
+        let options = FormatOptions {
+            indent: Some(Indent::Tab),
+            trailing_newline: true,
+            ..FormatOptions::new()
+        };
+        assert_eq!(
+            write(options),
+            "{\n\t\"name\": \"vow\",\n\t\"list\": [\n\t\t1,\n\t\t2\n\t],\n\t\"nested\": {\n\t\t\"z\": true,\n\t\t\"a\": false\n\t}\n}\n"
+        );
+
+        let options = FormatOptions {
+            indent: Some(Indent::Spaces(4)),
+            ..FormatOptions::new()
+        };
+        assert!(write(options).starts_with("{\n    \"name\": \"vow\",\n"));
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        let options = FormatOptions {
+            sort_keys: true,
+            ..FormatOptions::new()
+        };
+        assert_eq!(
+            write(options),
+            r#"{"list":[1,2],"name":"vow","nested":{"a":false,"z":true}}"#
+        );
+    }
+}
@@ -0,0 +1,130 @@
+/// Blank out comments and trailing commas so the result can be parsed as plain JSON.
+///
+/// Stripped bytes are replaced by spaces (newlines are kept), so line and column numbers
+/// reported by `serde_json` still point into the original document. Unterminated block
+/// comments are left untouched and surface as a syntax error.
+pub fn strip(buf: &[u8]) -> Vec<u8> {
+    let mut out = buf.to_vec();
+    let mut i = 0;
+    let mut in_str = false;
+    let mut trailing_comma = None;
+
+    while i < out.len() {
+        let b = out[i];
+
+        if in_str {
+            match b {
+                b'\\' => i += 1,
+                b'"' => in_str = false,
+                _ => {}
+            }
+            i += 1;
+            continue;
+        }
+
+        match (b, out.get(i + 1)) {
+            (b'/', Some(b'/')) => {
+                let end = out[i..]
+                    .iter()
+                    .position(|&b| b == b'\n')
+                    .map_or(out.len(), |pos| i + pos);
+                blank(&mut out[i..end]);
+                i = end;
+                continue;
+            }
+            (b'/', Some(b'*')) => {
+                let Some(pos) = out[i + 2..].windows(2).position(|w| w == b"*/") else {
+                    break;
+                };
+                let end = i + 2 + pos + 2;
+                blank(&mut out[i..end]);
+                i = end;
+                continue;
+            }
+            (b' ' | b'\t' | b'\r' | b'\n', _) => {}
+            (b',', _) => trailing_comma = Some(i),
+            _ => {
+                if let (Some(pos), b'}' | b']') = (trailing_comma, b) {
+                    out[pos] = b' ';
+                }
+                trailing_comma = None;
+                in_str = b == b'"';
+            }
+        }
+        i += 1;
+    }
+
+    out
+}
+
+fn blank(bytes: &mut [u8]) {
+    for b in bytes.iter_mut().filter(|b| **b != b'\n') {
+        *b = b' ';
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde::Deserialize;
+
+    use crate::format::{self, Format};
+
+    const LENIENT: Format = Format::Json { lenient: true };
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config {
+        name: String,
+        ports: Vec<u16>,
+    }
+
+    #[test]
+    fn test_comments_and_trailing_commas() {
+        let doc = br#"
+            // Service settings
+            {
+                /* the "name" */ "name": "a // not /* a comment", // trailing
+                "ports": [80, 443,],
+            }
+        "#;
+        let config: Config = format::des(LENIENT, doc).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                name: "a // not /* a comment".to_owned(),
+                ports: vec![80, 443],
+            }
+        );
+    }
+
+    #[test]
+    fn test_escaped_quotes() {
+        let doc = br#"{ "name": "say \"hi\" // still text", "ports": [] }"#;
+        let config: Config = format::des(LENIENT, doc).unwrap();
+        assert_eq!(config.name, r#"say "hi" // still text"#);
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        let err = format::des::<Config>(LENIENT, b"{ \"name\": \"a\", \"ports\": [] } /* open")
+            .unwrap_err();
+        assert!(err.is_invalid_data());
+
+        let err =
+            format::des::<Config>(LENIENT, b"{ \"name\": \"a\",, \"ports\": [] }").unwrap_err();
+        assert!(err.is_invalid_data());
+
+        let strict = Format::Json { lenient: false };
+        let err =
+            format::des::<Config>(strict, b"{ \"name\": \"a\", \"ports\": [], }").unwrap_err();
+        assert!(err.is_invalid_data());
+    }
+
+    #[test]
+    fn test_positions() {
+        let doc = b"{\n  // comment\n  \"name\": 1\n}";
+        let Err(crate::Error::Json(err)) = format::des::<Config>(LENIENT, doc) else {
+            panic!("expected json error");
+        };
+        assert_eq!(err.line(), 3);
+    }
+}
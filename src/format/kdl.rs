@@ -0,0 +1,1007 @@
+//! Serde mapping for KDL documents, on top of the `kdl` document model.
+//!
+//! A struct or map is written as one node per field, named after the field:
+//!
+//! - scalars become the single argument of the node (`port 8080`), and `None` is `#null`;
+//! - sequences of scalars become the arguments of the node (`tags a b`), while sequences
+//!   holding structs or sequences become children nodes named `-`;
+//! - nested structs and maps become children (`server { port 8080 }`);
+//! - unit enum variants are written as a string, other variants as a single child named after
+//!   the variant (`shape { circle 1.5 }`).
+//!
+//! When reading, properties (`server port=8080`) are accepted in addition to children
+//! wherever a struct or map is expected, and arguments and `-` children are both accepted
+//! for sequences.
+
+use std::fmt::{self, Display};
+
+use kdl::{FormatConfig, KdlDocument, KdlEntry, KdlError, KdlNode, KdlValue};
+use serde::{
+    de::{
+        self,
+        value::{MapDeserializer, SeqDeserializer},
+        DeserializeOwned, DeserializeSeed, EnumAccess, Expected, IntoDeserializer, Unexpected,
+        VariantAccess, Visitor,
+    },
+    forward_to_deserialize_any,
+    ser::{self, Serialize},
+};
+use serde_path_to_error::Track;
+
+use super::{FormatOptions, Indent};
+
+/// Error while reading a KDL document, with the line it was found on when known.
+#[derive(Debug)]
+pub struct DeError {
+    line: Option<usize>,
+    message: String,
+}
+
+impl DeError {
+    /// Line (1-based) the error was found on, if known.
+    pub const fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    const fn at(mut self, line: usize) -> Self {
+        if self.line.is_none() && line > 0 {
+            self.line = Some(line);
+        }
+        self
+    }
+}
+
+impl Display for DeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for DeError {}
+
+impl de::Error for DeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self {
+            line: None,
+            message: msg.to_string(),
+        }
+    }
+}
+
+/// Error while writing a KDL document, when the value has a shape KDL can't represent.
+#[derive(Debug)]
+pub struct SerError(String);
+
+impl Display for SerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SerError {}
+
+impl ser::Error for SerError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+fn line_of(text: &str, offset: usize) -> usize {
+    let end = offset.min(text.len());
+    text.as_bytes()[..end].split(|&b| b == b'\n').count()
+}
+
+fn parse_error(text: &str, err: &KdlError) -> DeError {
+    let Some(diagnostic) = err.diagnostics.first() else {
+        return de::Error::custom(err);
+    };
+    let message = diagnostic
+        .message
+        .as_ref()
+        .or(diagnostic.label.as_ref())
+        .map_or_else(|| err.to_string(), Clone::clone);
+    DeError {
+        line: Some(line_of(text, diagnostic.span.offset())),
+        message,
+    }
+}
+
+pub fn des<T: DeserializeOwned>(buf: &[u8], track: &mut Track) -> Result<T, DeError> {
+    let text = std::str::from_utf8(buf).map_err(de::Error::custom)?;
+    let doc: KdlDocument = text.parse().map_err(|err| parse_error(text, &err))?;
+    let de = De {
+        content: Content::Document(doc.nodes()),
+        line: 0,
+        text,
+    };
+    T::deserialize(serde_path_to_error::Deserializer::new(de, track))
+}
+
+pub fn se<T: Serialize>(
+    writer: &mut Vec<u8>,
+    value: &T,
+    options: FormatOptions,
+) -> Result<(), SerError> {
+    let mut doc = match value.serialize(Serializer)? {
+        Out::Value(_) => {
+            return Err(SerError(
+                "kdl requires a struct, map or sequence at the top level".to_owned(),
+            ))
+        }
+        Out::Node {
+            children: Some(doc),
+            ..
+        } => doc,
+        // A sequence of scalars
+        Out::Node { entries, .. } => {
+            let mut doc = KdlDocument::new();
+            for entry in entries {
+                let mut node = KdlNode::new("-");
+                node.push(entry);
+                doc.nodes_mut().push(node);
+            }
+            doc
+        }
+    };
+
+    if options.sort_keys {
+        sort(&mut doc);
+    }
+    let indent = options
+        .indent
+        .map_or_else(|| "    ".to_owned(), Indent::render);
+    doc.autoformat_config(&FormatConfig::builder().indent(&indent).build());
+    writer.extend_from_slice(doc.to_string().as_bytes());
+    Ok(())
+}
+
+/// Sort nodes by name, recursively. `-` nodes keep their order since the sort is stable.
+fn sort(doc: &mut KdlDocument) {
+    doc.nodes_mut()
+        .sort_by(|a, b| a.name().value().cmp(b.name().value()));
+    for node in doc.nodes_mut() {
+        if let Some(children) = node.children_mut() {
+            sort(children);
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Content<'a> {
+    Document(&'a [KdlNode]),
+    Node(&'a KdlNode),
+    Value(&'a KdlValue),
+}
+
+#[derive(Clone, Copy)]
+struct De<'a> {
+    content: Content<'a>,
+    /// Line of the node holding the content, `0` for the document itself
+    line: usize,
+    text: &'a str,
+}
+
+impl<'a> De<'a> {
+    fn node(&self, node: &'a KdlNode) -> Self {
+        Self {
+            content: Content::Node(node),
+            line: line_of(self.text, node.span().offset()),
+            text: self.text,
+        }
+    }
+
+    const fn value(&self, value: &'a KdlValue) -> Self {
+        Self {
+            content: Content::Value(value),
+            line: self.line,
+            text: self.text,
+        }
+    }
+
+    fn args(&self) -> Vec<&'a KdlValue> {
+        match self.content {
+            Content::Node(node) => node
+                .entries()
+                .iter()
+                .filter(|entry| entry.name().is_none())
+                .map(KdlEntry::value)
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    fn props(&self) -> Vec<(&'a str, &'a KdlValue)> {
+        match self.content {
+            Content::Node(node) => node
+                .entries()
+                .iter()
+                .filter_map(|entry| Some((entry.name()?.value(), entry.value())))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    fn children(&self) -> &'a [KdlNode] {
+        match self.content {
+            Content::Document(nodes) => nodes,
+            Content::Node(node) => node.children().map_or(&[], KdlDocument::nodes),
+            Content::Value(_) => &[],
+        }
+    }
+
+    /// The single value held, if any.
+    fn scalar(&self) -> Option<&'a KdlValue> {
+        match self.content {
+            Content::Value(value) => Some(value),
+            Content::Node(_) if self.children().is_empty() && self.props().is_empty() => {
+                match self.args().as_slice() {
+                    [value] => Some(value),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn invalid_type(&self, expected: &dyn Expected) -> DeError {
+        let unexpected = match self.content {
+            Content::Value(_) => Unexpected::Other("value"),
+            _ => Unexpected::Other("node"),
+        };
+        <DeError as de::Error>::invalid_type(unexpected, expected).at(self.line)
+    }
+
+    fn visit_scalar<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let Some(value) = self.scalar() else {
+            return Err(self.invalid_type(&visitor));
+        };
+        let res = match value {
+            KdlValue::String(s) => visitor.visit_str(s),
+            KdlValue::Integer(i) => {
+                if let Ok(i) = i64::try_from(*i) {
+                    visitor.visit_i64(i)
+                } else if let Ok(u) = u64::try_from(*i) {
+                    visitor.visit_u64(u)
+                } else {
+                    visitor.visit_i128(*i)
+                }
+            }
+            KdlValue::Float(f) => visitor.visit_f64(*f),
+            KdlValue::Bool(b) => visitor.visit_bool(*b),
+            KdlValue::Null => visitor.visit_unit(),
+        };
+        res.map_err(|e: DeError| e.at(self.line))
+    }
+}
+
+macro_rules! deserialize_scalar {
+    ($($method:ident)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+            self.visit_scalar(visitor)
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for De<'_> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.content {
+            Content::Value(_) => self.visit_scalar(visitor),
+            Content::Document(_) => self.deserialize_map(visitor),
+            Content::Node(_) if !self.children().is_empty() || !self.props().is_empty() => {
+                self.deserialize_map(visitor)
+            }
+            Content::Node(_) => match self.args().len() {
+                0 => visitor.visit_unit(),
+                1 => self.visit_scalar(visitor),
+                _ => self.deserialize_seq(visitor),
+            },
+        }
+    }
+
+    deserialize_scalar! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_identifier
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.scalar() {
+            Some(KdlValue::Null) => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let empty = self.args().is_empty() && self.props().is_empty();
+        match self.content {
+            Content::Value(KdlValue::Null) => visitor.visit_unit(),
+            Content::Node(_) if empty && self.children().is_empty() => visitor.visit_unit(),
+            _ => Err(self.invalid_type(&visitor)),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let items: Vec<_> = match self.content {
+            Content::Value(_) => return Err(self.invalid_type(&visitor)),
+            _ if !self.children().is_empty() => {
+                self.children().iter().map(|node| self.node(node)).collect()
+            }
+            _ => self.args().into_iter().map(|arg| self.value(arg)).collect(),
+        };
+        de::Deserializer::deserialize_any(SeqDeserializer::new(items.into_iter()), visitor)
+            .map_err(|e: DeError| e.at(self.line))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        if let Content::Value(_) = self.content {
+            return Err(self.invalid_type(&visitor));
+        }
+        let props = self
+            .props()
+            .into_iter()
+            .map(|(name, value)| (name, self.value(value)));
+        let children = self
+            .children()
+            .iter()
+            .map(|node| (node.name().value(), self.node(node)));
+        let mut map = MapDeserializer::new(props.chain(children));
+        let value = visitor
+            .visit_map(&mut map)
+            .map_err(|e: DeError| e.at(self.line))?;
+        map.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        if let Some(KdlValue::String(variant)) = self.scalar() {
+            return visitor
+                .visit_enum(variant.as_str().into_deserializer())
+                .map_err(|e: DeError| e.at(self.line));
+        }
+        match (
+            self.children(),
+            self.args().is_empty() && self.props().is_empty(),
+        ) {
+            ([node], true) => visitor.visit_enum(self.node(node)),
+            _ => Err(<DeError as de::Error>::custom(
+                "expected a variant name or a single child node named after the variant",
+            )
+            .at(self.line)),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bytes byte_buf
+    }
+}
+
+impl<'de> EnumAccess<'de> for De<'_> {
+    type Error = DeError;
+    type Variant = Self;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<(S::Value, Self), DeError> {
+        let Content::Node(node) = self.content else {
+            unreachable!("variants are read from nodes")
+        };
+        let name: &str = node.name().value();
+        let variant = seed.deserialize(name.into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for De<'_> {
+    type Error = DeError;
+
+    fn unit_variant(self) -> Result<(), DeError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value, DeError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, DeError> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+impl IntoDeserializer<'_, DeError> for De<'_> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Serialized form of a value, before it's attached to a node.
+enum Out {
+    Value(KdlValue),
+    Node {
+        entries: Vec<KdlEntry>,
+        children: Option<KdlDocument>,
+    },
+}
+
+impl Out {
+    const fn empty() -> Self {
+        Self::Node {
+            entries: vec![],
+            children: None,
+        }
+    }
+
+    fn into_node(self, name: &str) -> KdlNode {
+        let mut node = KdlNode::new(name);
+        match self {
+            Self::Value(value) => node.push(KdlEntry::new(value)),
+            Self::Node { entries, children } => {
+                *node.entries_mut() = entries;
+                if let Some(children) = children {
+                    node.set_children(children);
+                }
+            }
+        }
+        node
+    }
+
+    /// Wrap in a single child named after the variant.
+    fn variant(self, variant: &str) -> Self {
+        let mut doc = KdlDocument::new();
+        doc.nodes_mut().push(self.into_node(variant));
+        Self::Node {
+            entries: vec![],
+            children: Some(doc),
+        }
+    }
+}
+
+fn scalar(value: impl Into<KdlValue>) -> Out {
+    Out::Value(value.into())
+}
+
+macro_rules! serialize_integer {
+    ($($method:ident($ty:ty),)*) => {$(
+        fn $method(self, v: $ty) -> Result<Out, SerError> {
+            Ok(scalar(i128::from(v)))
+        }
+    )*};
+}
+
+struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Out;
+    type Error = SerError;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = SeqSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = MapSerializer;
+
+    serialize_integer! {
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<Out, SerError> {
+        Ok(scalar(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Out, SerError> {
+        i128::try_from(v)
+            .map_err(|_| SerError(format!("{v} is too large for a kdl integer")))
+            .map(scalar)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Out, SerError> {
+        Ok(scalar(f64::from(v)))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Out, SerError> {
+        Ok(scalar(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Out, SerError> {
+        Ok(scalar(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Out, SerError> {
+        Ok(scalar(v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Out, SerError> {
+        Ok(Out::Node {
+            entries: v.iter().map(|&b| KdlEntry::new(i128::from(b))).collect(),
+            children: None,
+        })
+    }
+
+    fn serialize_none(self) -> Result<Out, SerError> {
+        Ok(Out::Value(KdlValue::Null))
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Out, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Out, SerError> {
+        Ok(Out::empty())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Out, SerError> {
+        Ok(Out::empty())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Out, SerError> {
+        Ok(scalar(variant))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<Out, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Out, SerError> {
+        Ok(value.serialize(self)?.variant(variant))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, SerError> {
+        Ok(SeqSerializer {
+            items: Vec::with_capacity(len.unwrap_or_default()),
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, SerError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, SerError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, SerError> {
+        Ok(SeqSerializer {
+            items: Vec::with_capacity(len),
+            variant: Some(variant),
+        })
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<MapSerializer, SerError> {
+        Ok(MapSerializer {
+            doc: KdlDocument::new(),
+            key: None,
+            variant: None,
+        })
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<MapSerializer, SerError> {
+        self.serialize_map(None)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<MapSerializer, SerError> {
+        Ok(MapSerializer {
+            doc: KdlDocument::new(),
+            key: None,
+            variant: Some(variant),
+        })
+    }
+}
+
+struct SeqSerializer {
+    items: Vec<Out>,
+    variant: Option<&'static str>,
+}
+
+impl SeqSerializer {
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), SerError> {
+        self.items.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Out {
+        let scalars = self.items.iter().all(|item| matches!(item, Out::Value(_)));
+        let out = if scalars {
+            let entries = self.items.into_iter().filter_map(|item| match item {
+                Out::Value(value) => Some(KdlEntry::new(value)),
+                Out::Node { .. } => None,
+            });
+            Out::Node {
+                entries: entries.collect(),
+                children: None,
+            }
+        } else {
+            let mut doc = KdlDocument::new();
+            for item in self.items {
+                doc.nodes_mut().push(item.into_node("-"));
+            }
+            Out::Node {
+                entries: vec![],
+                children: Some(doc),
+            }
+        };
+        match self.variant {
+            Some(variant) => out.variant(variant),
+            None => out,
+        }
+    }
+}
+
+macro_rules! impl_seq {
+    ($($trait:ident :: $method:ident,)*) => {$(
+        impl ser::$trait for SeqSerializer {
+            type Ok = Out;
+            type Error = SerError;
+
+            fn $method<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), SerError> {
+                self.push(value)
+            }
+
+            fn end(self) -> Result<Out, SerError> {
+                Ok(self.finish())
+            }
+        }
+    )*};
+}
+
+impl_seq! {
+    SerializeSeq::serialize_element,
+    SerializeTuple::serialize_element,
+    SerializeTupleStruct::serialize_field,
+    SerializeTupleVariant::serialize_field,
+}
+
+struct MapSerializer {
+    doc: KdlDocument,
+    key: Option<String>,
+    variant: Option<&'static str>,
+}
+
+impl MapSerializer {
+    fn push<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> Result<(), SerError> {
+        let node = value
+            .serialize(Serializer)
+            .map_err(|e| SerError(format!("`{key}`: {e}")))?
+            .into_node(key);
+        self.doc.nodes_mut().push(node);
+        Ok(())
+    }
+
+    fn finish(self) -> Out {
+        let out = Out::Node {
+            entries: vec![],
+            children: (!self.doc.nodes().is_empty()).then_some(self.doc),
+        };
+        match self.variant {
+            Some(variant) => out.variant(variant),
+            None => out,
+        }
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Out;
+    type Error = SerError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), SerError> {
+        let key = match key.serialize(Serializer)? {
+            Out::Value(KdlValue::String(s)) => s,
+            Out::Value(KdlValue::Integer(i)) => i.to_string(),
+            Out::Value(KdlValue::Bool(b)) => b.to_string(),
+            _ => {
+                return Err(SerError(
+                    "map keys must be strings, integers or booleans".to_owned(),
+                ))
+            }
+        };
+        self.key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), SerError> {
+        let key = self.key.take().expect("serialize_key is called first");
+        self.push(&key, value)
+    }
+
+    fn end(self) -> Result<Out, SerError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = Out;
+    type Error = SerError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Out, SerError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStructVariant for MapSerializer {
+    type Ok = Out;
+    type Error = SerError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Out, SerError> {
+        Ok(self.finish())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::format::{self, Format, FormatOptions, Indent};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        debug: bool,
+        ratio: f64,
+        limit: Option<u8>,
+        tags: Vec<String>,
+        server: Server,
+        mirrors: Vec<Server>,
+        shapes: Vec<Shape>,
+        env: BTreeMap<String, String>,
+    }
+
+    fn config() -> Config {
+        Config {
+            name: "my app".to_owned(),
+            debug: false,
+            ratio: 1.5,
+            limit: None,
+            tags: vec!["a".to_owned(), "b".to_owned()],
+            server: Server {
+                host: "localhost".to_owned(),
+                port: 8080,
+            },
+            mirrors: vec![Server {
+                host: "m".to_owned(),
+                port: 1,
+            }],
+            shapes: vec![Shape::Point, Shape::Circle(0.5), Shape::Rect { w: 1, h: 2 }],
+            env: BTreeMap::new(),
+        }
+    }
+
+    const DOC: &str = r#"name "my app"
+debug #false
+ratio 1.5
+limit #null
+tags a b
+server {
+    host localhost
+    port 8080
+}
+mirrors {
+    - {
+        host m
+        port 1
+    }
+}
+shapes {
+    - Point
+    - {
+        Circle 0.5
+    }
+    - {
+        Rect {
+            w 1
+            h 2
+        }
+    }
+}
+env
+"#;
+
+    #[test]
+    fn test_round_trip() {
+        let mut buf = vec![];
+        format::se(Format::Kdl, FormatOptions::new(), &mut buf, &config()).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), DOC);
+        assert_eq!(
+            format::des::<Config>(Format::Kdl, DOC.as_bytes()).unwrap(),
+            config()
+        );
+    }
+
+    #[test]
+    fn test_hand_written() {
+        let doc = r#"
+            // Properties, dash children and comments are accepted
+            name "my app"; debug #false; ratio 1.5
+            tags { - a; - b }
+            server host=localhost port=8080
+            /- ignored "node"
+            mirrors { - host=m port=1 }
+            shapes Point { Circle 0.5 } { Rect w=1 h=2 }
+            env
+        "#;
+        // `shapes` above is not valid for a sequence of enums: only one form per node
+        assert!(format::des::<Config>(Format::Kdl, doc.as_bytes()).is_err());
+
+        let doc = doc.replace(
+            "shapes Point { Circle 0.5 } { Rect w=1 h=2 }",
+            "shapes { - Point; - { Circle 0.5; }; - { Rect w=1 h=2; }; }",
+        );
+        assert_eq!(
+            format::des::<Config>(Format::Kdl, doc.as_bytes()).unwrap(),
+            config()
+        );
+    }
+
+    #[test]
+    fn test_options() {
+        let options = FormatOptions {
+            indent: Some(Indent::Spaces(2)),
+            sort_keys: true,
+            ..FormatOptions::new()
+        };
+        let mut buf = vec![];
+        format::se(Format::Kdl, options, &mut buf, &config().server).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "host localhost\nport 8080\n"
+        );
+
+        let mut buf = vec![];
+        format::se(Format::Kdl, options, &mut buf, &vec![config().server]).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "- {\n  host localhost\n  port 8080\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        for (doc, line) in [
+            ("host localhost\nport {", Some(2)),
+            ("host localhost\n\nport x", Some(3)),
+            ("host localhost\nport 1 2", Some(2)),
+            ("host localhost", None),
+        ] {
+            let err = format::des::<Server>(Format::Kdl, doc.as_bytes()).unwrap_err();
+            assert!(err.is_invalid_data(), "{doc}");
+            let crate::Error::KdlDecode(err) = err else {
+                panic!("expected a kdl error");
+            };
+            assert_eq!(err.line(), line, "{doc}: {err}");
+        }
+
+        let mut buf = vec![];
+        let err = format::se(Format::Kdl, FormatOptions::new(), &mut buf, &1).unwrap_err();
+        assert!(!err.is_invalid_data());
+    }
+}
@@ -0,0 +1,833 @@
+//! Serde mapping shared by the line based formats, where every value is a string.
+//!
+//! Values are serialized into a tree of [`Node`]s: structs and maps become [`Node::Map`], and
+//! scalars (booleans, numbers, chars, strings and unit enum variants) become [`Node::Value`].
+//! `None` and `()` are skipped. Sequences, bytes and enum variants holding data are rejected,
+//! since there's no way to write them as a single line.
+//!
+//! When reading, scalars are parsed from the string on demand, so `port = 80` can be read into
+//! a `u16` as well as into a `String`. Empty values are read as `None`.
+//!
+//! `csv` only uses the reading half, writing rows through the `csv` crate directly.
+#![cfg_attr(
+    not(any(
+        feature = "format-ini",
+        feature = "format-properties",
+        feature = "format-dotenv"
+    )),
+    allow(dead_code)
+)]
+
+use std::fmt::{self, Display};
+
+use serde::{
+    de::{self, value::MapDeserializer, DeserializeOwned, IntoDeserializer, Unexpected, Visitor},
+    forward_to_deserialize_any,
+    ser::{self, Impossible, Serialize},
+};
+use serde_path_to_error::Track;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    /// A single value, with the line it was read from (`0` when serialized).
+    Value(String, usize),
+    Map(Vec<(String, Self)>),
+}
+
+impl Node {
+    /// Sort map entries by key, recursively.
+    pub fn sort(&mut self) {
+        if let Self::Map(entries) = self {
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            for (_, node) in entries {
+                node.sort();
+            }
+        }
+    }
+}
+
+/// Serialize `value` into a [`Node`], or `None` if there's nothing to write.
+pub fn to_node<T: ?Sized + Serialize>(value: &T) -> Result<Option<Node>, SerError> {
+    value.serialize(NodeSerializer)
+}
+
+pub fn from_node<T: DeserializeOwned>(node: Node, track: &mut Track) -> Result<T, DeError> {
+    T::deserialize(serde_path_to_error::Deserializer::new(node, track))
+}
+
+/// Deserialize a sequence of rows, e.g. the rows of a table, each with the line it was read
+/// from.
+///
+/// A row that can't be deserialized doesn't hide the next ones: the sequence is deserialized
+/// again from the following row, so that the error of every bad row is returned, in order.
+#[cfg(feature = "format-csv")]
+pub fn from_rows<T: DeserializeOwned>(
+    rows: Vec<(usize, Node)>,
+    track: &mut Track,
+) -> Result<T, Vec<DeError>> {
+    let mut rows = rows.into_iter();
+    let mut failed = false;
+    let err = match T::deserialize(serde_path_to_error::Deserializer::new(
+        Table::new(&mut rows, &mut failed),
+        track,
+    )) {
+        Ok(value) => return Ok(value),
+        Err(err) => err,
+    };
+
+    let mut errors = vec![err];
+    while std::mem::take(&mut failed) && rows.len() > 0 {
+        match T::deserialize(Table::new(&mut rows, &mut failed)) {
+            // Only errors of a row are reported, not e.g. the length of the remaining rows
+            Err(err) if failed => errors.push(err),
+            _ => break,
+        }
+    }
+    Err(errors)
+}
+
+/// Sequence of rows, recording whether a row failed to deserialize.
+#[cfg(feature = "format-csv")]
+struct Table<'a> {
+    rows: &'a mut std::vec::IntoIter<(usize, Node)>,
+    failed: &'a mut bool,
+    count: usize,
+}
+
+#[cfg(feature = "format-csv")]
+impl<'a> Table<'a> {
+    const fn new(rows: &'a mut std::vec::IntoIter<(usize, Node)>, failed: &'a mut bool) -> Self {
+        Self {
+            rows,
+            failed,
+            count: 0,
+        }
+    }
+}
+
+#[cfg(feature = "format-csv")]
+impl<'de> de::Deserializer<'de> for Table<'_> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, DeError> {
+        let value = visitor.visit_seq(&mut self)?;
+        match self.rows.len() {
+            0 => Ok(value),
+            remaining => Err(de::Error::custom(format!(
+                "expected {} rows, found {}",
+                self.count,
+                self.count + remaining
+            ))),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map struct enum
+        identifier ignored_any
+    }
+}
+
+#[cfg(feature = "format-csv")]
+impl<'de> de::SeqAccess<'de> for Table<'_> {
+    type Error = DeError;
+
+    fn next_element_seed<S: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<Option<S::Value>, DeError> {
+        let Some((line, row)) = self.rows.next() else {
+            return Ok(None);
+        };
+        self.count += 1;
+        match seed.deserialize(row) {
+            Ok(value) => Ok(Some(value)),
+            Err(err) => {
+                *self.failed = true;
+                Err(err.at(line))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.rows.len())
+    }
+}
+
+/// Decode `buf` as UTF-8 without byte order mark, reporting the line of the first invalid byte.
+pub fn utf8(buf: &[u8]) -> Result<&str, DeError> {
+    match std::str::from_utf8(buf) {
+        Ok(text) => Ok(text.strip_prefix('\u{feff}').unwrap_or(text)),
+        Err(err) => {
+            let valid = &buf[..err.valid_up_to()];
+            let line = valid.split(|&b| b == b'\n').count();
+            Err(DeError::new(line, "invalid UTF-8"))
+        }
+    }
+}
+
+/// Error while reading a line based format. Syntax errors and invalid values carry the line
+/// they were found on.
+#[derive(Debug)]
+pub struct DeError {
+    line: Option<usize>,
+    message: String,
+}
+
+impl DeError {
+    pub fn new(line: usize, message: impl Display) -> Self {
+        Self {
+            line: Some(line),
+            message: message.to_string(),
+        }
+    }
+
+    /// Line (1-based) the error was found on, if known.
+    pub const fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    const fn at(mut self, line: usize) -> Self {
+        if self.line.is_none() && line > 0 {
+            self.line = Some(line);
+        }
+        self
+    }
+}
+
+impl Display for DeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for DeError {}
+
+impl de::Error for DeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self {
+            line: None,
+            message: msg.to_string(),
+        }
+    }
+}
+
+/// Error while writing a line based format, usually because the value has a shape the format
+/// can't represent.
+#[derive(Debug)]
+pub struct SerError {
+    path: String,
+    message: String,
+}
+
+impl SerError {
+    pub fn new(message: impl Display) -> Self {
+        Self {
+            path: String::new(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Dotted path of the offending key, empty for the top level value.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Prefix the path with `key`.
+    pub fn at(mut self, key: &str) -> Self {
+        self.path = if self.path.is_empty() {
+            key.to_owned()
+        } else {
+            format!("{key}.{}", self.path)
+        };
+        self
+    }
+
+    fn unsupported(what: &str) -> Self {
+        Self::new(format!("{what} are not supported"))
+    }
+}
+
+impl Display for SerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "`{}`: {}", self.path, self.message)
+        }
+    }
+}
+
+impl std::error::Error for SerError {}
+
+impl ser::Error for SerError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::new(msg)
+    }
+}
+
+macro_rules! serialize_display {
+    ($($method:ident($ty:ty),)*) => {$(
+        fn $method(self, v: $ty) -> Result<Self::Ok, SerError> {
+            Ok(Some(Node::Value(v.to_string(), 0)))
+        }
+    )*};
+}
+
+struct NodeSerializer;
+
+impl ser::Serializer for NodeSerializer {
+    type Ok = Option<Node>;
+    type Error = SerError;
+    type SerializeSeq = Impossible<Self::Ok, SerError>;
+    type SerializeTuple = Impossible<Self::Ok, SerError>;
+    type SerializeTupleStruct = Impossible<Self::Ok, SerError>;
+    type SerializeTupleVariant = Impossible<Self::Ok, SerError>;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = Impossible<Self::Ok, SerError>;
+
+    serialize_display! {
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+    }
+
+    fn serialize_bytes(self, _: &[u8]) -> Result<Self::Ok, SerError> {
+        Err(SerError::unsupported("bytes"))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, SerError> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Self::Ok, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, SerError> {
+        Ok(None)
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Self::Ok, SerError> {
+        Ok(None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, SerError> {
+        Ok(Some(Node::Value(variant.to_owned(), 0)))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<Self::Ok, SerError> {
+        Err(SerError::unsupported("enum variants with data"))
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, SerError> {
+        Err(SerError::unsupported("sequences"))
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, SerError> {
+        Err(SerError::unsupported("tuples"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerError> {
+        Err(SerError::unsupported("tuple structs"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerError> {
+        Err(SerError::unsupported("enum variants with data"))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, SerError> {
+        Ok(MapSerializer::new(len.unwrap_or_default()))
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, SerError> {
+        Ok(MapSerializer::new(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, SerError> {
+        Err(SerError::unsupported("enum variants with data"))
+    }
+}
+
+struct MapSerializer {
+    entries: Vec<(String, Node)>,
+    key: Option<String>,
+}
+
+impl MapSerializer {
+    fn new(len: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(len),
+            key: None,
+        }
+    }
+
+    fn push<T: ?Sized + Serialize>(&mut self, key: String, value: &T) -> Result<(), SerError> {
+        if let Some(node) = to_node(value).map_err(|e| e.at(&key))? {
+            self.entries.push((key, node));
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Option<Node>;
+    type Error = SerError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), SerError> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), SerError> {
+        let key = self.key.take().expect("serialize_key is called first");
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerError> {
+        Ok(Some(Node::Map(self.entries)))
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = Option<Node>;
+    type Error = SerError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        self.push(key.to_owned(), value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerError> {
+        Ok(Some(Node::Map(self.entries)))
+    }
+}
+
+fn key_error() -> SerError {
+    SerError::new("map keys must be strings, numbers, booleans or unit variants")
+}
+
+macro_rules! key_display {
+    ($($method:ident($ty:ty),)*) => {$(
+        fn $method(self, v: $ty) -> Result<String, SerError> {
+            Ok(v.to_string())
+        }
+    )*};
+}
+
+macro_rules! key_unsupported {
+    ($($method:ident($($ty:ty),*),)*) => {$(
+        fn $method(self, $(_: $ty),*) -> Result<String, SerError> {
+            Err(key_error())
+        }
+    )*};
+}
+
+struct KeySerializer;
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = SerError;
+    type SerializeSeq = Impossible<String, SerError>;
+    type SerializeTuple = Impossible<String, SerError>;
+    type SerializeTupleStruct = Impossible<String, SerError>;
+    type SerializeTupleVariant = Impossible<String, SerError>;
+    type SerializeMap = Impossible<String, SerError>;
+    type SerializeStruct = Impossible<String, SerError>;
+    type SerializeStructVariant = Impossible<String, SerError>;
+
+    key_display! {
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_char(char),
+        serialize_str(&str),
+    }
+
+    key_unsupported! {
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_struct(&'static str),
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> Result<String, SerError> {
+        Err(key_error())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<String, SerError> {
+        Ok(variant.to_owned())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<String, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<String, SerError> {
+        Err(key_error())
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, SerError> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, SerError> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerError> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerError> {
+        Err(key_error())
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, SerError> {
+        Err(key_error())
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, SerError> {
+        Err(key_error())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, SerError> {
+        Err(key_error())
+    }
+}
+
+macro_rules! deserialize_parse {
+    ($($method:ident => $visit:ident,)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+            let Self::Value(value, line) = self else {
+                return self.deserialize_any(visitor);
+            };
+            match value.parse() {
+                Ok(parsed) => visitor.$visit(parsed).map_err(|e: DeError| e.at(line)),
+                Err(_) => Err(de::Error::invalid_value(Unexpected::Str(&value), &visitor))
+                    .map_err(|e: DeError| e.at(line)),
+            }
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for Node {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self {
+            Self::Value(value, line) => {
+                visitor.visit_string(value).map_err(|e: DeError| e.at(line))
+            }
+            Self::Map(entries) => {
+                // Keys are parsed like values, so that maps can have non-string keys
+                let entries = entries
+                    .into_iter()
+                    .map(|(key, node)| (Self::Value(key, 0), node));
+                let mut map = MapDeserializer::new(entries);
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    deserialize_parse! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self {
+            Self::Value(value, line) if value.is_empty() => {
+                visitor.visit_none().map_err(|e: DeError| e.at(line))
+            }
+            other => visitor.visit_some(other),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self {
+            Self::Value(value, line) if value.is_empty() => {
+                visitor.visit_unit().map_err(|e: DeError| e.at(line))
+            }
+            other => other.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        match self {
+            Self::Value(value, line) => visitor
+                .visit_enum(value.into_deserializer())
+                .map_err(|e: DeError| e.at(line)),
+            other @ Self::Map(_) => other.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf seq tuple tuple_struct map struct identifier
+    }
+}
+
+impl IntoDeserializer<'_, DeError> for Node {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+    use serde_path_to_error::Track;
+
+    use super::{from_node, to_node, Node};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Level {
+        Low,
+        High,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Inner {
+        level: Level,
+        ratio: f64,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        port: u16,
+        debug: bool,
+        missing: Option<char>,
+        inner: Inner,
+        extra: BTreeMap<u8, String>,
+    }
+
+    fn value(value: &str) -> Node {
+        Node::Value(value.to_owned(), 0)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let config = Config {
+            name: "vow".to_owned(),
+            port: 80,
+            debug: true,
+            missing: None,
+            inner: Inner {
+                level: Level::High,
+                ratio: 0.5,
+            },
+            extra: [(1, "one".to_owned())].into(),
+        };
+        let node = to_node(&config).unwrap().unwrap();
+        assert_eq!(
+            node,
+            Node::Map(vec![
+                ("name".to_owned(), value("vow")),
+                ("port".to_owned(), value("80")),
+                ("debug".to_owned(), value("true")),
+                (
+                    "inner".to_owned(),
+                    Node::Map(vec![
+                        ("level".to_owned(), value("High")),
+                        ("ratio".to_owned(), value("0.5")),
+                    ])
+                ),
+                (
+                    "extra".to_owned(),
+                    Node::Map(vec![("1".to_owned(), value("one"))])
+                ),
+            ])
+        );
+        assert_eq!(
+            from_node::<Config>(node, &mut Track::new()).unwrap(),
+            config
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        #[derive(Serialize)]
+        struct Outer {
+            inner: Tags,
+        }
+
+        #[derive(Serialize)]
+        struct Tags {
+            tags: Vec<u8>,
+        }
+
+        let err = to_node(&Outer {
+            inner: Tags { tags: vec![1] },
+        })
+        .unwrap_err();
+        assert_eq!(err.path(), "inner.tags");
+        assert_eq!(err.to_string(), "`inner.tags`: sequences are not supported");
+    }
+
+    #[test]
+    fn test_invalid_value() {
+        let node = Node::Map(vec![
+            ("level".to_owned(), Node::Value("Medium".to_owned(), 3)),
+            ("ratio".to_owned(), Node::Value("x".to_owned(), 4)),
+        ]);
+        let err = from_node::<Inner>(node, &mut Track::new()).unwrap_err();
+        assert_eq!(err.line(), Some(3));
+
+        let node = Node::Map(vec![
+            ("level".to_owned(), Node::Value("Low".to_owned(), 3)),
+            ("ratio".to_owned(), Node::Value("x".to_owned(), 4)),
+        ]);
+        let err = from_node::<Inner>(node, &mut Track::new()).unwrap_err();
+        assert_eq!(err.line(), Some(4));
+        assert!(err
+            .to_string()
+            .starts_with("line 4: invalid value: string \"x\""));
+    }
+}
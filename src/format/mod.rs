@@ -0,0 +1,759 @@
+use std::path::Path;
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_path_to_error::Track;
+
+use crate::{Error, MaybeSend, VowResult};
+
+pub(crate) mod auto;
+#[cfg(feature = "format-cbor")]
+mod cbor;
+#[cfg(feature = "format-csv")]
+pub(crate) mod csv;
+#[cfg(feature = "format-dotenv")]
+mod dotenv;
+#[cfg(feature = "format-ini")]
+mod ini;
+#[cfg(feature = "format-json")]
+mod json;
+#[cfg(feature = "format-json")]
+mod jsonc;
+#[cfg(feature = "format-kdl")]
+pub(crate) mod kdl;
+#[cfg(any(
+    feature = "format-ini",
+    feature = "format-properties",
+    feature = "format-dotenv",
+    feature = "format-csv"
+))]
+pub(crate) mod key_value;
+#[cfg(feature = "format-postcard")]
+mod postcard;
+#[cfg(feature = "format-properties")]
+mod properties;
+#[cfg(feature = "format-raw")]
+pub(crate) mod raw;
+#[cfg(feature = "format-toml-edit")]
+mod toml_edit;
+#[cfg(feature = "format-xml")]
+mod xml;
+
+pub trait ToFormat: MaybeSend {
+    fn to_format(self) -> Format;
+}
+
+pub trait ResolveFormat: MaybeSend {
+    /// Pick the format of the file at `path`, or `None` if it must be sniffed from the content.
+    fn resolve(self, path: Option<&Path>) -> VowResult<Option<Format>>;
+}
+
+impl<T: ToFormat> ResolveFormat for T {
+    fn resolve(self, _: Option<&Path>) -> VowResult<Option<Format>> {
+        Ok(Some(self.to_format()))
+    }
+}
+
+/// Options controlling how values are written.
+///
+/// Formats support the following options. Building fails with
+/// [`Error::UnsupportedOption`](crate::Error::UnsupportedOption) when `indent`, `sort_keys` or
+/// `pretty_arrays` is set for a format that doesn't support it, while `trailing_newline` is
+/// ignored by binary formats:
+///
+/// | Option             | `json` | `toml` | `toml_edit` | `cbor` | `postcard` | `xml` | `ini`, `properties`, `dotenv` | `csv`  | `kdl`  | `text` | `bytes` |
+/// |--------------------|--------|--------|-------------|--------|------------|-------|-------------------------------|--------|--------|--------|---------|
+/// | `indent`           | yes    |        | arrays      |        |            | yes   |                               |        | yes¹   |        |         |
+/// | `sort_keys`        | yes    |        | yes         | yes    |            |       | yes                           |        | yes    |        |         |
+/// | `trailing_newline` | yes    | always | always      |        |            | yes   | always                        | always | always | yes    |         |
+/// | `pretty_arrays`    |        |        | yes         |        |            |       |                               |        |        |        |         |
+///
+/// ¹ KDL is always written one node per line, indented with 4 spaces when `indent` is `None`.
+///
+/// Plain `toml` is written as is by `basic_toml`: use `toml_edit` to shape TOML output.
+///
+/// In `toml_edit` mode, the options only shape what is newly written: keys that already
+/// exist in the file keep their position and layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct FormatOptions {
+    /// Indentation of nested values. `None` writes compact output.
+    pub indent: Option<Indent>,
+    /// Write map and struct keys in sorted order, for deterministic output.
+    pub sort_keys: bool,
+    /// End the output with a newline.
+    pub trailing_newline: bool,
+    /// Write TOML arrays with one element per line.
+    pub pretty_arrays: bool,
+}
+
+impl FormatOptions {
+    /// Compact output, keys in serialization order, no trailing newline.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            indent: None,
+            sort_keys: false,
+            trailing_newline: false,
+            pretty_arrays: false,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// Fail if `format` can't honour the options.
+    pub(crate) const fn check(self, format: Format) -> VowResult<()> {
+        let (indent, sort_keys, pretty_arrays) = format.shapes();
+        let option = if self.indent.is_some() && !indent {
+            "indent"
+        } else if self.sort_keys && !sort_keys {
+            "sort_keys"
+        } else if self.pretty_arrays && !pretty_arrays {
+            "pretty_arrays"
+        } else {
+            return Ok(());
+        };
+        Err(Error::UnsupportedOption {
+            format: format.name(),
+            option,
+        })
+    }
+}
+
+/// Indentation unit used by [`FormatOptions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Indent {
+    /// Indent with the given number of spaces.
+    Spaces(u8),
+    /// Indent with one tab.
+    Tab,
+}
+
+impl Indent {
+    #[cfg_attr(
+        not(any(
+            feature = "format-json",
+            feature = "format-toml-edit",
+            feature = "format-kdl"
+        )),
+        allow(dead_code)
+    )]
+    pub(crate) fn render(self) -> String {
+        match self {
+            Self::Spaces(n) => " ".repeat(n.into()),
+            Self::Tab => "\t".to_owned(),
+        }
+    }
+}
+
+/// When to quote fields in CSV output.
+#[cfg(feature = "format-csv")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Quoting {
+    /// Only quote fields that contain the delimiter, a quote or a line break.
+    #[default]
+    Necessary,
+    /// Quote every field.
+    Always,
+    /// Quote every field that is not a number.
+    NonNumeric,
+    /// Never quote fields. The output may not be readable back.
+    Never,
+}
+
+/// Line endings of raw text.
+#[cfg(feature = "format-raw")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum LineEnding {
+    /// Keep line endings as they are, both ways.
+    #[default]
+    Preserve,
+    /// Read `\r\n` as `\n`, and write `\n`.
+    Lf,
+    /// Read `\r\n` as `\n`, and write `\r\n`.
+    Crlf,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Auto {
+    pub sniff: bool,
+}
+
+#[cfg(feature = "format-json")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json {
+    pub lenient: bool,
+}
+
+#[cfg(feature = "format-toml")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Toml {}
+
+#[cfg(feature = "format-cbor")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cbor {
+    pub self_describe: bool,
+}
+
+#[cfg(feature = "format-postcard")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Postcard {}
+
+#[cfg(feature = "format-toml-edit")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TomlEdit {}
+
+#[cfg(feature = "format-xml")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Xml {
+    pub root: Option<&'static str>,
+    pub scalar_attributes: bool,
+}
+
+#[cfg(feature = "format-ini")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ini {}
+
+#[cfg(feature = "format-properties")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Properties {}
+
+#[cfg(feature = "format-dotenv")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Dotenv {
+    pub prefix: Option<&'static str>,
+    pub uppercase: bool,
+}
+
+#[cfg(feature = "format-csv")]
+#[derive(Debug, Clone, Copy)]
+pub struct Csv {
+    pub delimiter: u8,
+    pub quote: u8,
+    pub quoting: Quoting,
+}
+
+#[cfg(feature = "format-kdl")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Kdl {}
+
+#[cfg(feature = "format-raw")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Text {
+    pub line_ending: LineEnding,
+    pub strip_newline: bool,
+}
+
+#[cfg(feature = "format-raw")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bytes {}
+
+#[cfg(feature = "format-json")]
+impl ToFormat for Json {
+    fn to_format(self) -> Format {
+        Format::Json {
+            lenient: self.lenient,
+        }
+    }
+}
+
+#[cfg(feature = "format-toml")]
+impl ToFormat for Toml {
+    fn to_format(self) -> Format {
+        Format::Toml
+    }
+}
+
+#[cfg(feature = "format-cbor")]
+impl ToFormat for Cbor {
+    fn to_format(self) -> Format {
+        Format::Cbor {
+            self_describe: self.self_describe,
+        }
+    }
+}
+
+#[cfg(feature = "format-postcard")]
+impl ToFormat for Postcard {
+    fn to_format(self) -> Format {
+        Format::Postcard
+    }
+}
+
+#[cfg(feature = "format-toml-edit")]
+impl ToFormat for TomlEdit {
+    fn to_format(self) -> Format {
+        Format::TomlEdit
+    }
+}
+
+#[cfg(feature = "format-xml")]
+impl ToFormat for Xml {
+    fn to_format(self) -> Format {
+        Format::Xml {
+            root: self.root,
+            scalar_attributes: self.scalar_attributes,
+        }
+    }
+}
+
+#[cfg(feature = "format-ini")]
+impl ToFormat for Ini {
+    fn to_format(self) -> Format {
+        Format::Ini
+    }
+}
+
+#[cfg(feature = "format-properties")]
+impl ToFormat for Properties {
+    fn to_format(self) -> Format {
+        Format::Properties
+    }
+}
+
+#[cfg(feature = "format-dotenv")]
+impl ToFormat for Dotenv {
+    fn to_format(self) -> Format {
+        Format::Dotenv {
+            prefix: self.prefix,
+            uppercase: self.uppercase,
+        }
+    }
+}
+
+#[cfg(feature = "format-csv")]
+impl ToFormat for Csv {
+    fn to_format(self) -> Format {
+        Format::Csv {
+            delimiter: self.delimiter,
+            quote: self.quote,
+            quoting: self.quoting,
+        }
+    }
+}
+
+#[cfg(feature = "format-kdl")]
+impl ToFormat for Kdl {
+    fn to_format(self) -> Format {
+        Format::Kdl
+    }
+}
+
+#[cfg(feature = "format-raw")]
+impl ToFormat for Text {
+    fn to_format(self) -> Format {
+        Format::Text {
+            line_ending: self.line_ending,
+            strip_newline: self.strip_newline,
+        }
+    }
+}
+
+#[cfg(feature = "format-raw")]
+impl ToFormat for Bytes {
+    fn to_format(self) -> Format {
+        Format::Bytes
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "format-json")] {
+        pub type DefaultFormat = Json;
+        impl Json {
+            pub const fn default() -> Self {
+                Self { lenient: false }
+            }
+        }
+    } else if #[cfg(feature = "format-toml")] {
+        pub type DefaultFormat = Toml;
+        impl Toml {
+            pub const fn default() -> Self {
+                Self {}
+            }
+        }
+    } else if #[cfg(feature = "format-cbor")] {
+        pub type DefaultFormat = Cbor;
+        impl Cbor {
+            pub const fn default() -> Self {
+                Self { self_describe: false }
+            }
+        }
+    } else if #[cfg(feature = "format-postcard")] {
+        pub type DefaultFormat = Postcard;
+        impl Postcard {
+            pub const fn default() -> Self {
+                Self {}
+            }
+        }
+    } else if #[cfg(feature = "format-toml-edit")] {
+        pub type DefaultFormat = TomlEdit;
+        impl TomlEdit {
+            pub const fn default() -> Self {
+                Self {}
+            }
+        }
+    } else if #[cfg(feature = "format-xml")] {
+        pub type DefaultFormat = Xml;
+        impl Xml {
+            pub const fn default() -> Self {
+                Self { root: None, scalar_attributes: false }
+            }
+        }
+    } else if #[cfg(feature = "format-ini")] {
+        pub type DefaultFormat = Ini;
+        impl Ini {
+            pub const fn default() -> Self {
+                Self {}
+            }
+        }
+    } else if #[cfg(feature = "format-properties")] {
+        pub type DefaultFormat = Properties;
+        impl Properties {
+            pub const fn default() -> Self {
+                Self {}
+            }
+        }
+    } else if #[cfg(feature = "format-dotenv")] {
+        pub type DefaultFormat = Dotenv;
+        impl Dotenv {
+            pub const fn default() -> Self {
+                Self { prefix: None, uppercase: false }
+            }
+        }
+    } else if #[cfg(feature = "format-csv")] {
+        pub type DefaultFormat = Csv;
+        impl Csv {
+            pub const fn default() -> Self {
+                Self { delimiter: b',', quote: b'"', quoting: Quoting::Necessary }
+            }
+        }
+    } else if #[cfg(feature = "format-kdl")] {
+        pub type DefaultFormat = Kdl;
+        impl Kdl {
+            pub const fn default() -> Self {
+                Self {}
+            }
+        }
+    } else if #[cfg(feature = "format-raw")] {
+        pub type DefaultFormat = Text;
+        impl Text {
+            pub const fn default() -> Self {
+                Self { line_ending: LineEnding::Preserve, strip_newline: false }
+            }
+        }
+    } else {
+        compile_error!("No format feature enabled");
+    }
+}
+
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Format {
+    #[cfg(feature = "format-json")]
+    Json { lenient: bool },
+
+    #[cfg(feature = "format-toml")]
+    Toml,
+
+    #[cfg(feature = "format-cbor")]
+    Cbor { self_describe: bool },
+
+    #[cfg(feature = "format-postcard")]
+    Postcard,
+
+    #[cfg(feature = "format-toml-edit")]
+    TomlEdit,
+
+    #[cfg(feature = "format-xml")]
+    Xml {
+        root: Option<&'static str>,
+        scalar_attributes: bool,
+    },
+
+    #[cfg(feature = "format-ini")]
+    Ini,
+
+    #[cfg(feature = "format-properties")]
+    Properties,
+
+    #[cfg(feature = "format-dotenv")]
+    Dotenv {
+        prefix: Option<&'static str>,
+        uppercase: bool,
+    },
+
+    #[cfg(feature = "format-csv")]
+    Csv {
+        delimiter: u8,
+        quote: u8,
+        quoting: Quoting,
+    },
+
+    #[cfg(feature = "format-kdl")]
+    Kdl,
+
+    #[cfg(feature = "format-raw")]
+    Text {
+        line_ending: LineEnding,
+        strip_newline: bool,
+    },
+
+    #[cfg(feature = "format-raw")]
+    Bytes,
+}
+
+impl Format {
+    /// Name of the format, as its builder method.
+    pub const fn name(self) -> &'static str {
+        match self {
+            #[cfg(feature = "format-json")]
+            Self::Json { .. } => "json",
+            #[cfg(feature = "format-toml")]
+            Self::Toml => "toml",
+            #[cfg(feature = "format-cbor")]
+            Self::Cbor { .. } => "cbor",
+            #[cfg(feature = "format-postcard")]
+            Self::Postcard => "postcard",
+            #[cfg(feature = "format-toml-edit")]
+            Self::TomlEdit => "toml_edit",
+            #[cfg(feature = "format-xml")]
+            Self::Xml { .. } => "xml",
+            #[cfg(feature = "format-ini")]
+            Self::Ini => "ini",
+            #[cfg(feature = "format-properties")]
+            Self::Properties => "properties",
+            #[cfg(feature = "format-dotenv")]
+            Self::Dotenv { .. } => "dotenv",
+            #[cfg(feature = "format-csv")]
+            Self::Csv { .. } => "csv",
+            #[cfg(feature = "format-kdl")]
+            Self::Kdl => "kdl",
+            #[cfg(feature = "format-raw")]
+            Self::Text { .. } => "text",
+            #[cfg(feature = "format-raw")]
+            Self::Bytes => "bytes",
+        }
+    }
+
+    /// Whether the format supports the `indent`, `sort_keys` and `pretty_arrays` options, see
+    /// [`FormatOptions`].
+    const fn shapes(self) -> (bool, bool, bool) {
+        match self {
+            #[cfg(feature = "format-json")]
+            Self::Json { .. } => (true, true, false),
+            #[cfg(feature = "format-toml-edit")]
+            Self::TomlEdit => (true, true, true),
+            #[cfg(feature = "format-cbor")]
+            Self::Cbor { .. } => (false, true, false),
+            #[cfg(feature = "format-xml")]
+            Self::Xml { .. } => (true, false, false),
+            #[cfg(feature = "format-ini")]
+            Self::Ini => (false, true, false),
+            #[cfg(feature = "format-properties")]
+            Self::Properties => (false, true, false),
+            #[cfg(feature = "format-dotenv")]
+            Self::Dotenv { .. } => (false, true, false),
+            #[cfg(feature = "format-kdl")]
+            Self::Kdl => (true, true, false),
+            #[allow(unreachable_patterns)]
+            _ => (false, false, false),
+        }
+    }
+
+    /// Whether the format is human readable text, as opposed to binary.
+    pub const fn is_text(self) -> bool {
+        match self {
+            #[cfg(feature = "format-json")]
+            Self::Json { .. } => true,
+            #[cfg(feature = "format-toml")]
+            Self::Toml => true,
+            #[cfg(feature = "format-toml-edit")]
+            Self::TomlEdit => true,
+            #[cfg(feature = "format-xml")]
+            Self::Xml { .. } => true,
+            #[cfg(feature = "format-ini")]
+            Self::Ini => true,
+            #[cfg(feature = "format-properties")]
+            Self::Properties => true,
+            #[cfg(feature = "format-dotenv")]
+            Self::Dotenv { .. } => true,
+            #[cfg(feature = "format-csv")]
+            Self::Csv { .. } => true,
+            #[cfg(feature = "format-kdl")]
+            Self::Kdl => true,
+            #[cfg(feature = "format-raw")]
+            Self::Text { .. } => true,
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    /// Whether the format reads every value as a string, parsing it by type on demand.
+    #[cfg(any(feature = "versioning", feature = "merge"))]
+    pub const fn reads_strings(self) -> bool {
+        match self {
+            #[cfg(feature = "format-xml")]
+            Self::Xml { .. } => true,
+            #[cfg(feature = "format-ini")]
+            Self::Ini => true,
+            #[cfg(feature = "format-properties")]
+            Self::Properties => true,
+            #[cfg(feature = "format-dotenv")]
+            Self::Dotenv { .. } => true,
+            #[cfg(feature = "format-csv")]
+            Self::Csv { .. } => true,
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+}
+
+pub fn des<T: DeserializeOwned>(format: Format, buf: &[u8]) -> VowResult<T> {
+    des_tracked(format, buf, &mut Track::new())
+}
+
+/// Like [`des`], recording the key of the value that failed to deserialize in the error.
+///
+/// Plain `toml` and `cbor` don't expose their deserializer, so their keys are not known.
+pub fn des_keyed<T: DeserializeOwned>(format: Format, buf: &[u8]) -> VowResult<T> {
+    let mut track = Track::new();
+    des_tracked(format, buf, &mut track).map_err(|err| err.at_key(&track.path()))
+}
+
+#[cfg_attr(
+    not(any(
+        feature = "format-json",
+        feature = "format-postcard",
+        feature = "format-toml-edit",
+        feature = "format-xml",
+        feature = "format-ini",
+        feature = "format-properties",
+        feature = "format-dotenv",
+        feature = "format-csv",
+        feature = "format-kdl"
+    )),
+    allow(unused_variables, clippy::needless_pass_by_ref_mut)
+)]
+fn des_tracked<T: DeserializeOwned>(format: Format, buf: &[u8], track: &mut Track) -> VowResult<T> {
+    let res = match format {
+        #[cfg(feature = "format-json")]
+        Format::Json { lenient: false } => json::des(buf, track)?,
+        #[cfg(feature = "format-json")]
+        Format::Json { lenient: true } => json::des(&jsonc::strip(buf), track)?,
+        #[cfg(feature = "format-toml")]
+        Format::Toml => basic_toml::from_slice(buf)?,
+        #[cfg(feature = "format-cbor")]
+        Format::Cbor { .. } => cbor::des(buf)?,
+        #[cfg(feature = "format-postcard")]
+        Format::Postcard => postcard::des(buf, track)?,
+        #[cfg(feature = "format-toml-edit")]
+        Format::TomlEdit => toml_edit::des(buf, track)?,
+        #[cfg(feature = "format-xml")]
+        Format::Xml {
+            scalar_attributes, ..
+        } => xml::des(buf, scalar_attributes, track)?,
+        #[cfg(feature = "format-ini")]
+        Format::Ini => ini::des(buf, track)?,
+        #[cfg(feature = "format-properties")]
+        Format::Properties => properties::des(buf, track)?,
+        #[cfg(feature = "format-dotenv")]
+        Format::Dotenv { prefix, uppercase } => dotenv::des(buf, prefix, uppercase, track)?,
+        #[cfg(feature = "format-csv")]
+        Format::Csv {
+            delimiter, quote, ..
+        } => csv::des(buf, delimiter, quote, track)?,
+        #[cfg(feature = "format-kdl")]
+        Format::Kdl => kdl::des(buf, track)?,
+        #[cfg(feature = "format-raw")]
+        Format::Text {
+            line_ending,
+            strip_newline,
+        } => raw::des_text(buf, line_ending, strip_newline)?,
+        #[cfg(feature = "format-raw")]
+        Format::Bytes => raw::des_bytes(buf)?,
+    };
+    Ok(res)
+}
+
+#[cfg(all(
+    test,
+    any(
+        feature = "format-json",
+        feature = "format-cbor",
+        feature = "format-postcard",
+        feature = "format-toml-edit",
+        feature = "format-xml",
+        feature = "format-ini",
+        feature = "format-properties",
+        feature = "format-dotenv",
+        feature = "format-csv",
+        feature = "format-kdl",
+        feature = "format-raw"
+    )
+))]
+pub fn se<T: Serialize>(
+    format: Format,
+    options: FormatOptions,
+    writer: &mut Vec<u8>,
+    value: &T,
+) -> VowResult<()> {
+    update(format, options, &[], writer, value)
+}
+
+/// Serialize `value` in place of `previous`, the content last read from or written to the file.
+///
+/// Formats that can't preserve anything from the previous content behave like [`se`].
+#[cfg_attr(not(feature = "format-toml-edit"), allow(unused_variables))]
+pub fn update<T: Serialize>(
+    format: Format,
+    options: FormatOptions,
+    previous: &[u8],
+    writer: &mut Vec<u8>,
+    value: &T,
+) -> VowResult<()> {
+    let start = writer.len();
+    match format {
+        #[cfg(feature = "format-json")]
+        Format::Json { .. } => json::se(writer, value, options)?,
+        #[cfg(feature = "format-toml")]
+        Format::Toml => {
+            writer.extend_from_slice(basic_toml::to_string(value)?.as_bytes());
+        }
+        #[cfg(feature = "format-cbor")]
+        Format::Cbor { self_describe } => {
+            cbor::se(writer, value, self_describe, options.sort_keys)?;
+        }
+        #[cfg(feature = "format-postcard")]
+        Format::Postcard => postcard::se(writer, value)?,
+        #[cfg(feature = "format-toml-edit")]
+        Format::TomlEdit => toml_edit::se(writer, value, previous, options)?,
+        #[cfg(feature = "format-xml")]
+        Format::Xml {
+            root,
+            scalar_attributes,
+        } => xml::se(writer, value, root, scalar_attributes, options)?,
+        #[cfg(feature = "format-ini")]
+        Format::Ini => ini::se(writer, value, options)?,
+        #[cfg(feature = "format-properties")]
+        Format::Properties => properties::se(writer, value, options)?,
+        #[cfg(feature = "format-dotenv")]
+        Format::Dotenv { prefix, uppercase } => {
+            dotenv::se(writer, value, prefix, uppercase, options)?;
+        }
+        #[cfg(feature = "format-csv")]
+        Format::Csv {
+            delimiter,
+            quote,
+            quoting,
+        } => csv::se(writer, value, delimiter, quote, quoting)?,
+        #[cfg(feature = "format-kdl")]
+        Format::Kdl => kdl::se(writer, value, options)?,
+        #[cfg(feature = "format-raw")]
+        Format::Text { line_ending, .. } => raw::se_text(writer, value, line_ending, options)?,
+        #[cfg(feature = "format-raw")]
+        Format::Bytes => raw::se_bytes(writer, value)?,
+    }
+
+    if options.trailing_newline && format.is_text() && !writer[start..].ends_with(b"\n") {
+        writer.push(b'\n');
+    }
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use postcard::{Deserializer, Error};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_path_to_error::Track;
+
+/// Size of the header preceding the payload: little-endian `u32` payload length followed by
+/// little-endian `u32` CRC-32 of the payload.
+///
+/// Postcard is not self-describing, so without the header a truncated or corrupted file could
+/// still decode into a (wrong) value.
+const HEADER_LEN: usize = 8;
+
+pub fn des<T: DeserializeOwned>(buf: &[u8], track: &mut Track) -> Result<T, Error> {
+    let (header, payload) = buf
+        .split_first_chunk::<HEADER_LEN>()
+        .ok_or(Error::DeserializeUnexpectedEnd)?;
+    let (len, crc) = header.split_at(4);
+    let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+    let crc = u32::from_le_bytes(crc.try_into().unwrap());
+
+    match payload.len().cmp(&len) {
+        std::cmp::Ordering::Less => return Err(Error::DeserializeUnexpectedEnd),
+        std::cmp::Ordering::Greater => return Err(Error::DeserializeBadEncoding),
+        std::cmp::Ordering::Equal => {}
+    }
+
+    if crc32fast::hash(payload) != crc {
+        return Err(Error::DeserializeBadCrc);
+    }
+
+    let mut de = Deserializer::from_bytes(payload);
+    let value = T::deserialize(serde_path_to_error::Deserializer::new(&mut de, track))?;
+    if !de.finalize()?.is_empty() {
+        return Err(Error::DeserializeBadEncoding);
+    }
+
+    Ok(value)
+}
+
+pub fn se<T: Serialize>(writer: &mut Vec<u8>, value: &T) -> Result<(), Error> {
+    let start = writer.len();
+    writer.extend_from_slice(&[0; HEADER_LEN]);
+    *writer = postcard::to_extend(value, std::mem::take(writer))?;
+
+    let payload = &writer[start + HEADER_LEN..];
+    let len = u32::try_from(payload.len()).map_err(|_| Error::SerializeBufferFull)?;
+    let crc = crc32fast::hash(payload);
+
+    writer[start..start + 4].copy_from_slice(&len.to_le_bytes());
+    writer[start + 4..start + HEADER_LEN].copy_from_slice(&crc.to_le_bytes());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::format::{self, Format, FormatOptions};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Mode {
+        Off,
+        Level(u8),
+        Range { from: i32, to: i32 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct State {
+        id: u64,
+        name: String,
+        ratio: f64,
+        tags: Vec<String>,
+        limits: BTreeMap<String, u32>,
+        parent: Option<u64>,
+        mode: Mode,
+    }
+
+    fn state() -> State {
+        State {
+            id: 7,
+            name: "queue".to_owned(),
+            ratio: 0.25,
+            tags: vec!["a".to_owned(), "b".to_owned()],
+            limits: [("cpu".to_owned(), 4)].into(),
+            parent: None,
+            mode: Mode::Range { from: -1, to: 10 },
+        }
+    }
+
+    fn encode(value: &State) -> Vec<u8> {
+        let mut buf = vec![];
+        format::se(Format::Postcard, FormatOptions::new(), &mut buf, value).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let buf = encode(&state());
+        assert_eq!(
+            format::des::<State>(Format::Postcard, &buf).unwrap(),
+            state()
+        );
+
+        let mut buf = vec![];
+        format::se(
+            Format::Postcard,
+            FormatOptions::new(),
+            &mut buf,
+            &vec![Mode::Off, Mode::Level(3)],
+        )
+        .unwrap();
+        assert_eq!(
+            format::des::<Vec<Mode>>(Format::Postcard, &buf).unwrap(),
+            vec![Mode::Off, Mode::Level(3)]
+        );
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        let buf = encode(&state());
+
+        for len in 0..buf.len() {
+            let err = format::des::<State>(Format::Postcard, &buf[..len]).unwrap_err();
+            assert!(err.is_invalid_data(), "truncated to {len}: {err}");
+        }
+
+        let mut trailing = buf.clone();
+        trailing.push(0);
+        let err = format::des::<State>(Format::Postcard, &trailing).unwrap_err();
+        assert!(err.is_invalid_data());
+
+        let mut flipped = buf;
+        *flipped.last_mut().unwrap() ^= 0x01;
+        let err = format::des::<State>(Format::Postcard, &flipped).unwrap_err();
+        assert!(err.is_invalid_data());
+
+        // Valid header and checksum, but the payload is a different type with leftover bytes
+        let mut other = vec![];
+        format::se(
+            Format::Postcard,
+            FormatOptions::new(),
+            &mut other,
+            &(1u8, 2u8),
+        )
+        .unwrap();
+        let err = format::des::<u8>(Format::Postcard, &other).unwrap_err();
+        assert!(err.is_invalid_data());
+    }
+}
@@ -0,0 +1,324 @@
+use std::fmt::Write;
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_path_to_error::Track;
+
+use super::{
+    key_value::{self, DeError, Node, SerError},
+    FormatOptions,
+};
+
+/// Parse a `.properties` document, following `java.util.Properties::load`.
+///
+/// Dots in keys denote nesting, so `server.port=80` is read as the field `port` of the struct
+/// in the field `server`. Raw UTF-8 is accepted in addition to `\uXXXX` escapes.
+pub fn des<T: DeserializeOwned>(buf: &[u8], track: &mut Track) -> Result<T, DeError> {
+    let text = key_value::utf8(buf)?;
+    let mut root = vec![];
+    let mut lines = text.lines().enumerate();
+
+    while let Some((index, line)) = lines.next() {
+        let n = index + 1;
+        let line = line.trim_start_matches(is_space);
+        if line.is_empty() || line.starts_with(['#', '!']) {
+            continue;
+        }
+
+        let mut logical = line.to_owned();
+        while continues(&logical) {
+            logical.pop();
+            match lines.next() {
+                Some((_, next)) => logical.push_str(next.trim_start_matches(is_space)),
+                None => break,
+            }
+        }
+
+        let (key, value) = split(&logical).map_err(|msg| DeError::new(n, msg))?;
+        insert(&mut root, &key, Node::Value(value, n)).map_err(|msg| DeError::new(n, msg))?;
+    }
+
+    key_value::from_node(Node::Map(root), track)
+}
+
+const fn is_space(ch: char) -> bool {
+    matches!(ch, ' ' | '\t' | '\x0c')
+}
+
+/// Whether the line ends with an odd number of backslashes, i.e. continues on the next line.
+fn continues(line: &str) -> bool {
+    line.chars().rev().take_while(|&ch| ch == '\\').count() % 2 == 1
+}
+
+/// Split a logical line into its unescaped key and value.
+fn split(line: &str) -> Result<(String, String), &'static str> {
+    let mut chars = line.chars().peekable();
+    let mut key = String::new();
+    while let Some(&ch) = chars.peek() {
+        if ch == '=' || ch == ':' || is_space(ch) {
+            break;
+        }
+        chars.next();
+        if ch == '\\' {
+            key.push(unescape(&mut chars)?);
+        } else {
+            key.push(ch);
+        }
+    }
+
+    while chars.next_if(|&ch| is_space(ch)).is_some() {}
+    if chars.next_if(|&ch| ch == '=' || ch == ':').is_some() {
+        while chars.next_if(|&ch| is_space(ch)).is_some() {}
+    }
+
+    let mut value = String::new();
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            value.push(unescape(&mut chars)?);
+        } else {
+            value.push(ch);
+        }
+    }
+    Ok((key, value))
+}
+
+fn unescape(chars: &mut impl Iterator<Item = char>) -> Result<char, &'static str> {
+    Ok(match chars.next() {
+        Some('t') => '\t',
+        Some('n') => '\n',
+        Some('r') => '\r',
+        Some('f') => '\x0c',
+        Some('u') => {
+            let unit = hex(chars)?;
+            if !(0xd800..0xdc00).contains(&unit) {
+                return char::from_u32(unit.into()).ok_or("invalid unicode escape");
+            }
+            // High surrogate, must be followed by an escaped low surrogate
+            let low = match (chars.next(), chars.next()) {
+                (Some('\\'), Some('u')) => hex(chars)?,
+                _ => return Err("invalid unicode escape"),
+            };
+            char::decode_utf16([unit, low])
+                .next()
+                .and_then(Result::ok)
+                .ok_or("invalid unicode escape")?
+        }
+        Some(ch) => ch,
+        None => return Err("unexpected end of line after `\\`"),
+    })
+}
+
+fn hex(chars: &mut impl Iterator<Item = char>) -> Result<u16, &'static str> {
+    let digits: String = chars.take(4).collect();
+    if digits.len() != 4 {
+        return Err("invalid unicode escape");
+    }
+    u16::from_str_radix(&digits, 16).map_err(|_| "invalid unicode escape")
+}
+
+fn insert(entries: &mut Vec<(String, Node)>, key: &str, value: Node) -> Result<(), String> {
+    let (head, rest) = match key.split_once('.') {
+        Some((head, rest)) => (head, Some(rest)),
+        None => (key, None),
+    };
+    let existing = entries.iter_mut().find(|(k, _)| k == head);
+
+    match (existing, rest) {
+        (None, None) => entries.push((head.to_owned(), value)),
+        (None, Some(rest)) => {
+            let mut nested = vec![];
+            insert(&mut nested, rest, value)?;
+            entries.push((head.to_owned(), Node::Map(nested)));
+        }
+        (Some((_, Node::Map(nested))), Some(rest)) => insert(nested, rest, value)?,
+        (Some(_), None) => return Err(format!("duplicate key `{head}`")),
+        (Some(_), Some(_)) => return Err(format!("`{head}` is both a value and a section")),
+    }
+    Ok(())
+}
+
+/// Write `value`, which must be a struct or map, as `key=value` lines. Nested structs and maps
+/// are flattened into dotted keys, and characters outside of printable ASCII are written as
+/// `\uXXXX` escapes so the output can be read as ISO 8859-1 too.
+pub fn se<T: Serialize>(
+    writer: &mut Vec<u8>,
+    value: &T,
+    options: FormatOptions,
+) -> Result<(), SerError> {
+    let Some(mut node) = key_value::to_node(value)? else {
+        return Ok(());
+    };
+    if options.sort_keys {
+        node.sort();
+    }
+    let Node::Map(entries) = node else {
+        return Err(SerError::new(
+            "properties require a struct or map at the top level",
+        ));
+    };
+
+    let mut out = String::new();
+    write_entries(&mut out, "", entries)?;
+    writer.extend_from_slice(out.as_bytes());
+    Ok(())
+}
+
+fn write_entries(
+    out: &mut String,
+    prefix: &str,
+    entries: Vec<(String, Node)>,
+) -> Result<(), SerError> {
+    for (key, node) in entries {
+        if key.is_empty() || key.contains('.') {
+            let err = SerError::new("keys must be non-empty and can't contain `.`").at(&key);
+            return Err(if prefix.is_empty() {
+                err
+            } else {
+                err.at(prefix)
+            });
+        }
+        let path = if prefix.is_empty() {
+            key
+        } else {
+            format!("{prefix}.{key}")
+        };
+
+        match node {
+            Node::Map(entries) => write_entries(out, &path, entries)?,
+            Node::Value(value, _) => {
+                escape(out, &path, true);
+                out.push('=');
+                escape(out, &value, false);
+                out.push('\n');
+            }
+        }
+    }
+    Ok(())
+}
+
+fn escape(out: &mut String, text: &str, key: bool) {
+    for (i, ch) in text.chars().enumerate() {
+        match ch {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\x0c' => out.push_str("\\f"),
+            ' ' if key || i == 0 => out.push_str("\\ "),
+            '=' | ':' | '#' | '!' if key || i == 0 => {
+                out.push('\\');
+                out.push(ch);
+            }
+            ' '..='~' => out.push(ch),
+            ch => {
+                for unit in ch.encode_utf16(&mut [0; 2]) {
+                    let _ = write!(out, "\\u{unit:04x}");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde::{Deserialize, Serialize};
+
+    use crate::format::{self, Format, FormatOptions};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        #[serde(rename = "app name")]
+        name: String,
+        greeting: String,
+        server: Server,
+    }
+
+    fn config() -> Config {
+        Config {
+            name: "vów".to_owned(),
+            greeting: " hi = 😀\n".to_owned(),
+            server: Server {
+                host: "localhost".to_owned(),
+                port: 8080,
+            },
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut buf = vec![];
+        format::se(
+            Format::Properties,
+            FormatOptions::new(),
+            &mut buf,
+            &config(),
+        )
+        .unwrap();
+        let doc = String::from_utf8(buf).unwrap();
+        assert_eq!(
+            doc,
+            "app\\ name=v\\u00f3w\ngreeting=\\ hi = \\ud83d\\ude00\\n\nserver.host=localhost\nserver.port=8080\n"
+        );
+        assert_eq!(
+            format::des::<Config>(Format::Properties, doc.as_bytes()).unwrap(),
+            config()
+        );
+    }
+
+    #[test]
+    fn test_java_syntax() {
+        let doc = "# comment\n! another\n  app\\ name : vów\ngreeting \\ hi = \\\n    \\ud83d\\ude00\\n\nserver.host localhost\r\nserver.port\t=\t8080\n";
+        assert_eq!(
+            format::des::<Config>(Format::Properties, doc.as_bytes()).unwrap(),
+            config()
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        #[derive(Serialize)]
+        struct Dotted {
+            #[serde(rename = "a.b")]
+            a: u8,
+        }
+
+        let mut buf = vec![];
+        let err = format::se(
+            Format::Properties,
+            FormatOptions::new(),
+            &mut buf,
+            &Dotted { a: 1 },
+        )
+        .unwrap_err();
+        assert!(!err.is_invalid_data());
+        assert_eq!(
+            err.to_string(),
+            "Encode error: `a.b`: keys must be non-empty and can't contain `.`"
+        );
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        for (doc, line) in [
+            ("app\\ name=a\ngreeting=\\u12\n", 2),
+            ("server=a\nserver.port=1\n", 2),
+            ("greeting=a\n\ngreeting=b\n", 3),
+            (
+                "app\\ name=a\ngreeting=b\nserver.host=c\nserver.port=x\n",
+                4,
+            ),
+        ] {
+            let err = format::des::<Config>(Format::Properties, doc.as_bytes()).unwrap_err();
+            assert!(err.is_invalid_data());
+            let crate::Error::KeyValueDecode(err) = err else {
+                panic!("expected a decode error");
+            };
+            assert_eq!(err.line(), Some(line), "{doc}");
+        }
+    }
+}
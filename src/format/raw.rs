@@ -0,0 +1,563 @@
+//! Raw text and bytes, stored verbatim.
+//!
+//! Text mode binds strings and scalars, which are parsed from and written as their text
+//! representation. Bytes mode binds byte buffers, i.e. `Vec<u8>`, `[u8; N]` or
+//! `serde_bytes::ByteBuf`, and strings.
+
+use std::{borrow::Cow, fmt::Display};
+
+use serde::{
+    de::{self, value::SeqDeserializer, DeserializeOwned, IntoDeserializer, Visitor},
+    forward_to_deserialize_any,
+    ser::{self, Impossible, Serialize},
+};
+
+use super::{FormatOptions, LineEnding};
+
+/// Error while reading raw text or bytes: invalid UTF-8, or content that doesn't parse as the
+/// bound type.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct DeError(String);
+
+impl de::Error for DeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+/// Error while writing raw text or bytes, when the value is not a string, scalar or buffer.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct SerError(String);
+
+impl ser::Error for SerError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+/// An empty file is treated as missing rather than as an empty value, so that the default
+/// value gets written.
+fn non_empty(buf: &[u8]) -> Result<(), DeError> {
+    if buf.is_empty() {
+        Err(DeError("empty file".to_owned()))
+    } else {
+        Ok(())
+    }
+}
+
+pub fn des_text<T: DeserializeOwned>(
+    buf: &[u8],
+    line_ending: LineEnding,
+    strip_newline: bool,
+) -> Result<T, DeError> {
+    non_empty(buf)?;
+    let text = std::str::from_utf8(buf).map_err(|err| {
+        let valid = &buf[..err.valid_up_to()];
+        let line = valid.split(|&b| b == b'\n').count();
+        DeError(format!("line {line}: invalid UTF-8"))
+    })?;
+
+    let mut text = match line_ending {
+        LineEnding::Preserve => Cow::Borrowed(text),
+        LineEnding::Lf | LineEnding::Crlf => Cow::Owned(text.replace("\r\n", "\n")),
+    };
+    if strip_newline {
+        let len = text.strip_suffix('\n').map_or(text.len(), |text| {
+            text.strip_suffix('\r').unwrap_or(text).len()
+        });
+        match &mut text {
+            Cow::Borrowed(text) => *text = &text[..len],
+            Cow::Owned(text) => text.truncate(len),
+        }
+    }
+    T::deserialize(TextDeserializer(&text))
+}
+
+pub fn des_bytes<T: DeserializeOwned>(buf: &[u8]) -> Result<T, DeError> {
+    non_empty(buf)?;
+    T::deserialize(BytesDeserializer(buf))
+}
+
+pub fn se_text<T: Serialize>(
+    writer: &mut Vec<u8>,
+    value: &T,
+    line_ending: LineEnding,
+    options: FormatOptions,
+) -> Result<(), SerError> {
+    let out = value.serialize(Serializer(Mode::Text))?;
+    // Only strings can be written in text mode
+    let text = String::from_utf8(out).expect("text mode only writes strings");
+    let newline = match line_ending {
+        LineEnding::Crlf => "\r\n",
+        LineEnding::Preserve | LineEnding::Lf => "\n",
+    };
+    let mut text = match line_ending {
+        LineEnding::Preserve => text,
+        LineEnding::Lf => text.replace("\r\n", "\n"),
+        LineEnding::Crlf => text.replace("\r\n", "\n").replace('\n', "\r\n"),
+    };
+    if options.trailing_newline && !text.ends_with('\n') {
+        text.push_str(newline);
+    }
+    writer.extend_from_slice(text.as_bytes());
+    Ok(())
+}
+
+pub fn se_bytes<T: Serialize>(writer: &mut Vec<u8>, value: &T) -> Result<(), SerError> {
+    writer.extend_from_slice(&value.serialize(Serializer(Mode::Bytes))?);
+    Ok(())
+}
+
+struct TextDeserializer<'a>(&'a str);
+
+impl TextDeserializer<'_> {
+    fn parse<T>(&self) -> Result<T, DeError>
+    where
+        T: std::str::FromStr,
+        T::Err: Display,
+    {
+        self.0
+            .trim()
+            .parse()
+            .map_err(|err| DeError(format!("can't parse `{}`: {err}", self.0.trim())))
+    }
+}
+
+macro_rules! deserialize_parse {
+    ($($method:ident => $visit:ident,)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+            visitor.$visit(self.parse()?)
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for TextDeserializer<'_> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_str(self.0)
+    }
+
+    deserialize_parse! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_enum(self.0.trim().into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+struct BytesDeserializer<'a>(&'a [u8]);
+
+impl<'de> de::Deserializer<'de> for BytesDeserializer<'_> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_bytes(self.0)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        de::Deserializer::deserialize_any(SeqDeserializer::new(self.0.iter().copied()), visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let text = std::str::from_utf8(self.0).map_err(de::Error::custom)?;
+        visitor.visit_str(text)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char bytes byte_buf unit
+        unit_struct tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Text,
+    Bytes,
+    /// Element of a byte sequence
+    Byte,
+}
+
+struct Serializer(Mode);
+
+impl Serializer {
+    fn display(self, value: impl Display) -> Result<Vec<u8>, SerError> {
+        match self.0 {
+            Mode::Text | Mode::Bytes => Ok(value.to_string().into_bytes()),
+            Mode::Byte => Err(self.unsupported("a non-byte value")),
+        }
+    }
+
+    fn unsupported(self, what: &str) -> SerError {
+        SerError(match self.0 {
+            Mode::Text => format!("text mode requires a string or scalar, found {what}"),
+            Mode::Bytes => format!("bytes mode requires a byte buffer or string, found {what}"),
+            Mode::Byte => format!("bytes mode requires a sequence of bytes, found {what}"),
+        })
+    }
+}
+
+macro_rules! serialize_display {
+    ($($method:ident($ty:ty),)*) => {$(
+        fn $method(self, v: $ty) -> Result<Vec<u8>, SerError> {
+            self.display(v)
+        }
+    )*};
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Vec<u8>;
+    type Error = SerError;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = Impossible<Vec<u8>, SerError>;
+    type SerializeTupleVariant = Impossible<Vec<u8>, SerError>;
+    type SerializeMap = Impossible<Vec<u8>, SerError>;
+    type SerializeStruct = Impossible<Vec<u8>, SerError>;
+    type SerializeStructVariant = Impossible<Vec<u8>, SerError>;
+
+    serialize_display! {
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Vec<u8>, SerError> {
+        match self.0 {
+            Mode::Byte => Ok(vec![v]),
+            Mode::Text | Mode::Bytes => self.display(v),
+        }
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Vec<u8>, SerError> {
+        match self.0 {
+            Mode::Bytes => Ok(v.to_vec()),
+            Mode::Text | Mode::Byte => Err(self.unsupported("bytes")),
+        }
+    }
+
+    fn serialize_none(self) -> Result<Vec<u8>, SerError> {
+        Err(self.unsupported("`None`"))
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Vec<u8>, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Vec<u8>, SerError> {
+        Err(self.unsupported("a unit"))
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Vec<u8>, SerError> {
+        Err(self.unsupported("a unit struct"))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Vec<u8>, SerError> {
+        match self.0 {
+            Mode::Text => self.display(variant),
+            Mode::Bytes | Mode::Byte => Err(self.unsupported("an enum")),
+        }
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<Vec<u8>, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<Vec<u8>, SerError> {
+        Err(self.unsupported("an enum"))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, SerError> {
+        match self.0 {
+            Mode::Bytes => Ok(SeqSerializer(Vec::with_capacity(len.unwrap_or_default()))),
+            Mode::Text | Mode::Byte => Err(self.unsupported("a sequence")),
+        }
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, SerError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerError> {
+        Err(self.unsupported("a tuple struct"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerError> {
+        Err(self.unsupported("an enum"))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, SerError> {
+        Err(self.unsupported("a map"))
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, SerError> {
+        Err(self.unsupported("a struct"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, SerError> {
+        Err(self.unsupported("an enum"))
+    }
+}
+
+struct SeqSerializer(Vec<u8>);
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Vec<u8>;
+    type Error = SerError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), SerError> {
+        self.0.extend(value.serialize(Serializer(Mode::Byte))?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Vec<u8>, SerError> {
+        Ok(self.0)
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Vec<u8>;
+    type Error = SerError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), SerError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Vec<u8>, SerError> {
+        Ok(self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde::{Deserialize, Serialize};
+
+    use crate::format::{self, Format, FormatOptions, LineEnding};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Theme {
+        Dark,
+        Light,
+    }
+
+    const TEXT: Format = Format::Text {
+        line_ending: LineEnding::Preserve,
+        strip_newline: false,
+    };
+
+    fn write<T: Serialize>(format: Format, options: FormatOptions, value: &T) -> Vec<u8> {
+        let mut buf = vec![];
+        format::se(format, options, &mut buf, value).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_text() {
+        let token = "s3cr3t \"token\"\r\n".to_owned();
+        let buf = write(TEXT, FormatOptions::new(), &token);
+        assert_eq!(buf, token.as_bytes());
+        assert_eq!(format::des::<String>(TEXT, &buf).unwrap(), token);
+
+        // Scalars are parsed, ignoring surrounding whitespace
+        assert_eq!(format::des::<u32>(TEXT, b"1234\n").unwrap(), 1234);
+        assert_eq!(write(TEXT, FormatOptions::new(), &1234), b"1234");
+
+        assert_eq!(write(TEXT, FormatOptions::new(), &Theme::Dark), b"Dark");
+        assert_eq!(
+            format::des::<Theme>(TEXT, b"Light\n").unwrap(),
+            Theme::Light
+        );
+    }
+
+    #[test]
+    fn test_newlines() {
+        let format = Format::Text {
+            line_ending: LineEnding::Crlf,
+            strip_newline: true,
+        };
+        let options = FormatOptions {
+            trailing_newline: true,
+            ..FormatOptions::new()
+        };
+        let buf = write(format, options, &"a\nb\r\nc");
+        assert_eq!(buf, b"a\r\nb\r\nc\r\n");
+        assert_eq!(format::des::<String>(format, &buf).unwrap(), "a\nb\nc");
+
+        let format = Format::Text {
+            line_ending: LineEnding::Lf,
+            strip_newline: false,
+        };
+        assert_eq!(write(format, options, &"a\r\nb"), b"a\nb\n");
+        assert_eq!(
+            format::des::<String>(format, b"a\r\nb\r\n").unwrap(),
+            "a\nb\n"
+        );
+
+        let format = Format::Text {
+            line_ending: LineEnding::Preserve,
+            strip_newline: true,
+        };
+        assert_eq!(
+            format::des::<String>(format, b"a\r\n\r\n").unwrap(),
+            "a\r\n"
+        );
+    }
+
+    #[test]
+    fn test_bytes() {
+        let blob = vec![0, 159, 146, 150, 255];
+        let buf = write(Format::Bytes, FormatOptions::new(), &blob);
+        assert_eq!(buf, blob);
+        assert_eq!(format::des::<Vec<u8>>(Format::Bytes, &buf).unwrap(), blob);
+        assert_eq!(
+            format::des::<[u8; 5]>(Format::Bytes, &buf).unwrap(),
+            [0, 159, 146, 150, 255]
+        );
+        assert_eq!(
+            format::des::<String>(Format::Bytes, b"text").unwrap(),
+            "text"
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        #[derive(Serialize)]
+        struct Data {
+            value: u8,
+        }
+
+        for format in [TEXT, Format::Bytes] {
+            let mut buf = vec![];
+            let err =
+                format::se(format, FormatOptions::new(), &mut buf, &Data { value: 1 }).unwrap_err();
+            assert!(!err.is_invalid_data());
+        }
+        let mut buf = vec![];
+        let err = format::se(TEXT, FormatOptions::new(), &mut buf, &vec![1_u8]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Encode error: text mode requires a string or scalar, found a sequence"
+        );
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        for (format, buf) in [(TEXT, &b""[..]), (TEXT, b"ok\n\xff"), (Format::Bytes, b"")] {
+            let err = format::des::<String>(format, buf).unwrap_err();
+            assert!(err.is_invalid_data());
+        }
+        let err = format::des::<String>(TEXT, b"ok\n\xff").unwrap_err();
+        assert_eq!(err.to_string(), "Decode error: line 2: invalid UTF-8");
+
+        assert!(format::des::<u32>(TEXT, b"12a")
+            .unwrap_err()
+            .is_invalid_data());
+        assert!(format::des::<[u8; 2]>(Format::Bytes, b"abc")
+            .unwrap_err()
+            .is_invalid_data());
+    }
+}
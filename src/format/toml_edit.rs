@@ -0,0 +1,387 @@
+use serde::{
+    de::{DeserializeOwned, Error as _},
+    Serialize,
+};
+use serde_path_to_error::Track;
+use toml_edit::{de, ser, Array, DocumentMut, Item, Table, Value};
+
+use super::{FormatOptions, Indent};
+
+pub fn des<T: DeserializeOwned>(buf: &[u8], track: &mut Track) -> Result<T, de::Error> {
+    let text = std::str::from_utf8(buf).map_err(de::Error::custom)?;
+    T::deserialize(serde_path_to_error::Deserializer::new(
+        de::Deserializer::parse(text)?,
+        track,
+    ))
+}
+
+/// Serialize `value`, keeping comments, ordering and whitespace of `previous` for every key that
+/// didn't change. Falls back to a fresh document if `previous` is empty or not valid TOML.
+///
+/// `options` only apply to the freshly serialized document, i.e. to keys that are new or changed.
+pub fn se<T: Serialize>(
+    writer: &mut Vec<u8>,
+    value: &T,
+    previous: &[u8],
+    options: FormatOptions,
+) -> Result<(), ser::Error> {
+    let mut new = ser::to_document(value)?;
+    if options.sort_keys {
+        new.as_table_mut().sort_values();
+        new.as_table_mut()
+            .iter_mut()
+            .filter_map(|(_, item)| item.as_value_mut())
+            .for_each(sort);
+    }
+    expand(new.as_table_mut());
+    if options.pretty_arrays {
+        let indent = options.indent.unwrap_or(Indent::Spaces(4)).render();
+        prettify(new.as_table_mut(), &indent);
+    }
+
+    let doc = match std::str::from_utf8(previous).map(str::parse::<DocumentMut>) {
+        Ok(Ok(mut old)) if !previous.is_empty() => {
+            merge_table(old.as_table_mut(), std::mem::take(new.as_table_mut()));
+            old
+        }
+        _ => new,
+    };
+
+    writer.extend_from_slice(doc.to_string().as_bytes());
+    Ok(())
+}
+
+/// Turn the inline tables produced by the serializer into regular `[table]`s and
+/// `[[array.of.tables]]`, the way they are usually written by hand.
+fn expand(table: &mut Table) {
+    for (_, item) in table.iter_mut() {
+        let taken = std::mem::take(item);
+        *item = match taken {
+            Item::Value(Value::InlineTable(inline)) => {
+                let mut table = inline.into_table();
+                expand(&mut table);
+                Item::Table(table)
+            }
+            Item::Value(Value::Array(array))
+                if !array.is_empty() && array.iter().all(Value::is_inline_table) =>
+            {
+                let mut tables = toml_edit::ArrayOfTables::new();
+                for value in array {
+                    let Value::InlineTable(inline) = value else {
+                        unreachable!()
+                    };
+                    let mut table = inline.into_table();
+                    expand(&mut table);
+                    tables.push(table);
+                }
+                Item::ArrayOfTables(tables)
+            }
+            other => other,
+        };
+    }
+
+    let has_values = table.iter().any(|(_, item)| item.is_value());
+    if !has_values && !table.is_empty() {
+        table.set_implicit(true);
+    }
+}
+
+/// Sort inline tables recursively. Must be called before [`expand`], while every nested table
+/// is still inline.
+fn sort(value: &mut Value) {
+    match value {
+        Value::InlineTable(table) => {
+            table.sort_values();
+            table.iter_mut().for_each(|(_, value)| sort(value));
+        }
+        Value::Array(array) => array.iter_mut().for_each(sort),
+        _ => {}
+    }
+}
+
+/// Write non-empty arrays with one element per line.
+fn prettify(table: &mut Table, indent: &str) {
+    for (_, item) in table.iter_mut() {
+        match item {
+            Item::Value(Value::Array(array)) => prettify_array(array, indent),
+            Item::Table(table) => prettify(table, indent),
+            Item::ArrayOfTables(tables) => tables.iter_mut().for_each(|t| prettify(t, indent)),
+            _ => {}
+        }
+    }
+}
+
+fn prettify_array(array: &mut Array, indent: &str) {
+    if array.is_empty() {
+        return;
+    }
+    for value in array.iter_mut() {
+        value.decor_mut().set_prefix(format!("\n{indent}"));
+        value.decor_mut().set_suffix("");
+    }
+    array.set_trailing_comma(true);
+    array.set_trailing("\n");
+}
+
+fn merge(old: &mut Item, new: Item) {
+    match (old, new) {
+        (Item::Table(old), Item::Table(new)) => merge_table(old, new),
+        (Item::ArrayOfTables(old), Item::ArrayOfTables(new)) if old.len() == new.len() => {
+            for (old, new) in old.iter_mut().zip(new) {
+                merge_table(old, new);
+            }
+        }
+        // Keep the inline layout chosen in the file
+        (Item::Value(old @ Value::InlineTable(_)), Item::Table(new)) => {
+            merge_value(old, Value::InlineTable(new.into_inline_table()));
+        }
+        (Item::Value(old @ Value::Array(_)), Item::ArrayOfTables(new)) => {
+            merge_value(old, Value::Array(new.into_array()));
+        }
+        (Item::Value(old), Item::Value(new)) => merge_value(old, new),
+        (old, new) => *old = new,
+    }
+}
+
+fn merge_table(old: &mut Table, new: Table) {
+    old.retain(|key, _| new.contains_key(key));
+    for (key, item) in new {
+        match old.get_mut(&key) {
+            Some(old) => merge(old, item),
+            None => {
+                old.insert(&key, item);
+            }
+        }
+    }
+}
+
+fn merge_value(old: &mut Value, new: Value) {
+    match (old, new) {
+        (Value::InlineTable(old), Value::InlineTable(new)) => {
+            old.retain(|key, _| new.contains_key(key));
+            for (key, value) in new {
+                match old.get_mut(&key) {
+                    Some(old) => merge_value(old, value),
+                    None => {
+                        old.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(old), Value::Array(new)) if old.len() == new.len() => {
+            for (old, new) in old.iter_mut().zip(new) {
+                merge_value(old, new);
+            }
+        }
+        (old, mut new) => {
+            if !same(old, &new) {
+                *new.decor_mut() = old.decor().clone();
+                *old = new;
+            }
+        }
+    }
+}
+
+#[allow(clippy::float_cmp)]
+fn same(old: &Value, new: &Value) -> bool {
+    match (old, new) {
+        (Value::String(old), Value::String(new)) => old.value() == new.value(),
+        (Value::Integer(old), Value::Integer(new)) => old.value() == new.value(),
+        (Value::Float(old), Value::Float(new)) => {
+            old.value() == new.value() || (old.value().is_nan() && new.value().is_nan())
+        }
+        (Value::Boolean(old), Value::Boolean(new)) => old.value() == new.value(),
+        (Value::Datetime(old), Value::Datetime(new)) => old.value() == new.value(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+    use toml_edit::Datetime;
+
+    use crate::format::{self, Format, FormatOptions, Indent};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        debug: bool,
+        updated: Datetime,
+        server: Server,
+        limits: BTreeMap<String, u32>,
+        mirrors: Vec<Server>,
+    }
+
+    const DOC: &str = r#"# Service configuration
+name = "vow"  # display name
+debug = false
+updated = 2024-10-01T12:00:00Z
+
+# Where to listen
+[server]
+port = 8080 # public port
+host = "localhost"
+
+[limits]
+cpu = 2
+
+[[mirrors]]
+host = "a.example"
+port = 1
+"#;
+
+    fn update(previous: &str, value: &Config) -> String {
+        let mut buf = vec![];
+        super::se(&mut buf, value, previous.as_bytes(), FormatOptions::new()).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let config: Config = format::des(Format::TomlEdit, DOC.as_bytes()).unwrap();
+        assert_eq!(config.updated.to_string(), "2024-10-01T12:00:00Z");
+        assert_eq!(config.server.port, 8080);
+
+        let mut buf = vec![];
+        format::se(Format::TomlEdit, FormatOptions::new(), &mut buf, &config).unwrap();
+        assert_eq!(
+            format::des::<Config>(Format::TomlEdit, &buf).unwrap(),
+            config
+        );
+        assert!(String::from_utf8(buf).unwrap().contains("[server]"));
+    }
+
+    #[test]
+    fn test_unchanged() {
+        let config: Config = format::des(Format::TomlEdit, DOC.as_bytes()).unwrap();
+        assert_eq!(update(DOC, &config), DOC);
+    }
+
+    #[test]
+    fn test_preserve_layout() {
+        let mut config: Config = format::des(Format::TomlEdit, DOC.as_bytes()).unwrap();
+        config.server.port = 9090;
+        config.debug = true;
+        config.limits.insert("mem".to_owned(), 512);
+
+        let updated = update(DOC, &config);
+        assert_eq!(
+            updated,
+            DOC.replace("debug = false", "debug = true")
+                .replace("port = 8080", "port = 9090")
+                .replace("cpu = 2\n", "cpu = 2\nmem = 512\n")
+        );
+        assert_eq!(
+            format::des::<Config>(Format::TomlEdit, updated.as_bytes()).unwrap(),
+            config
+        );
+    }
+
+    #[test]
+    fn test_new_table() {
+        let doc = DOC.replace("\n[limits]\ncpu = 2\n", "");
+        let mut config: Config = format::des(Format::TomlEdit, DOC.as_bytes()).unwrap();
+        config.limits.insert("io".to_owned(), 3);
+
+        let updated = update(&doc, &config);
+        assert!(updated.starts_with(&doc));
+        assert!(updated.ends_with("\n[limits]\ncpu = 2\nio = 3\n"));
+    }
+
+    #[test]
+    fn test_removed_and_inline() {
+        let doc = "name = \"vow\" # keep me\ndebug = true\nupdated = 2024-10-01\nserver = { host = \"h\", port = 1 }\nlimits = {}\nmirrors = []\n";
+        let mut config: Config = format::des(Format::TomlEdit, doc.as_bytes()).unwrap();
+        config.server.port = 2;
+        config.mirrors.clear();
+
+        let updated = update(doc, &config);
+        assert_eq!(updated, doc.replace("port = 1", "port = 2"));
+    }
+
+    #[test]
+    fn test_invalid_previous() {
+        let config: Config = format::des(Format::TomlEdit, DOC.as_bytes()).unwrap();
+        let fresh = update("not = [valid", &config);
+        assert_eq!(
+            format::des::<Config>(Format::TomlEdit, fresh.as_bytes()).unwrap(),
+            config
+        );
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        let err = format::des::<Config>(Format::TomlEdit, b"name = ").unwrap_err();
+        assert!(err.is_invalid_data());
+        let err = format::des::<Config>(Format::TomlEdit, b"name = \"vow\"").unwrap_err();
+        assert!(err.is_invalid_data());
+    }
+
+    #[test]
+    fn test_options() {
+        #[derive(Serialize)]
+        struct Inner {
+            z: u8,
+            a: u8,
+        }
+
+        #[derive(Serialize)]
+        struct Outer {
+            list: Vec<u8>,
+            empty: Vec<u8>,
+            b: Inner,
+            a: Inner,
+        }
+
+        let value = Outer {
+            list: vec![1, 2],
+            empty: vec![],
+            b: Inner { z: 1, a: 2 },
+            a: Inner { z: 3, a: 4 },
+        };
+        let options = FormatOptions {
+            indent: Some(Indent::Spaces(2)),
+            sort_keys: true,
+            pretty_arrays: true,
+            ..FormatOptions::new()
+        };
+        let mut buf = vec![];
+        format::se(Format::TomlEdit, options, &mut buf, &value).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "empty = []\nlist = [\n  1,\n  2,\n]\n\n[a]\na = 4\nz = 3\n\n[b]\na = 2\nz = 1\n"
+        );
+    }
+
+    #[test]
+    fn test_vow() {
+        let path = std::env::temp_dir().join("vow-toml-edit.toml");
+        std::fs::write(&path, DOC).unwrap();
+
+        let mut vow = crate::Vow::open(&path)
+            .toml_edit()
+            .with_type::<Config>()
+            .build()
+            .unwrap();
+        vow.update(|config| config.server.port = 9090).unwrap();
+        vow.update(|config| config.name = "vow!".to_owned())
+            .unwrap();
+        vow.force_reload().unwrap();
+
+        assert_eq!(vow.server.port, 9090);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            DOC.replace("port = 8080", "port = 9090")
+                .replace("\"vow\"", "\"vow!\"")
+        );
+    }
+}
@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+
+use quick_xml::{
+    events::{BytesEnd, BytesStart, BytesText, Event},
+    se::Serializer,
+    DeError, Reader, Writer,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_path_to_error::Track;
+
+use super::{FormatOptions, Indent};
+
+pub fn des<T: DeserializeOwned>(
+    buf: &[u8],
+    scalar_attributes: bool,
+    track: &mut Track,
+) -> Result<T, DeError> {
+    let elements;
+    let buf = if scalar_attributes {
+        elements = attributes_to_elements(buf)?;
+        elements.as_slice()
+    } else {
+        buf
+    };
+    let mut de = quick_xml::de::Deserializer::from_reader(buf);
+    T::deserialize(serde_path_to_error::Deserializer::new(&mut de, track))
+}
+
+pub fn se<T: Serialize>(
+    writer: &mut Vec<u8>,
+    value: &T,
+    root: Option<&str>,
+    scalar_attributes: bool,
+    options: FormatOptions,
+) -> Result<(), DeError> {
+    let mut out = String::new();
+    let mut ser = Serializer::with_root(&mut out, root)?;
+    match options.indent {
+        Some(indent) if !scalar_attributes => {
+            let (ch, size) = indent_spec(indent);
+            ser.indent(ch.into(), size);
+        }
+        _ => {}
+    }
+    value.serialize(ser)?;
+
+    if scalar_attributes {
+        elements_to_attributes(out.as_bytes(), writer, options.indent)?;
+    } else {
+        writer.extend_from_slice(out.as_bytes());
+    }
+    Ok(())
+}
+
+const fn indent_spec(indent: Indent) -> (u8, usize) {
+    match indent {
+        Indent::Spaces(n) => (b' ', n as usize),
+        Indent::Tab => (b'\t', 1),
+    }
+}
+
+/// Rewrite every attribute as a child element (`<a b="1"/>` into `<a><b>1</b></a>`), so that
+/// files using either convention can be read into the same structure.
+fn attributes_to_elements(buf: &[u8]) -> Result<Vec<u8>, DeError> {
+    let mut reader = Reader::from_reader(buf);
+    let mut writer = Writer::new(Vec::with_capacity(buf.len()));
+
+    loop {
+        match reader.read_event()? {
+            Event::Eof => break,
+            Event::Start(start) => {
+                let (start, children) = split_attributes(&start)?;
+                writer.write_event(Event::Start(start))?;
+                for (name, value) in children {
+                    write_simple(&mut writer, &name, &value)?;
+                }
+            }
+            Event::Empty(start) => {
+                let (start, children) = split_attributes(&start)?;
+                if children.is_empty() {
+                    writer.write_event(Event::Empty(start))?;
+                } else {
+                    writer.write_event(Event::Start(start.borrow()))?;
+                    for (name, value) in children {
+                        write_simple(&mut writer, &name, &value)?;
+                    }
+                    writer.write_event(Event::End(start.to_end()))?;
+                }
+            }
+            event => writer.write_event(event)?,
+        }
+    }
+
+    Ok(writer.into_inner())
+}
+
+/// Split the attributes of `start` into the ones to keep (namespace declarations) and the ones
+/// to turn into elements, as `(name, escaped value)` pairs.
+fn split_attributes(
+    start: &BytesStart,
+) -> Result<(BytesStart<'static>, Vec<(String, String)>), DeError> {
+    let name = String::from_utf8_lossy(start.name().as_ref()).into_owned();
+    let mut kept = BytesStart::new(name);
+    let mut children = vec![];
+
+    for attr in start.attributes() {
+        let attr = attr.map_err(quick_xml::Error::InvalidAttr)?;
+        let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
+        if key == "xmlns" || key.starts_with("xmlns:") {
+            kept.push_attribute(attr);
+        } else {
+            let value = attr.unescape_value()?;
+            children.push((key, quick_xml::escape::escape(value.as_ref()).into_owned()));
+        }
+    }
+
+    Ok((kept, children))
+}
+
+fn write_simple(writer: &mut Writer<Vec<u8>>, name: &str, escaped: &str) -> Result<(), DeError> {
+    writer.write_event(Event::Start(BytesStart::new(name)))?;
+    writer.write_event(Event::Text(BytesText::from_escaped(escaped)))?;
+    writer.write_event(Event::End(BytesEnd::new(name)))?;
+    Ok(())
+}
+
+enum Node {
+    Element(BytesStart<'static>, Vec<Self>),
+    Other(Event<'static>),
+}
+
+/// Hoist every child element holding only text (`<b>1</b>`) into an attribute of its parent,
+/// unless the name is repeated among its siblings (which means it's a sequence).
+fn elements_to_attributes(
+    buf: &[u8],
+    out: &mut Vec<u8>,
+    indent: Option<Indent>,
+) -> Result<(), DeError> {
+    let mut reader = Reader::from_reader(buf);
+    let mut stack: Vec<(BytesStart<'static>, Vec<Node>)> = vec![];
+    let mut nodes = vec![];
+
+    loop {
+        let node = match reader.read_event()? {
+            Event::Eof => break,
+            Event::Start(start) => {
+                stack.push((start.into_owned(), vec![]));
+                continue;
+            }
+            Event::End(_) => {
+                let (start, children) = stack.pop().expect("reader checks tag balance");
+                Node::Element(start, children)
+            }
+            Event::Empty(start) => Node::Element(start.into_owned(), vec![]),
+            event => Node::Other(event.into_owned()),
+        };
+        match stack.last_mut() {
+            Some((_, children)) => children.push(node),
+            None => nodes.push(node),
+        }
+    }
+
+    let mut writer = match indent {
+        Some(indent) => {
+            let (ch, size) = indent_spec(indent);
+            Writer::new_with_indent(out, ch, size)
+        }
+        None => Writer::new(out),
+    };
+    for node in nodes {
+        write_node(&mut writer, node)?;
+    }
+    Ok(())
+}
+
+fn write_node(writer: &mut Writer<&mut Vec<u8>>, node: Node) -> Result<(), DeError> {
+    let (mut start, children) = match node {
+        Node::Other(event) => return Ok(writer.write_event(event)?),
+        Node::Element(start, children) => (start, children),
+    };
+
+    let mut counts = HashMap::new();
+    for node in &children {
+        if let Node::Element(child, _) = node {
+            *counts.entry(child.name().as_ref().to_vec()).or_insert(0) += 1;
+        }
+    }
+
+    let mut kept = vec![];
+    for node in children {
+        match hoistable(&node, &start, &counts)? {
+            Some((key, value)) => start.push_attribute((key.as_str(), value.as_str())),
+            None => kept.push(node),
+        }
+    }
+
+    if kept.is_empty() {
+        writer.write_event(Event::Empty(start))?;
+        return Ok(());
+    }
+
+    let end = start.to_end().into_owned();
+    writer.write_event(Event::Start(start))?;
+    for node in kept {
+        write_node(writer, node)?;
+    }
+    writer.write_event(Event::End(end))?;
+    Ok(())
+}
+
+fn hoistable(
+    node: &Node,
+    parent: &BytesStart,
+    counts: &HashMap<Vec<u8>, usize>,
+) -> Result<Option<(String, String)>, DeError> {
+    let Node::Element(child, grand) = node else {
+        return Ok(None);
+    };
+    let [Node::Other(Event::Text(text))] = grand.as_slice() else {
+        return Ok(None);
+    };
+
+    let name = child.name();
+    let unique = counts.get(name.as_ref()) == Some(&1);
+    let has_attrs = child.attributes().next().is_some();
+    let taken = parent
+        .attributes()
+        .any(|attr| attr.is_ok_and(|attr| attr.key == name));
+    if !unique || has_attrs || taken {
+        return Ok(None);
+    }
+
+    let key = String::from_utf8_lossy(name.as_ref()).into_owned();
+    Ok(Some((key, text.unescape()?.into_owned())))
+}
+
+#[cfg(test)]
+mod test {
+    use serde::{Deserialize, Serialize};
+
+    use crate::format::{self, Format, FormatOptions, Indent};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Server {
+        #[serde(rename = "@name")]
+        name: String,
+        port: u16,
+        tags: Vec<String>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Plain {
+        name: String,
+        port: u16,
+        tags: Vec<String>,
+    }
+
+    fn server() -> Server {
+        Server {
+            name: "a & b".to_owned(),
+            port: 80,
+            tags: vec!["x".to_owned(), "y".to_owned()],
+        }
+    }
+
+    fn plain() -> Plain {
+        Plain {
+            name: "a & b".to_owned(),
+            port: 80,
+            tags: vec!["x".to_owned()],
+        }
+    }
+
+    fn write<T: Serialize>(format: Format, options: FormatOptions, value: &T) -> String {
+        let mut buf = vec![];
+        format::se(format, options, &mut buf, value).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_prefixed() {
+        let format = Format::Xml {
+            root: None,
+            scalar_attributes: false,
+        };
+        let xml = write(format, FormatOptions::new(), &server());
+        assert_eq!(
+            xml,
+            r#"<Server name="a &amp; b"><port>80</port><tags>x</tags><tags>y</tags></Server>"#
+        );
+        assert_eq!(
+            format::des::<Server>(format, xml.as_bytes()).unwrap(),
+            server()
+        );
+    }
+
+    #[test]
+    fn test_root_and_indent() {
+        let format = Format::Xml {
+            root: Some("server"),
+            scalar_attributes: false,
+        };
+        let options = FormatOptions {
+            indent: Some(Indent::Spaces(2)),
+            trailing_newline: true,
+            ..FormatOptions::new()
+        };
+        let xml = write(format, options, &server());
+        assert_eq!(
+            xml,
+            "<server name=\"a &amp; b\">\n  <port>80</port>\n  <tags>x</tags>\n  <tags>y</tags>\n</server>\n"
+        );
+        assert_eq!(
+            format::des::<Server>(format, xml.as_bytes()).unwrap(),
+            server()
+        );
+    }
+
+    #[test]
+    fn test_scalar_attributes() {
+        let format = Format::Xml {
+            root: Some("server"),
+            scalar_attributes: true,
+        };
+        let xml = write(format, FormatOptions::new(), &plain());
+        assert_eq!(xml, r#"<server name="a &amp; b" port="80" tags="x"/>"#);
+        assert_eq!(
+            format::des::<Plain>(format, xml.as_bytes()).unwrap(),
+            plain()
+        );
+
+        // Elements are still accepted when reading
+        let xml = "<server port=\"80\"><name>a &amp; b</name><tags>x</tags></server>";
+        assert_eq!(
+            format::des::<Plain>(format, xml.as_bytes()).unwrap(),
+            plain()
+        );
+
+        let repeated = Plain {
+            tags: vec!["x".to_owned(), "y".to_owned()],
+            ..plain()
+        };
+        let options = FormatOptions {
+            indent: Some(Indent::Tab),
+            ..FormatOptions::new()
+        };
+        let xml = write(format, options, &repeated);
+        assert_eq!(
+            xml,
+            "<server name=\"a &amp; b\" port=\"80\">\n\t<tags>x</tags>\n\t<tags>y</tags>\n</server>"
+        );
+        assert_eq!(
+            format::des::<Plain>(format, xml.as_bytes()).unwrap(),
+            repeated
+        );
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        let format = Format::Xml {
+            root: None,
+            scalar_attributes: false,
+        };
+        for xml in [
+            "",
+            "<Server",
+            "<Server></Other>",
+            "<Server><port>x</port></Server>",
+        ] {
+            let err = format::des::<Server>(format, xml.as_bytes()).unwrap_err();
+            assert!(err.is_invalid_data(), "{xml}: {err}");
+        }
+    }
+}
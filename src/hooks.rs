@@ -0,0 +1,272 @@
+use std::{fmt, sync::Arc};
+
+use crate::{Error, VowResult};
+
+type Validate<T> = Arc<dyn Fn(&T) -> Result<(), String> + Send + Sync>;
+type AfterLoad<T> = Arc<dyn Fn(&mut T) + Send + Sync>;
+type BeforeSave<T> = Arc<dyn Fn(&T) + Send + Sync>;
+
+/// Hooks run on the value when it's loaded and saved, see
+/// [`VowBuilder::validate`](crate::VowBuilder::validate).
+pub(crate) struct Hooks<T> {
+    validate: Vec<Validate<T>>,
+    after_load: Vec<AfterLoad<T>>,
+    before_save: Vec<BeforeSave<T>>,
+    /// Clone of the value to change, when changes can be rejected
+    stage: Option<fn(&T) -> T>,
+}
+
+impl<T> Hooks<T> {
+    pub const fn new() -> Self {
+        Self {
+            validate: Vec::new(),
+            after_load: Vec::new(),
+            before_save: Vec::new(),
+            stage: None,
+        }
+    }
+
+    pub fn validate<V, E>(&mut self, f: V)
+    where
+        T: Clone,
+        V: Fn(&T) -> Result<(), E> + Send + Sync + 'static,
+        E: fmt::Display,
+    {
+        self.stage = Some(T::clone);
+        self.validate
+            .push(Arc::new(move |value| f(value).map_err(|e| e.to_string())));
+    }
+
+    pub fn after_load(&mut self, f: impl Fn(&mut T) + Send + Sync + 'static) {
+        self.after_load.push(Arc::new(f));
+    }
+
+    pub fn before_save(&mut self, f: impl Fn(&T) + Send + Sync + 'static) {
+        self.before_save.push(Arc::new(f));
+    }
+
+    /// A copy of `value` to make a change on, if the change can be rejected: the value is
+    /// then only replaced once the change is checked.
+    pub fn stage(&self, value: &T) -> Option<T> {
+        self.stage.map(|clone| clone(value))
+    }
+
+    fn check(&self, value: &T) -> VowResult<()> {
+        self.validate
+            .iter()
+            .try_for_each(|f| f(value).map_err(Error::Validate))
+    }
+
+    /// Run on a value read from the file: normalize it, then validate it.
+    pub fn loaded(&self, value: &mut T) -> VowResult<()> {
+        for f in &self.after_load {
+            f(value);
+        }
+        self.check(value)
+    }
+
+    /// Run on a value about to be written: validate it, then let it be observed.
+    pub fn saving(&self, value: &T) -> VowResult<()> {
+        self.check(value)?;
+        for f in &self.before_save {
+            f(value);
+        }
+        Ok(())
+    }
+}
+
+impl<T> Default for Hooks<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for Hooks<T> {
+    fn clone(&self) -> Self {
+        Self {
+            validate: self.validate.clone(),
+            after_load: self.after_load.clone(),
+            before_save: self.before_save.clone(),
+            stage: self.stage,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Hooks<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("validate", &self.validate.len())
+            .field("after_load", &self.after_load.len())
+            .field("before_save", &self.before_save.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, feature = "format-json"))]
+mod test {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::{ErrorKind, Vow};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        port: u16,
+    }
+
+    fn config(name: &str, port: u16) -> Config {
+        Config {
+            name: name.to_owned(),
+            port,
+        }
+    }
+
+    fn check(config: &Config) -> Result<(), String> {
+        if config.port < 1024 {
+            return Err(format!("port {} is reserved", config.port));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_vow() {
+        let path = std::env::temp_dir().join("vow-hooks.json");
+        std::fs::write(&path, r#"{"name":" Ada ","port":8080}"#).unwrap();
+        let saved = Arc::new(AtomicUsize::new(0));
+        let open = || {
+            let saved = saved.clone();
+            Vow::open(&path)
+                .default(config("default", 8080))
+                .validate(check)
+                .after_load(|config| config.name = config.name.trim().to_owned())
+                .before_save(move |_| {
+                    saved.fetch_add(1, Ordering::Relaxed);
+                })
+                .build()
+        };
+
+        // Normalized once loaded
+        let mut vow = open().unwrap();
+        assert_eq!(*vow, config("Ada", 8080));
+        assert_eq!(saved.load(Ordering::Relaxed), 0);
+
+        // Rejected: the previous value is kept, and the file left untouched
+        let written = std::fs::read_to_string(&path).unwrap();
+        let err = vow.set(config("Ada", 80)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Validate);
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "`{}`: Validation error: port 80 is reserved",
+                path.display()
+            )
+        );
+        assert_eq!(*vow, config("Ada", 8080));
+        assert!(vow.update(|config| config.port = 22).is_err());
+        assert!(vow.map(|_| config("Ada", 443)).is_err());
+        assert_eq!(*vow, config("Ada", 8080));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), written);
+        assert_eq!(saved.load(Ordering::Relaxed), 0);
+
+        vow.update(|config| config.port = 8443).unwrap();
+        assert_eq!(saved.load(Ordering::Relaxed), 1);
+
+        // Invalid content is never loaded nor overwritten
+        std::fs::write(&path, r#"{"name":"Ada","port":21}"#).unwrap();
+        assert_eq!(vow.force_reload().unwrap_err().kind(), ErrorKind::Validate);
+        assert_eq!(vow.port, 8443);
+        assert_eq!(open().err().unwrap().kind(), ErrorKind::Validate);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            r#"{"name":"Ada","port":21}"#
+        );
+
+        // Including the default value
+        std::fs::remove_file(&path).unwrap();
+        let err = Vow::open(&path)
+            .default(config("default", 1))
+            .validate(check)
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::Validate);
+        assert_eq!(std::fs::read(&path).unwrap(), b"");
+
+        // Registered once the type is set, before the default
+        let err = Vow::open(&path)
+            .with_type::<Config>()
+            .validate(check)
+            .default(config("default", 1))
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::Validate);
+        assert_eq!(std::fs::read(&path).unwrap(), b"");
+    }
+
+    #[cfg(feature = "format-ini")]
+    #[test]
+    fn test_rejected_update() {
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Session {
+            user: String,
+            timeout: f32,
+            #[serde(skip)]
+            token: Option<String>,
+        }
+
+        let path = std::env::temp_dir().join("vow-hooks-rejected.ini");
+        let _ = std::fs::remove_file(&path);
+        let mut vow = Vow::open(&path)
+            .ini()
+            .default(Session {
+                user: "ada".to_owned(),
+                timeout: 0.1,
+                token: None,
+            })
+            .validate(|session: &Session| match session.timeout {
+                timeout if timeout > 0.0 => Ok(()),
+                _ => Err("timeout must be positive"),
+            })
+            .build()
+            .unwrap();
+        vow.update(|session| session.token = Some("t0k3n".to_owned()))
+            .unwrap();
+        let kept = vow.get().clone();
+
+        // Kept as is, including the field that is never written
+        let err = vow.update(|session| session.timeout = 0.0).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Validate);
+        assert_eq!(*vow, kept);
+        let err = vow
+            .map(|session| Session {
+                timeout: -1.0,
+                ..session
+            })
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Validate);
+        assert_eq!(*vow, kept);
+    }
+
+    #[cfg(feature = "backend-tokio")]
+    #[tokio::test]
+    async fn test_vow_async() {
+        let path = std::env::temp_dir().join("vow-hooks-async.json");
+        let _ = std::fs::remove_file(&path);
+        let mut vow = crate::VowAsync::open_tokio(&path)
+            .default(config("default", 8080))
+            .validate(check)
+            .build()
+            .await
+            .unwrap();
+        assert!(vow.update(|config| config.port = 22).await.is_err());
+        assert_eq!(*vow, config("default", 8080));
+        vow.update(|config| config.port = 8443).await.unwrap();
+        assert_eq!(vow.port, 8443);
+    }
+}
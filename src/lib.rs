@@ -12,13 +12,52 @@
 #![cfg_attr(not(feature = "send"), allow(clippy::future_not_send))]
 
 mod_use::mod_use![r#async, blocking, error, builder, shared];
+#[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+mod compress;
+#[cfg(feature = "encrypt")]
+mod encrypt;
+#[cfg(feature = "envelope")]
+mod envelope;
 mod format;
+mod hooks;
 mod marker;
+#[cfg(feature = "schema")]
+mod schema;
+#[cfg(feature = "sign")]
+mod sign;
+#[cfg(any(
+    feature = "versioning",
+    feature = "merge",
+    feature = "preserve",
+    feature = "schema"
+))]
+mod tree;
+#[cfg(any(feature = "unknown-keys", feature = "preserve"))]
+mod unknown;
+#[cfg(feature = "versioning")]
+mod versioning;
 
-use std::{convert::Infallible, marker::PhantomData, path::Path};
+use std::{borrow::Cow, convert::Infallible, marker::PhantomData, path::Path};
 
+#[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+pub use compress::Compression;
+#[cfg(feature = "sign")]
+pub use ed25519_dalek::{SigningKey, VerifyingKey};
+#[cfg(feature = "encrypt")]
+pub use encrypt::Key;
+#[cfg(feature = "envelope")]
+pub use envelope::EnvelopeError;
 use format::Format;
-use serde::{de::DeserializeOwned, Serialize};
+#[cfg(feature = "format-raw")]
+pub use format::LineEnding;
+#[cfg(feature = "format-csv")]
+pub use format::Quoting;
+pub use format::{FormatOptions, Indent};
+#[cfg(feature = "schema")]
+pub use schema::{json_schema, JsonSchema, SchemaError, SCHEMA_KEY};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+#[cfg(feature = "versioning")]
+pub use versioning::{Value, VERSION_KEY};
 
 use crate::{
     format::DefaultFormat,
@@ -37,6 +76,7 @@ impl<T> Data for T where T: Serialize + DeserializeOwned + MaybeSend {}
 pub struct Vow<T, F> {
     pub(crate) value: Option<T>,
     pub(crate) io: Io<F, Blocking>,
+    pub(crate) hooks: hooks::Hooks<T>,
 }
 
 /// Asynchronously binds data to a file.
@@ -44,6 +84,7 @@ pub struct Vow<T, F> {
 pub struct VowAsync<T, F> {
     pub(crate) value: Option<T>,
     pub(crate) io: Io<F, Async>,
+    pub(crate) hooks: hooks::Hooks<T>,
 }
 
 shared_impl!(Vow<T, F: VowFile>);
@@ -134,21 +175,309 @@ impl<T, F: VowFileAsync> VowAsync<T, F> {
 #[derive(Debug, Clone)]
 struct Io<F, A> {
     pub(crate) file: F,
+    /// Path of the file, when it was opened by path
+    pub(crate) path: Option<std::path::PathBuf>,
     pub(crate) buf: Vec<u8>,
     pub(crate) format: Format,
+    pub(crate) options: FormatOptions,
+    #[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+    pub(crate) compression: Compression,
     pub(crate) asyncness: PhantomData<A>,
     pub(crate) deny_invalid: bool,
+    #[cfg(feature = "encrypt")]
+    pub(crate) cipher: Option<encrypt::Cipher>,
+    #[cfg(feature = "envelope")]
+    pub(crate) envelope: Option<envelope::Envelope>,
+    #[cfg(feature = "sign")]
+    pub(crate) signer: Option<sign::Signer>,
+    #[cfg(feature = "versioning")]
+    pub(crate) versioning: Option<std::sync::Arc<versioning::Versioning>>,
+    /// How to merge the default value, serialized as an untyped tree
+    #[cfg(feature = "merge")]
+    pub(crate) merge: Option<(Merge, std::sync::Arc<serde_value::Value>)>,
+    /// In preserve mode, paths of the keys of the content ignored when it was last loaded
+    #[cfg(feature = "preserve")]
+    pub(crate) preserve: Option<Vec<Vec<String>>>,
+    /// Keys of the content ignored when it was last loaded
+    #[cfg(feature = "unknown-keys")]
+    pub(crate) unknown: Vec<String>,
+    #[cfg(feature = "unknown-keys")]
+    pub(crate) deny_unknown: bool,
+    #[cfg(feature = "schema")]
+    pub(crate) schema: schema::Binding,
+}
+
+/// How the default value is merged into the content, see [`VowBuilder::merge_defaults`].
+#[cfg(feature = "merge")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Merge {
+    /// Only in the loaded value
+    InMemory,
+    /// Also written back to the file
+    WriteBack,
+}
+
+/// Value read from the file.
+pub(crate) struct Decoded<T> {
+    pub value: T,
+    /// Schema version the content was upgraded from
+    pub upgraded: Option<u32>,
+    /// The content was upgraded or completed, and should be written back
+    pub stale: bool,
+    /// Paths of the keys ignored by the deserialization
+    #[cfg(any(feature = "unknown-keys", feature = "preserve"))]
+    pub unknown: Vec<Vec<String>>,
+}
+
+impl<T> Decoded<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            value,
+            upgraded: None,
+            stale: false,
+            #[cfg(any(feature = "unknown-keys", feature = "preserve"))]
+            unknown: Vec::new(),
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Decoded<T> {
+    /// Deserialize the value, recording the keys it ignored.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[cfg(any(feature = "unknown-keys", feature = "preserve"))]
+        {
+            let mut unknown = Vec::new();
+            let value = serde_ignored::deserialize(deserializer, |path| {
+                unknown.push(unknown::key_path(&path));
+            })?;
+            Ok(Self {
+                unknown,
+                ..Self::new(value)
+            })
+        }
+        #[cfg(not(any(feature = "unknown-keys", feature = "preserve")))]
+        T::deserialize(deserializer).map(Self::new)
+    }
 }
 
 impl<F, A> Io<F, A> {
-    pub fn new(file: F, format: Format, deny_invalid: bool) -> Self {
+    pub fn new(file: F, format: Format, options: FormatOptions, deny_invalid: bool) -> Self {
         Self {
             file,
+            path: None,
             buf: Vec::with_capacity(BUF_SIZE),
             format,
+            options,
+            #[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+            compression: Compression::None,
             deny_invalid,
             asyncness: PhantomData,
+            #[cfg(feature = "encrypt")]
+            cipher: None,
+            #[cfg(feature = "envelope")]
+            envelope: None,
+            #[cfg(feature = "sign")]
+            signer: None,
+            #[cfg(feature = "versioning")]
+            versioning: None,
+            #[cfg(feature = "merge")]
+            merge: None,
+            #[cfg(feature = "preserve")]
+            preserve: None,
+            #[cfg(feature = "unknown-keys")]
+            unknown: Vec::new(),
+            #[cfg(feature = "unknown-keys")]
+            deny_unknown: false,
+            #[cfg(feature = "schema")]
+            schema: schema::Binding::default(),
+        }
+    }
+
+    /// Serialize `value` into a new buffer, on top of the content last read or written which is
+    /// left in `self.buf`.
+    pub fn encode<T: Serialize>(&mut self, value: &T) -> VowResult<Vec<u8>> {
+        let (format, options) = (self.format, self.options);
+        let mut buf = Vec::with_capacity(self.buf.len().max(BUF_SIZE));
+        // Nothing can be preserved from corrupted content
+        #[cfg(feature = "versioning")]
+        let version = self.versioning.as_ref().map(|v| (v.key, v.version));
+        #[cfg(feature = "preserve")]
+        let preserve = self.preserve.clone();
+        #[cfg(feature = "schema")]
+        let reference = self.schema.reference.clone();
+        let previous = self.plain().unwrap_or_default();
+        #[cfg(feature = "preserve")]
+        #[allow(unused_mut)]
+        let mut previous_tree = preserve.and_then(|ignored| {
+            let tree = format::des::<serde_value::Value>(format, &previous).ok()?;
+            Some(tree::pick(&tree, &ignored))
+        });
+        // The version is written anew
+        #[cfg(all(feature = "preserve", feature = "versioning"))]
+        if let (Some(serde_value::Value::Map(map)), Some((key, _))) = (&mut previous_tree, version)
+        {
+            map.remove(&serde_value::Value::String(key.to_owned()));
+        }
+        // So is the reference to the schema
+        #[cfg(all(feature = "preserve", feature = "schema"))]
+        if let (Some(serde_value::Value::Map(map)), Some(_)) = (&mut previous_tree, &reference) {
+            map.remove(&serde_value::Value::String(SCHEMA_KEY.to_owned()));
+        }
+        #[cfg(any(feature = "versioning", feature = "preserve", feature = "schema"))]
+        let value = &tree::Layered {
+            value,
+            #[cfg(feature = "schema")]
+            reference: reference.as_deref(),
+            #[cfg(feature = "versioning")]
+            version,
+            #[cfg(feature = "preserve")]
+            previous: previous_tree.as_ref(),
+        };
+        format::update(format, options, &previous, &mut buf, value)?;
+        #[cfg(feature = "envelope")]
+        if let Some(envelope) = self.envelope {
+            buf = envelope.seal(&buf);
+        }
+        #[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+        #[allow(unused_mut)]
+        let mut buf = compress::compress(buf, self.compression)?;
+        #[cfg(feature = "encrypt")]
+        if let Some(cipher) = &mut self.cipher {
+            buf = cipher.encrypt(&buf)?;
+        }
+        #[cfg(feature = "sign")]
+        if let Some(signer) = &self.signer {
+            buf = signer.sign(&buf)?;
+        }
+        Ok(buf)
+    }
+
+    /// Deserialize the content last read, left in `self.buf`. In merge mode, what the content
+    /// lacks is filled from the default value.
+    pub fn decode<T: DeserializeOwned>(&mut self) -> VowResult<Decoded<T>> {
+        let format = self.format;
+        // Decrypted content must not leak into errors
+        #[cfg(feature = "encrypt")]
+        let secret = self.cipher.is_some();
+        #[cfg(not(feature = "encrypt"))]
+        let secret = false;
+        #[cfg(feature = "schema")]
+        let validator = self.schema.validator.clone();
+        #[cfg(feature = "versioning")]
+        let versioning = self.versioning.clone();
+        #[cfg(feature = "merge")]
+        let merge = self.merge.clone();
+        #[cfg(any(feature = "versioning", feature = "merge"))]
+        let layers = tree::Layers {
+            #[cfg(feature = "versioning")]
+            versioning: versioning.as_deref(),
+            #[cfg(feature = "merge")]
+            merge: merge
+                .as_ref()
+                .map(|(merge, default)| (&**default, *merge == Merge::WriteBack)),
+        };
+        let plain = self.plain().during(Operation::Read)?;
+        #[cfg(feature = "schema")]
+        if let Some(validator) = validator {
+            schema::validate(
+                &validator,
+                format,
+                &plain,
+                #[cfg(feature = "versioning")]
+                versioning.as_deref(),
+                #[cfg(feature = "merge")]
+                merge.as_ref().map(|(_, default)| &**default),
+            )
+            .map_err(|err| err.parsing(&plain, secret))?;
+        }
+        #[cfg(any(feature = "versioning", feature = "merge"))]
+        let decoded = tree::decode(format, &plain, &layers);
+        #[cfg(not(any(feature = "versioning", feature = "merge")))]
+        let decoded: VowResult<Decoded<T>> = format::des_keyed(format, &plain);
+        let decoded = decoded.map_err(|err| err.parsing(&plain, secret))?;
+        #[cfg(feature = "unknown-keys")]
+        let decoded = self.check_unknown(decoded).during(Operation::Parse)?;
+        #[cfg(feature = "preserve")]
+        if let Some(ignored) = &mut self.preserve {
+            ignored.clone_from(&decoded.unknown);
+        }
+        Ok(decoded)
+    }
+
+    /// Record the keys ignored in `decoded`, rejecting them in strict mode.
+    #[cfg(feature = "unknown-keys")]
+    #[cfg_attr(
+        not(any(feature = "versioning", feature = "schema")),
+        allow(unused_mut)
+    )]
+    fn check_unknown<T>(&mut self, mut decoded: Decoded<T>) -> VowResult<Decoded<T>> {
+        // The version is read by the versioning layer, not by `T`
+        #[cfg(feature = "versioning")]
+        if let Some(versioning) = &self.versioning {
+            decoded
+                .unknown
+                .retain(|path| !matches!(path.as_slice(), [key] if key == versioning.key));
+        }
+        // So is the reference to the schema, by editors
+        #[cfg(feature = "schema")]
+        if self.schema.reference.is_some() {
+            decoded
+                .unknown
+                .retain(|path| !matches!(path.as_slice(), [key] if key == SCHEMA_KEY));
+        }
+        let unknown: Vec<_> = decoded.unknown.iter().map(|path| path.join(".")).collect();
+        if self.deny_unknown && !unknown.is_empty() {
+            return Err(Error::UnknownKeys(unknown));
+        }
+        self.unknown = unknown;
+        Ok(decoded)
+    }
+
+    /// The content last read, verified, decrypted, decompressed and out of its envelope.
+    #[cfg_attr(not(feature = "encrypt"), allow(clippy::needless_pass_by_ref_mut))]
+    #[cfg_attr(
+        not(any(
+            feature = "sign",
+            feature = "encrypt",
+            feature = "compress-gzip",
+            feature = "compress-zstd",
+            feature = "envelope"
+        )),
+        allow(clippy::unnecessary_wraps, clippy::missing_const_for_fn)
+    )]
+    pub fn plain(&mut self) -> VowResult<Cow<'_, [u8]>> {
+        #[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+        let compression = self.compression;
+        let stored = self.buf.as_slice();
+        #[cfg(feature = "sign")]
+        let stored = match &self.signer {
+            Some(signer) => signer.verify(stored)?,
+            None => stored,
+        };
+        #[cfg(feature = "encrypt")]
+        let buf = match &mut self.cipher {
+            Some(cipher) => Cow::Owned(cipher.decrypt(stored)?),
+            None => Cow::Borrowed(stored),
+        };
+        #[cfg(not(feature = "encrypt"))]
+        let buf = Cow::Borrowed(stored);
+        #[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+        let buf = match buf {
+            Cow::Borrowed(buf) => compress::decompress(buf, compression)?,
+            Cow::Owned(buf) => Cow::Owned(compress::decompress(&buf, compression)?.into_owned()),
+        };
+        #[cfg(feature = "envelope")]
+        if let Some(envelope) = self.envelope {
+            let start = envelope.open(&buf)?;
+            return Ok(match buf {
+                Cow::Borrowed(buf) => Cow::Borrowed(&buf[start..]),
+                Cow::Owned(mut buf) => {
+                    buf.drain(..start);
+                    Cow::Owned(buf)
+                }
+            });
         }
+        Ok(buf)
     }
 
     pub fn take_buf(&mut self) -> Vec<u8> {
@@ -1,15 +1,21 @@
 use std::marker::PhantomData;
 
-use crate::MaybeSend;
+use crate::{hooks::Hooks, MaybeSend};
 
 trait Seal {}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
-pub struct Just<T>(pub(crate) T);
+/// A default value, and the hooks registered for its type.
+#[derive(Debug, Clone)]
+pub struct Just<T>(pub(crate) T, pub(crate) Hooks<T>);
 
+/// No default value, and a type that may still change.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Nothing<T>(pub(crate) PhantomData<T>);
 
+/// No default value, for a type set with `with_type`, and the hooks registered for it.
+#[derive(Debug, Clone)]
+pub struct Typed<T>(pub(crate) Hooks<T>);
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct Async {}
 
@@ -25,21 +31,49 @@ impl<T> Default for Nothing<T> {
 pub trait ToOption: Seal + MaybeSend {
     type Some: MaybeSend;
 
-    fn maybe(self) -> Option<Self::Some>;
+    fn into_parts(self) -> (Option<Self::Some>, Hooks<Self::Some>);
+}
+
+/// A builder state whose type is set, so that hooks can be registered for it.
+pub trait Hooked: ToOption {
+    fn hooks(&mut self) -> &mut Hooks<Self::Some>;
 }
 
 impl<T> Seal for Just<T> {}
 impl<T: MaybeSend> ToOption for Just<T> {
     type Some = T;
-    fn maybe(self) -> Option<T> {
-        Some(self.0)
+
+    fn into_parts(self) -> (Option<T>, Hooks<T>) {
+        (Some(self.0), self.1)
+    }
+}
+
+impl<T: MaybeSend> Hooked for Just<T> {
+    fn hooks(&mut self) -> &mut Hooks<T> {
+        &mut self.1
     }
 }
 
 impl<T> Seal for Nothing<T> {}
 impl<T: MaybeSend> ToOption for Nothing<T> {
     type Some = T;
-    fn maybe(self) -> Option<T> {
-        None
+
+    fn into_parts(self) -> (Option<T>, Hooks<T>) {
+        (None, Hooks::new())
+    }
+}
+
+impl<T> Seal for Typed<T> {}
+impl<T: MaybeSend> ToOption for Typed<T> {
+    type Some = T;
+
+    fn into_parts(self) -> (Option<T>, Hooks<T>) {
+        (None, self.0)
+    }
+}
+
+impl<T: MaybeSend> Hooked for Typed<T> {
+    fn hooks(&mut self) -> &mut Hooks<T> {
+        &mut self.0
     }
 }
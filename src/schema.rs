@@ -0,0 +1,313 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use jsonschema::Validator;
+pub use schemars::JsonSchema;
+use serde_json::Value;
+
+use crate::{
+    format::{self, Format},
+    Error, Operation, ResultExt, VowResult,
+};
+
+/// Key the reference to the schema is written under, see
+/// [`VowBuilder::schema_reference`](crate::VowBuilder::schema_reference).
+pub const SCHEMA_KEY: &str = "$schema";
+
+/// JSON Schema of `T`, as generated by schemars.
+#[must_use]
+pub fn json_schema<T: JsonSchema>() -> Value {
+    schemars::schema_for!(T).to_value()
+}
+
+/// A value of the content that doesn't match the JSON Schema, see
+/// [`Error::Schema`](crate::Error::Schema).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    /// JSON pointer to the value, e.g. `/server/port`, empty for the whole content
+    pub path: String,
+    /// What doesn't match
+    pub message: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "/"
+        } else {
+            &self.path
+        };
+        write!(f, "{path}: {}", self.message)
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// What the builder does with the JSON Schema of the value.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Options {
+    /// Schema the content is validated against
+    pub validate: Option<Value>,
+    /// Written under [`SCHEMA_KEY`]
+    pub reference: Option<String>,
+    /// Schema written to a file when building
+    pub file: Option<(PathBuf, Value)>,
+}
+
+impl Options {
+    pub const fn new() -> Self {
+        Self {
+            validate: None,
+            reference: None,
+            file: None,
+        }
+    }
+
+    /// The schema file to write, with its content.
+    fn file(&self) -> VowResult<Option<(&Path, Vec<u8>)>> {
+        let Some((path, schema)) = &self.file else {
+            return Ok(None);
+        };
+        let mut json = serde_json::to_vec_pretty(schema).map_err(io::Error::from)?;
+        json.push(b'\n');
+        Ok(Some((path, json)))
+    }
+
+    /// Write the schema file.
+    pub fn write_file(&self) -> VowResult<()> {
+        if let Some((path, json)) = self.file()? {
+            std::fs::write(path, json)
+                .during(Operation::Write)
+                .in_file(Some(path))?;
+        }
+        Ok(())
+    }
+
+    /// Like [`write_file`](Self::write_file), through the asynchronous file API.
+    pub async fn write_file_async<F: crate::VowFileAsync>(&self) -> VowResult<()> {
+        if let Some((path, json)) = self.file()? {
+            F::write_new(path, json)
+                .await
+                .during(Operation::Write)
+                .in_file(Some(path))?;
+        }
+        Ok(())
+    }
+
+    /// Compile the schema to validate against.
+    pub fn bind(self) -> VowResult<Binding> {
+        let validator = self
+            .validate
+            .map(|schema| {
+                jsonschema::validator_for(&schema).map_err(|err| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("invalid JSON Schema: {err}"),
+                    )
+                })
+            })
+            .transpose()?;
+        Ok(Binding {
+            validator: validator.map(Arc::new),
+            reference: self.reference,
+        })
+    }
+}
+
+/// The JSON Schema, as used when reading and writing.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Binding {
+    pub validator: Option<Arc<Validator>>,
+    pub reference: Option<String>,
+}
+
+/// Validate `buf` against the schema, reporting every value that doesn't match.
+///
+/// Empty content is left to the format, so that a new file receives the default value. Content
+/// from an older schema version is left to its migrations. Content merged over a default value
+/// is validated once merged.
+pub fn validate(
+    validator: &Validator,
+    format: Format,
+    buf: &[u8],
+    #[cfg(feature = "versioning")] versioning: Option<&crate::versioning::Versioning>,
+    #[cfg(feature = "merge")] merge: Option<&serde_value::Value>,
+) -> VowResult<()> {
+    if buf.is_empty() {
+        return Ok(());
+    }
+    let document: Value = format::des(format, buf)?;
+    #[cfg(feature = "versioning")]
+    if versioning.is_some_and(|versioning| {
+        serde_value::to_value(&document).is_ok_and(|tree| !versioning.is_current(&tree))
+    }) {
+        return Ok(());
+    }
+    #[cfg(feature = "merge")]
+    let document = match merge {
+        Some(default) => {
+            let mut tree = serde_value::to_value(document).map_err(io::Error::other)?;
+            crate::tree::fill(&mut tree, default.clone());
+            serde_json::to_value(tree).map_err(io::Error::from)?
+        }
+        None => document,
+    };
+    let errors: Vec<_> = validator
+        .iter_errors(&document)
+        .map(|err| SchemaError {
+            path: err.instance_path().to_string(),
+            message: err.to_string(),
+        })
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Schema(errors))
+    }
+}
+
+#[cfg(all(test, feature = "format-json"))]
+mod test {
+    use serde::{Deserialize, Serialize};
+
+    use super::{json_schema, JsonSchema};
+    use crate::{Error, Operation, Vow};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+    struct Config {
+        name: String,
+        port: u16,
+        tls: Tls,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+    struct Tls {
+        enabled: bool,
+    }
+
+    fn config() -> Config {
+        Config {
+            name: "vow".to_owned(),
+            port: 8080,
+            tls: Tls { enabled: false },
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let path = std::env::temp_dir().join("vow-schema.json");
+        let open = || Vow::open(&path).default(config()).validate_schema().build();
+
+        // A new file receives the default value
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(*open().unwrap(), config());
+
+        // Every mismatch is reported, and the file is left untouched
+        let content = r#"{"name":1,"port":-1,"tls":{"enabled":"yes"}}"#;
+        std::fs::write(&path, content).unwrap();
+        let err = open().err().unwrap();
+        let Error::Schema(errors) = err.inner() else {
+            panic!("expected a schema error");
+        };
+        let paths: Vec<_> = errors.iter().map(|err| err.path.as_str()).collect();
+        assert_eq!(paths, ["/name", "/port", "/tls/enabled"]);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+
+        std::fs::write(&path, r#"{"name":"vow","port":80}"#).unwrap();
+        let err = open().err().unwrap();
+        assert!(matches!(err.inner(), Error::Schema(errors) if errors.len() == 1));
+        assert_eq!(err.operation(), Some(Operation::Parse));
+        assert_eq!(
+            err.inner().to_string(),
+            "Schema error: /: \"tls\" is a required property"
+        );
+    }
+
+    #[test]
+    fn test_reference() {
+        let dir = std::env::temp_dir().join("vow-schema");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+        let schema = dir.join("config.schema.json");
+        let open = || {
+            Vow::open(&path)
+                .default(config())
+                .schema_file(&schema)
+                .schema_reference("./config.schema.json")
+                .validate_schema()
+                .build()
+                .unwrap()
+        };
+
+        let mut vow = open();
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&schema).unwrap()).unwrap();
+        assert_eq!(written, json_schema::<Config>());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            r#"{"$schema":"./config.schema.json","name":"vow","port":8080,"tls":{"enabled":false}}"#
+        );
+
+        // Read back, and written once
+        vow.update(|config| config.port = 8443).unwrap();
+        vow.force_reload().unwrap();
+        assert_eq!(vow.port, 8443);
+        #[cfg(feature = "unknown-keys")]
+        assert!(vow.unknown_keys().is_empty());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            r#"{"$schema":"./config.schema.json","name":"vow","port":8443,"tls":{"enabled":false}}"#
+        );
+        assert_eq!(open().port, 8443);
+    }
+
+    #[cfg(feature = "backend-tokio")]
+    #[tokio::test]
+    async fn test_schema_file_async() {
+        let dir = std::env::temp_dir().join("vow-schema-async");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let schema = dir.join("config.schema.json");
+        crate::VowAsync::open_tokio(dir.join("config.json"))
+            .default(config())
+            .schema_file(&schema)
+            .build()
+            .await
+            .unwrap();
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&schema).unwrap()).unwrap();
+        assert_eq!(written, json_schema::<Config>());
+    }
+
+    #[cfg(feature = "merge")]
+    #[test]
+    fn test_merged() {
+        let path = std::env::temp_dir().join("vow-schema-merged.json");
+        let open = || {
+            Vow::open(&path)
+                .merge_defaults()
+                .default(config())
+                .validate_schema()
+                .build()
+        };
+
+        // What the file lacks comes from the default value
+        std::fs::write(&path, r#"{"port":80}"#).unwrap();
+        assert_eq!(
+            *open().unwrap(),
+            Config {
+                port: 80,
+                ..config()
+            }
+        );
+
+        // What it has is still validated
+        std::fs::write(&path, r#"{"port":-1}"#).unwrap();
+        let err = open().err().unwrap();
+        assert!(matches!(err.inner(), Error::Schema(errors) if errors[0].path == "/port"));
+    }
+}
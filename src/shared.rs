@@ -1,17 +1,25 @@
 macro_rules! shared_impl {
     ($Vow:ident<$T:ident, $F:ident: $bound:ident> $(, $async:tt + $await:tt)?) => {
         const _: () = {
-            use crate::Data;
+            use crate::{Data, Operation, ResultExt};
 
             const NO_VALUE: &str =
                 "Value was taken and not returned, likely due to an error during async io";
 
             impl<$T, $F> $Vow<$T, $F> {
                 /// Get the value.
-                pub fn get(&self) -> &T {
+                pub const fn get(&self) -> &T {
                     &self.value.as_ref().expect(NO_VALUE)
                 }
 
+                /// Paths of the keys of the file that the value ignored when it was last loaded,
+                /// e.g. `server.prot` for a misspelled `port`. Elements of sequences are
+                /// numbered from `0`.
+                #[cfg(feature = "unknown-keys")]
+                pub fn unknown_keys(&self) -> &[String] {
+                    &self.io.unknown
+                }
+
                 pub(crate) fn take(&mut self) -> T {
                     std::mem::take(&mut self.value).expect(NO_VALUE)
                 }
@@ -23,7 +31,9 @@ macro_rules! shared_impl {
             {
                 /// Set the value.
                 pub $($async)? fn set(&mut self, value: T) -> VowResult<()> {
-                    self.value = Some(self.io.sync(Some(value), true)$(.$await)??);
+                    self.writable()?;
+                    let value = self.io.sync(Some(value), true, &self.hooks)$(.$await)?;
+                    self.value = Some(value.in_file(self.io.path.as_deref())?);
                     Ok(())
                 }
 
@@ -32,9 +42,12 @@ macro_rules! shared_impl {
                 where
                     U: FnOnce(T) -> T + MaybeSend,
                 {
-                    let val = f(self.take());
-                    self.value = Some(self.io.sync(Some(val), true)$(.$await)??);
-                    Ok(())
+                    self.writable()?;
+                    let val = match self.hooks.stage(self.get()) {
+                        Some(staged) => f(staged),
+                        None => f(self.take()),
+                    };
+                    self.set(val)$(.$await)?
                 }
 
                 /// Update the value.
@@ -42,23 +55,42 @@ macro_rules! shared_impl {
                 where
                     U: FnOnce(&mut T) + MaybeSend,
                 {
-                    let mut val = self.take();
+                    self.writable()?;
+                    let mut val = match self.hooks.stage(self.get()) {
+                        Some(staged) => staged,
+                        None => self.take(),
+                    };
                     f(&mut val);
-                    self.value = Some(self.io.sync(Some(val), true)$(.$await)??);
+                    self.set(val)$(.$await)?
+                }
+
+                /// Fail before the value is taken if the file can't be written, e.g. when its
+                /// signature is only verified.
+                #[allow(clippy::missing_const_for_fn)]
+                #[cfg_attr(not(feature = "sign"), allow(clippy::unused_self))]
+                fn writable(&self) -> VowResult<()> {
+                    #[cfg(feature = "sign")]
+                    if matches!(self.io.signer, Some(crate::sign::Signer::Verify(_))) {
+                        return Err(crate::Error::ReadOnly).in_file(self.io.path.as_deref());
+                    }
                     Ok(())
                 }
 
                 /// Force reload the value.
                 pub $($async)? fn force_reload(&mut self) -> VowResult<()> {
-                    self.value = Some(self.io.sync(None, false)$(.$await)??);
+                    let value = self.io.sync(None, false, &self.hooks)$(.$await)?;
+                    self.value = Some(value.in_file(self.io.path.as_deref())?);
                     Ok(())
                 }
 
 
                 /// Flush the content down to disk.
                 pub $($async)? fn flush(&mut self) -> VowResult<()> {
-                    self.io.file.flush()$(.$await)??;
-                    Ok(())
+                    self.io
+                        .file
+                        .flush()$(.$await)?
+                        .during(Operation::Flush)
+                        .in_file(self.io.path.as_deref())
                 }
             }
 
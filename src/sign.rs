@@ -0,0 +1,215 @@
+use ed25519_dalek::{Signature, Signer as _, SigningKey, VerifyingKey, SIGNATURE_LENGTH};
+
+use crate::{Error, VowResult};
+
+const MAGIC: &[u8; 4] = b"VOWG";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = MAGIC.len() + 1;
+
+/// Signs the stored content with ed25519, as a header made of:
+///
+/// - the magic bytes `VOWG` and a version byte;
+/// - the signature of the whole file but itself.
+///
+/// With only the public key, content is verified but can't be written.
+#[derive(Debug, Clone)]
+pub(crate) enum Signer {
+    Sign(SigningKey),
+    Verify(VerifyingKey),
+}
+
+fn verify_error(msg: &str) -> Error {
+    Error::Verify(msg.to_owned())
+}
+
+impl Signer {
+    fn verifying_key(&self) -> VerifyingKey {
+        match self {
+            Self::Sign(key) => key.verifying_key(),
+            Self::Verify(key) => *key,
+        }
+    }
+
+    /// Sign `content`, failing with [`Error::ReadOnly`] without the private key.
+    pub fn sign(&self, content: &[u8]) -> VowResult<Vec<u8>> {
+        let Self::Sign(key) = self else {
+            return Err(Error::ReadOnly);
+        };
+        let mut message = Vec::with_capacity(HEADER_LEN + content.len());
+        message.extend_from_slice(MAGIC);
+        message.push(VERSION);
+        message.extend_from_slice(content);
+        let signature = key.sign(&message);
+
+        let mut out = Vec::with_capacity(message.len() + SIGNATURE_LENGTH);
+        out.extend_from_slice(&message[..HEADER_LEN]);
+        out.extend_from_slice(&signature.to_bytes());
+        out.extend_from_slice(content);
+        Ok(out)
+    }
+
+    /// Verify `buf`, returning the signed content. Empty content is returned as is when the
+    /// file can be signed, so that a new file receives the default value.
+    pub fn verify<'a>(&self, buf: &'a [u8]) -> VowResult<&'a [u8]> {
+        if buf.is_empty() && matches!(self, Self::Sign(_)) {
+            return Ok(buf);
+        }
+        let rest = buf
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| verify_error("the file is not signed"))?;
+        let (&version, rest) = rest
+            .split_first()
+            .ok_or_else(|| verify_error("truncated header"))?;
+        if version != VERSION {
+            return Err(Error::Verify(format!("unsupported version {version}")));
+        }
+        let (signature, content) = rest
+            .split_first_chunk::<SIGNATURE_LENGTH>()
+            .ok_or_else(|| verify_error("truncated header"))?;
+
+        let mut message = Vec::with_capacity(HEADER_LEN + content.len());
+        message.extend_from_slice(&buf[..HEADER_LEN]);
+        message.extend_from_slice(content);
+        self.verifying_key()
+            .verify_strict(&message, &Signature::from_bytes(signature))
+            .map_err(|_| {
+                verify_error("the signature doesn't match: wrong key, or the file was changed")
+            })?;
+        Ok(content)
+    }
+}
+
+#[cfg(all(test, feature = "format-json"))]
+mod test {
+    use ed25519_dalek::SigningKey;
+    use serde::{Deserialize, Serialize};
+
+    use super::Signer;
+    use crate::{Error, ErrorKind, Vow};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        url: String,
+    }
+
+    fn config(url: &str) -> Config {
+        Config {
+            url: url.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_verify() {
+        let key = SigningKey::from_bytes(&[1; 32]);
+        let admin = Signer::Sign(key.clone());
+        let signed = admin.sign(b"content").unwrap();
+        assert!(signed.ends_with(b"content"));
+
+        let verifier = Signer::Verify(key.verifying_key());
+        assert_eq!(verifier.verify(&signed).unwrap(), b"content");
+        assert_eq!(admin.verify(b"").unwrap(), b"");
+        assert!(matches!(verifier.sign(b"content"), Err(Error::ReadOnly)));
+
+        let mut changed = signed.clone();
+        *changed.last_mut().unwrap() ^= 1;
+        let other = Signer::Verify(SigningKey::from_bytes(&[2; 32]).verifying_key());
+        for (err, msg) in [
+            (
+                verifier.verify(&changed),
+                "the signature doesn't match: wrong key, or the file was changed",
+            ),
+            (
+                other.verify(&signed),
+                "the signature doesn't match: wrong key, or the file was changed",
+            ),
+            (verifier.verify(b"content"), "the file is not signed"),
+            (verifier.verify(b""), "the file is not signed"),
+            (verifier.verify(&signed[..20]), "truncated header"),
+        ] {
+            let err = err.unwrap_err();
+            assert!(!err.is_invalid_data());
+            assert_eq!(err.to_string(), format!("Verification error: {msg}"));
+        }
+    }
+
+    #[test]
+    fn test_vow() {
+        let path = std::env::temp_dir().join("vow-sign.json");
+        let _ = std::fs::remove_file(&path);
+        let key = SigningKey::from_bytes(&[1; 32]);
+
+        // The admin writes the file
+        let mut vow = Vow::open(&path)
+            .sign(key.clone())
+            .default(config("default"))
+            .build()
+            .unwrap();
+        vow.set(config("https://example.com")).unwrap();
+        let signed = std::fs::read(&path).unwrap();
+
+        // Clients only read it
+        let open = || {
+            Vow::open(&path)
+                .verify(key.verifying_key())
+                .default(config("default"))
+                .build()
+        };
+        let mut vow = open().unwrap();
+        assert_eq!(vow.url, "https://example.com");
+        let err = vow.set(config("https://evil.com")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ReadOnly);
+        assert_eq!(std::fs::read(&path).unwrap(), signed);
+
+        // The value is kept
+        let err = vow.update(|config| config.url.clear()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ReadOnly);
+        let err = vow.map(|_| config("https://evil.com")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ReadOnly);
+        assert_eq!(vow.get().url, "https://example.com");
+
+        // Changed locally: refused, and never overwritten
+        std::fs::write(&path, b"{\"url\":\"https://evil.com\"}").unwrap();
+        assert_eq!(open().err().unwrap().kind(), ErrorKind::Verify);
+        let mut changed = signed;
+        let len = changed.len();
+        changed[len - 3] = b'x';
+        std::fs::write(&path, &changed).unwrap();
+        assert_eq!(open().err().unwrap().kind(), ErrorKind::Verify);
+        assert_eq!(std::fs::read(&path).unwrap(), changed);
+    }
+
+    #[test]
+    fn test_read_only_file() {
+        let path = std::env::temp_dir().join("vow-sign-read-only.json");
+        let key = SigningKey::from_bytes(&[1; 32]);
+        let open = || {
+            Vow::open(&path)
+                .verify(key.verifying_key())
+                .default(config("default"))
+                .build()
+        };
+
+        // Never created
+        let _ = std::fs::remove_file(&path);
+        let err = open().err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::Io);
+        assert!(!path.exists());
+
+        // Shipped read-only by an admin
+        let mut vow = Vow::open(&path)
+            .sign(key.clone())
+            .default(config("https://example.com"))
+            .build()
+            .unwrap();
+        vow.flush().unwrap();
+        drop(vow);
+        let mut permissions = std::fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(&path, permissions.clone()).unwrap();
+        let vow = open();
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        std::fs::set_permissions(&path, permissions).unwrap();
+        assert_eq!(vow.unwrap().url, "https://example.com");
+    }
+}
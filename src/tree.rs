@@ -0,0 +1,912 @@
+#[cfg(any(feature = "versioning", feature = "preserve", feature = "schema"))]
+use std::collections::{BTreeMap, BTreeSet};
+
+#[cfg(any(feature = "versioning", feature = "preserve", feature = "schema"))]
+use serde::{
+    ser::{self, Impossible, SerializeMap, SerializeStruct},
+    Serialize, Serializer,
+};
+use serde_value::Value;
+#[cfg(any(feature = "versioning", feature = "merge"))]
+use {
+    crate::{
+        format::{self, Format},
+        Decoded, VowResult,
+    },
+    serde::{
+        de::{
+            self,
+            value::{MapDeserializer, SeqDeserializer},
+            DeserializeOwned, IntoDeserializer, Unexpected, Visitor,
+        },
+        forward_to_deserialize_any, Deserialize, Deserializer,
+    },
+    serde_value::{DeserializerError, ValueDeserializer},
+};
+
+#[cfg(any(feature = "versioning", feature = "merge"))]
+/// What the content goes through as an untyped tree before being deserialized.
+pub(crate) struct Layers<'a> {
+    /// Schema version to upgrade the content to
+    #[cfg(feature = "versioning")]
+    pub versioning: Option<&'a crate::versioning::Versioning>,
+    /// Default value filling what the content lacks, and whether completed content is written
+    /// back
+    #[cfg(feature = "merge")]
+    pub merge: Option<(&'a Value, bool)>,
+}
+
+#[cfg(any(feature = "versioning", feature = "merge"))]
+impl Layers<'_> {
+    const fn is_empty(&self) -> bool {
+        #[cfg(feature = "versioning")]
+        if self.versioning.is_some() {
+            return false;
+        }
+        #[cfg(feature = "merge")]
+        if self.merge.is_some() {
+            return false;
+        }
+        true
+    }
+
+    #[cfg_attr(
+        not(feature = "versioning"),
+        allow(
+            clippy::unnecessary_wraps,
+            clippy::unused_self,
+            clippy::missing_const_for_fn
+        )
+    )]
+    fn upgrade(&self, tree: Value) -> VowResult<(Value, Option<u32>)> {
+        #[cfg(feature = "versioning")]
+        if let Some(versioning) = self.versioning {
+            return versioning.upgrade(tree);
+        }
+        Ok((tree, None))
+    }
+
+    /// Fill what `tree` lacks from the default value. Returns whether it should be written back
+    /// if anything was filled.
+    #[cfg_attr(
+        not(feature = "merge"),
+        allow(
+            clippy::unused_self,
+            clippy::missing_const_for_fn,
+            clippy::needless_pass_by_ref_mut
+        )
+    )]
+    fn complete(&self, tree: &mut Value) -> Option<bool> {
+        #[cfg(feature = "merge")]
+        if let Some((default, write_back)) = self.merge {
+            return fill(tree, default.clone()).then_some(write_back);
+        }
+        let _ = tree;
+        None
+    }
+}
+
+#[cfg(any(feature = "versioning", feature = "merge"))]
+/// Deserialize `buf` through `layers`.
+///
+/// Content that is left as is by every layer is deserialized straight from `buf`, so that errors
+/// come from the format, with their position.
+pub fn decode<T>(format: Format, buf: &[u8], layers: &Layers<'_>) -> VowResult<Decoded<T>>
+where
+    T: DeserializeOwned,
+{
+    if layers.is_empty() {
+        return format::des_keyed(format, buf);
+    }
+    let tree: Value = format::des(format, buf)?;
+    #[cfg_attr(not(feature = "versioning"), allow(unused_variables))]
+    let (mut tree, upgraded) = layers.upgrade(tree)?;
+    let completed = layers.complete(&mut tree);
+    #[cfg(feature = "versioning")]
+    if let Some(from) = upgraded {
+        let mut track = serde_path_to_error::Track::new();
+        let decoded = deserialize_tree(format, tree, &mut track)
+            .map_err(|err| crate::versioning::mismatch_error(err).at_key(&track.path()))?;
+        return Ok(Decoded {
+            upgraded: Some(from),
+            stale: true,
+            ..decoded
+        });
+    }
+    if let Some(write_back) = completed {
+        // Otherwise, the error is reported by the format below
+        let mut track = serde_path_to_error::Track::new();
+        if let Ok(decoded) = deserialize_tree::<T>(format, tree, &mut track) {
+            return Ok(Decoded {
+                stale: write_back,
+                ..decoded
+            });
+        }
+    }
+    format::des_keyed(format, buf)
+}
+
+#[cfg(any(feature = "versioning", feature = "merge"))]
+/// Deserialize `tree` read from `format`, recording the key of the value that failed in `track`.
+fn deserialize_tree<T: DeserializeOwned>(
+    format: Format,
+    tree: Value,
+    track: &mut serde_path_to_error::Track,
+) -> Result<Decoded<T>, DeserializerError> {
+    if format.reads_strings() {
+        Decoded::deserialize(serde_path_to_error::Deserializer::new(Lenient(tree), track))
+    } else {
+        Decoded::deserialize(serde_path_to_error::Deserializer::new(
+            ValueDeserializer::<DeserializerError>::new(tree),
+            track,
+        ))
+    }
+}
+
+#[cfg(any(feature = "versioning", feature = "merge"))]
+/// Untyped tree read from a format whose values are all strings, like ini, deserialized by
+/// parsing the strings by type on demand as the format does. Values filled from the default
+/// keep their type.
+struct Lenient(Value);
+
+#[cfg(any(feature = "versioning", feature = "merge"))]
+macro_rules! deserialize_parse {
+    ($($method:ident => $visit:ident,)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializerError> {
+            match self.0 {
+                Value::String(value) => match value.parse() {
+                    Ok(parsed) => visitor.$visit(parsed),
+                    Err(_) => Err(de::Error::invalid_value(Unexpected::Str(&value), &visitor)),
+                },
+                other => Self(other).deserialize_any(visitor),
+            }
+        }
+    )*};
+}
+
+#[cfg(any(feature = "versioning", feature = "merge"))]
+impl<'de> Deserializer<'de> for Lenient {
+    type Error = DeserializerError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializerError> {
+        match self.0 {
+            Value::Map(entries) => {
+                let mut map =
+                    MapDeserializer::new(entries.into_iter().map(|(k, v)| (Self(k), Self(v))));
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            Value::Seq(items) => {
+                let mut seq = SeqDeserializer::new(items.into_iter().map(Self));
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Value::Option(Some(value)) => visitor.visit_some(Self(*value)),
+            Value::Newtype(value) => visitor.visit_newtype_struct(Self(*value)),
+            other => ValueDeserializer::new(other).deserialize_any(visitor),
+        }
+    }
+
+    deserialize_parse! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, DeserializerError> {
+        match self.0 {
+            Value::Option(None) | Value::Unit => visitor.visit_none(),
+            Value::String(value) if value.is_empty() => visitor.visit_none(),
+            Value::Option(Some(value)) => visitor.visit_some(Self(*value)),
+            other => visitor.visit_some(Self(other)),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializerError> {
+        match self.0 {
+            Value::String(value) if value.is_empty() => visitor.visit_unit(),
+            other => Self(other).deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeserializerError> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeserializerError> {
+        match self.0 {
+            Value::Newtype(value) => visitor.visit_newtype_struct(Self(*value)),
+            other => visitor.visit_newtype_struct(Self(other)),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeserializerError> {
+        match self.0 {
+            Value::String(value) => visitor.visit_enum(value.into_deserializer()),
+            other => ValueDeserializer::new(other).deserialize_enum(name, variants, visitor),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, DeserializerError> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf seq tuple tuple_struct map struct identifier
+    }
+}
+
+#[cfg(any(feature = "versioning", feature = "merge"))]
+impl IntoDeserializer<'_, DeserializerError> for Lenient {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Deep-merge `tree` over `default`, inserting the keys of `default` that `tree` lacks in every
+/// map. Returns whether any key was inserted.
+#[cfg(feature = "merge")]
+pub fn fill(tree: &mut Value, default: Value) -> bool {
+    let (Value::Map(map), Value::Map(default)) = (tree, unwrap(default)) else {
+        return false;
+    };
+    let mut filled = false;
+    for (key, default) in default {
+        if let Some(value) = map.get_mut(&key) {
+            filled |= fill(value, default);
+        } else {
+            map.insert(key, default);
+            filled = true;
+        }
+    }
+    filled
+}
+
+/// The value inside of options and newtypes, which the content doesn't mark.
+#[cfg(feature = "merge")]
+fn unwrap(value: Value) -> Value {
+    match value {
+        Value::Option(Some(value)) | Value::Newtype(value) => unwrap(*value),
+        value => value,
+    }
+}
+
+/// `value` as serialized, with the reference to its JSON Schema and the schema version first,
+/// and the keys of the previous content it doesn't cover last.
+///
+/// The value is written as a map when there's anything to add, so it must be a struct or a map.
+#[cfg(any(feature = "versioning", feature = "preserve", feature = "schema"))]
+pub(crate) struct Layered<'a, T: ?Sized> {
+    pub value: &'a T,
+    /// Reference to the JSON Schema
+    #[cfg(feature = "schema")]
+    pub reference: Option<&'a str>,
+    /// Key and version of the schema
+    #[cfg(feature = "versioning")]
+    pub version: Option<(&'static str, u32)>,
+    /// Content the value was read from, whose unknown keys are preserved
+    #[cfg(feature = "preserve")]
+    pub previous: Option<&'a Value>,
+}
+
+#[cfg(any(feature = "versioning", feature = "preserve", feature = "schema"))]
+impl<T: Serialize + ?Sized> Serialize for Layered<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[cfg(feature = "schema")]
+        let reference = self
+            .reference
+            .map(|reference| (crate::schema::SCHEMA_KEY, reference));
+        #[cfg(not(feature = "schema"))]
+        let reference: Option<(&str, &str)> = None;
+        #[cfg(feature = "versioning")]
+        let version = self.version;
+        #[cfg(not(feature = "versioning"))]
+        let version: Option<(&str, u32)> = None;
+        #[cfg(feature = "preserve")]
+        let unknown = self
+            .previous
+            .and_then(|previous| Unknown::new(previous, self.value));
+        #[cfg(not(feature = "preserve"))]
+        let unknown: Option<Unknown<'_>> = None;
+        if reference.is_none() && version.is_none() && unknown.is_none() {
+            return self.value.serialize(serializer);
+        }
+
+        let mut map = serializer.serialize_map(None)?;
+        if let Some((key, reference)) = reference {
+            map.serialize_entry(key, reference)?;
+        }
+        if let Some((key, version)) = version {
+            map.serialize_entry(key, &version)?;
+        }
+        self.value.serialize(Flatten {
+            map: &mut map,
+            previous: unknown.as_ref().map(|unknown| unknown.previous),
+            key: None,
+        })?;
+        if let Some(unknown) = &unknown {
+            for (key, value) in unknown.extra() {
+                map.serialize_entry(key, value)?;
+            }
+        }
+        map.end()
+    }
+}
+
+/// Keys of the previous content, some of which the value doesn't cover.
+#[cfg(any(feature = "versioning", feature = "preserve", feature = "schema"))]
+struct Unknown<'a> {
+    previous: &'a BTreeMap<Value, Value>,
+    current: BTreeSet<String>,
+}
+
+#[cfg(any(feature = "versioning", feature = "preserve", feature = "schema"))]
+impl<'a> Unknown<'a> {
+    #[cfg(feature = "preserve")]
+    fn new<T: Serialize + ?Sized>(previous: &'a Value, value: &T) -> Option<Self> {
+        let current = serde_value::to_value(value).ok()?;
+        if !has_unknown(previous, &current) {
+            return None;
+        }
+        Some(Self {
+            previous: as_map(previous)?,
+            current: as_map(&current)?.keys().filter_map(key_str).collect(),
+        })
+    }
+
+    /// Previous entries the value doesn't cover.
+    fn extra(&self) -> impl Iterator<Item = (&'a Value, &'a Value)> + '_ {
+        self.previous
+            .iter()
+            .filter(|(key, _)| key_str(key).is_some_and(|key| !self.current.contains(&key)))
+    }
+}
+
+/// The map inside of `value`, through options and newtypes.
+#[cfg(feature = "preserve")]
+fn as_map(value: &Value) -> Option<&BTreeMap<Value, Value>> {
+    match value {
+        Value::Map(map) => Some(map),
+        Value::Option(Some(value)) | Value::Newtype(value) => as_map(value),
+        _ => None,
+    }
+}
+
+/// Keys are compared as strings, which is all most formats have.
+#[cfg(any(feature = "versioning", feature = "preserve", feature = "schema"))]
+fn key_str(key: &Value) -> Option<String> {
+    Some(match key {
+        Value::String(key) => key.clone(),
+        Value::Char(key) => key.to_string(),
+        Value::Bool(key) => key.to_string(),
+        Value::U8(key) => key.to_string(),
+        Value::U16(key) => key.to_string(),
+        Value::U32(key) => key.to_string(),
+        Value::U64(key) => key.to_string(),
+        Value::I8(key) => key.to_string(),
+        Value::I16(key) => key.to_string(),
+        Value::I32(key) => key.to_string(),
+        Value::I64(key) => key.to_string(),
+        _ => return None,
+    })
+}
+
+/// The entries of `tree` at `paths`, with the maps leading to them.
+#[cfg(feature = "preserve")]
+pub fn pick<P: AsRef<[String]>>(tree: &Value, paths: &[P]) -> Value {
+    let Some(map) = as_map(tree) else {
+        return Value::Map(BTreeMap::new());
+    };
+    let picked = map
+        .iter()
+        .filter_map(|(key, value)| {
+            let name = key_str(key)?;
+            let rest: Vec<&[String]> = paths
+                .iter()
+                .filter_map(|path| match path.as_ref() {
+                    [first, rest @ ..] if *first == name => Some(rest),
+                    _ => None,
+                })
+                .collect();
+            if rest.iter().any(|rest| rest.is_empty()) {
+                Some((key.clone(), value.clone()))
+            } else {
+                (!rest.is_empty()).then(|| (key.clone(), pick(value, &rest)))
+            }
+        })
+        .collect();
+    Value::Map(picked)
+}
+
+/// Whether a map in `previous` has keys its counterpart in `current` lacks.
+#[cfg(feature = "preserve")]
+fn has_unknown(previous: &Value, current: &Value) -> bool {
+    let (Some(previous), Some(current)) = (as_map(previous), as_map(current)) else {
+        return false;
+    };
+    let current: BTreeMap<_, _> = current
+        .iter()
+        .filter_map(|(key, value)| Some((key_str(key)?, value)))
+        .collect();
+    previous.iter().any(|(key, previous)| {
+        key_str(key).is_some_and(|key| {
+            current
+                .get(&key)
+                .is_none_or(|current| has_unknown(previous, current))
+        })
+    })
+}
+
+/// Serializes the fields of a struct or the entries of a map into another map, preserving the
+/// unknown keys of their previous content.
+#[cfg(any(feature = "versioning", feature = "preserve", feature = "schema"))]
+struct Flatten<'a, 'p, M> {
+    map: &'a mut M,
+    previous: Option<&'p BTreeMap<Value, Value>>,
+    /// Key of the entry being serialized
+    key: Option<String>,
+}
+
+#[cfg(any(feature = "versioning", feature = "preserve", feature = "schema"))]
+impl<'p, M: SerializeMap> Flatten<'_, 'p, M> {
+    fn previous(&self, key: &str) -> Option<&'p Value> {
+        self.previous?
+            .iter()
+            .find(|(k, _)| key_str(k).as_deref() == Some(key))
+            .map(|(_, value)| value)
+    }
+
+    #[cfg_attr(not(feature = "preserve"), allow(unused_variables))]
+    fn serialize_entry<T: Serialize + ?Sized>(
+        &mut self,
+        previous: Option<&'p Value>,
+        value: &T,
+    ) -> Result<(), M::Error> {
+        #[cfg(feature = "preserve")]
+        if previous.is_some() {
+            return self.map.serialize_value(&Layered {
+                value,
+                #[cfg(feature = "schema")]
+                reference: None,
+                #[cfg(feature = "versioning")]
+                version: None,
+                previous,
+            });
+        }
+        self.map.serialize_value(value)
+    }
+}
+
+#[cfg(any(feature = "versioning", feature = "preserve", feature = "schema"))]
+fn unsupported<E: ser::Error>() -> E {
+    E::custom("the value must be a struct or a map")
+}
+
+#[cfg(any(feature = "versioning", feature = "preserve", feature = "schema"))]
+macro_rules! unsupported {
+    ($($method:ident($($arg:ty),*) -> $ret:ty;)*) => {
+        $(fn $method(self, $(_: $arg),*) -> Result<$ret, Self::Error> {
+            Err(unsupported())
+        })*
+    };
+}
+
+#[cfg(any(feature = "versioning", feature = "preserve", feature = "schema"))]
+impl<M: SerializeMap> Serializer for Flatten<'_, '_, M> {
+    type Ok = ();
+    type Error = M::Error;
+    type SerializeSeq = Impossible<(), M::Error>;
+    type SerializeTuple = Impossible<(), M::Error>;
+    type SerializeTupleStruct = Impossible<(), M::Error>;
+    type SerializeTupleVariant = Impossible<(), M::Error>;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), M::Error>;
+
+    unsupported! {
+        serialize_bool(bool) -> ();
+        serialize_i8(i8) -> ();
+        serialize_i16(i16) -> ();
+        serialize_i32(i32) -> ();
+        serialize_i64(i64) -> ();
+        serialize_u8(u8) -> ();
+        serialize_u16(u16) -> ();
+        serialize_u32(u32) -> ();
+        serialize_u64(u64) -> ();
+        serialize_f32(f32) -> ();
+        serialize_f64(f64) -> ();
+        serialize_char(char) -> ();
+        serialize_str(&str) -> ();
+        serialize_bytes(&[u8]) -> ();
+        serialize_none() -> ();
+        serialize_unit_variant(&'static str, u32, &'static str) -> ();
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), Self::Error> {
+        Err(unsupported())
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self, Self::Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self, Self::Error> {
+        Ok(self)
+    }
+}
+
+#[cfg(any(feature = "versioning", feature = "preserve", feature = "schema"))]
+impl<M: SerializeMap> SerializeMap for Flatten<'_, '_, M> {
+    type Ok = ();
+    type Error = M::Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Self::Error> {
+        if self.previous.is_some() {
+            self.key = serde_value::to_value(key).ok().as_ref().and_then(key_str);
+        }
+        self.map.serialize_key(key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        let previous = self.key.take().and_then(|key| self.previous(&key));
+        self.serialize_entry(previous, value)
+    }
+
+    fn end(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(any(feature = "versioning", feature = "preserve", feature = "schema"))]
+impl<M: SerializeMap> SerializeStruct for Flatten<'_, '_, M> {
+    type Ok = ();
+    type Error = M::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        let previous = self.previous(key);
+        self.map.serialize_key(key)?;
+        self.serialize_entry(previous, value)
+    }
+
+    fn end(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(all(
+    test,
+    feature = "format-json",
+    any(feature = "merge", feature = "preserve")
+))]
+mod test {
+    use serde::{Deserialize, Serialize};
+
+    #[cfg(feature = "merge")]
+    use super::fill;
+    #[cfg(feature = "merge")]
+    use crate::ErrorKind;
+    use crate::Vow;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        port: u16,
+        tls: Tls,
+        proxy: Option<String>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Tls {
+        enabled: bool,
+        cert: String,
+    }
+
+    fn default() -> Config {
+        Config {
+            name: "default".to_owned(),
+            port: 80,
+            tls: Tls {
+                enabled: false,
+                cert: "cert.pem".to_owned(),
+            },
+            proxy: Some("proxy:3128".to_owned()),
+        }
+    }
+
+    #[cfg(feature = "merge")]
+    #[test]
+    fn test_fill() {
+        let mut tree: serde_value::Value =
+            serde_json::from_str(r#"{"name":"mine","tls":{"enabled":true},"extra":1}"#).unwrap();
+        assert!(fill(&mut tree, serde_value::to_value(default()).unwrap()));
+        let config: Config = tree.clone().deserialize_into().unwrap();
+        assert_eq!(
+            config,
+            Config {
+                name: "mine".to_owned(),
+                tls: Tls {
+                    enabled: true,
+                    cert: "cert.pem".to_owned(),
+                },
+                ..default()
+            }
+        );
+        // Nothing left to fill
+        assert!(!fill(&mut tree, serde_value::to_value(default()).unwrap()));
+    }
+
+    #[cfg(feature = "merge")]
+    #[test]
+    fn test_merge() {
+        let path = std::env::temp_dir().join("vow-merge.json");
+        let partial = r#"{"name":"mine","port":8080}"#;
+        let expected = Config {
+            name: "mine".to_owned(),
+            port: 8080,
+            ..default()
+        };
+
+        std::fs::write(&path, partial).unwrap();
+        let mut vow = Vow::open(&path)
+            .merge_defaults()
+            .default(default())
+            .build()
+            .unwrap();
+        assert_eq!(*vow, expected);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), partial);
+
+        // Merged again on reload
+        std::fs::write(&path, r#"{"name":"reloaded"}"#).unwrap();
+        vow.force_reload().unwrap();
+        assert_eq!(
+            *vow,
+            Config {
+                name: "reloaded".to_owned(),
+                ..default()
+            }
+        );
+        std::fs::write(&path, partial).unwrap();
+
+        let vow = Vow::open(&path)
+            .merge_defaults_and_write()
+            .default(default())
+            .build()
+            .unwrap();
+        assert_eq!(*vow, expected);
+        let written: Config =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written, expected);
+
+        // Wrong values are still reported by the format
+        std::fs::write(&path, r#"{"port":"http"}"#).unwrap();
+        let err = Vow::open(&path)
+            .merge_defaults()
+            .deny_invalid()
+            .default(default())
+            .build()
+            .err()
+            .unwrap();
+        assert!(err.is_invalid_data());
+        assert!(err.to_string().contains("line 1 column"), "{err}");
+    }
+
+    #[cfg(all(feature = "merge", feature = "format-ini"))]
+    #[test]
+    fn test_merge_strings() {
+        let path = std::env::temp_dir().join("vow-merge.ini");
+        let partial = "name = mine\nport = 8080\n\n[tls]\nenabled = true\n";
+        std::fs::write(&path, partial).unwrap();
+        let vow = Vow::open(&path)
+            .ini()
+            .merge_defaults()
+            .default(default())
+            .build()
+            .unwrap();
+        assert_eq!(
+            *vow,
+            Config {
+                name: "mine".to_owned(),
+                port: 8080,
+                tls: Tls {
+                    enabled: true,
+                    cert: "cert.pem".to_owned(),
+                },
+                ..default()
+            }
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), partial);
+    }
+
+    #[cfg(feature = "merge")]
+    #[test]
+    fn test_merge_rejected() {
+        let path = std::env::temp_dir().join("vow-merge-rejected.json");
+        let partial = r#"{"name":"mine","port":8080}"#;
+        std::fs::write(&path, partial).unwrap();
+        let mut vow = Vow::open(&path)
+            .merge_defaults()
+            .default(default())
+            .validate(|config: &Config| match config.port {
+                0..1024 => Err(format!("port {} is reserved", config.port)),
+                _ => Ok(()),
+            })
+            .build()
+            .unwrap();
+
+        // The previous value is kept, although the file alone can't be decoded
+        let err = vow.update(|config| config.port = 22).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Validate);
+        assert_eq!(
+            *vow,
+            Config {
+                name: "mine".to_owned(),
+                port: 8080,
+                ..default()
+            }
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), partial);
+    }
+
+    #[cfg(feature = "preserve")]
+    #[test]
+    fn test_preserve() {
+        let path = std::env::temp_dir().join("vow-preserve.json");
+        let open = || {
+            Vow::open(&path)
+                .preserve_unknown()
+                .default(default())
+                .build()
+                .unwrap()
+        };
+
+        // Written by a newer version
+        std::fs::write(
+            &path,
+            r#"{"theme":{"dark":true},"name":"old","port":80,"tls":{"pin":"abc","enabled":false,"cert":"c"},"proxy":null}"#,
+        )
+        .unwrap();
+        let mut vow = open();
+        vow.update(|config| {
+            config.name = "new".to_owned();
+            config.tls.enabled = true;
+        })
+        .unwrap();
+        let written = r#"{"name":"new","port":80,"tls":{"enabled":true,"cert":"c","pin":"abc"},"proxy":null,"theme":{"dark":true}}"#;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), written);
+
+        // Kept across writes and reloads
+        vow.update(|config| config.name = "newer".to_owned())
+            .unwrap();
+        vow.force_reload().unwrap();
+        vow.update(|config| config.name = "new".to_owned()).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), written);
+
+        // Nothing to preserve: written as usual
+        std::fs::remove_file(&path).unwrap();
+        let mut vow = open();
+        vow.update(|config| config.port = 8080).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            serde_json::to_string(&*vow).unwrap()
+        );
+    }
+
+    #[cfg(feature = "preserve")]
+    #[test]
+    fn test_preserve_skipped() {
+        #[derive(Debug, Default, Serialize, Deserialize)]
+        struct Session {
+            user: String,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            token: Option<String>,
+        }
+
+        let path = std::env::temp_dir().join("vow-preserve-skipped.json");
+        std::fs::write(&path, r#"{"user":"ada","token":"abc","extra":1}"#).unwrap();
+        let mut vow = Vow::open(&path)
+            .preserve_unknown()
+            .default(Session::default())
+            .build()
+            .unwrap();
+
+        // Cleared, not mistaken for an unknown key
+        vow.update(|session| session.token = None).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            r#"{"user":"ada","extra":1}"#
+        );
+        vow.force_reload().unwrap();
+        assert_eq!(vow.token, None);
+    }
+
+    #[cfg(all(feature = "preserve", feature = "versioning"))]
+    #[test]
+    fn test_preserve_versioning() {
+        let path = std::env::temp_dir().join("vow-preserve-versioning.json");
+        std::fs::write(
+            &path,
+            r#"{"version":1,"name":"old","extra":1,"port":80,"tls":{"enabled":false,"cert":"c"}}"#,
+        )
+        .unwrap();
+        let mut vow = Vow::open(&path)
+            .schema_version(1)
+            .preserve_unknown()
+            .default(default())
+            .build()
+            .unwrap();
+        vow.update(|config| config.name = "new".to_owned()).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            r#"{"version":1,"name":"new","port":80,"tls":{"enabled":false,"cert":"c"},"proxy":null,"extra":1}"#
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use serde_ignored::Path;
+
+/// Path of an ignored key, e.g. `["server", "tls", "pin"]` or `["mirrors", "0", "url"]`. Options
+/// and newtypes don't add a segment.
+pub(crate) fn key_path(path: &Path<'_>) -> Vec<String> {
+    fn push(path: &Path<'_>, out: &mut Vec<String>) {
+        let segment = match path {
+            Path::Root => return,
+            Path::Seq { parent, index } => {
+                push(parent, out);
+                index.to_string()
+            }
+            Path::Map { parent, key } => {
+                push(parent, out);
+                key.clone()
+            }
+            Path::Some { parent }
+            | Path::NewtypeStruct { parent }
+            | Path::NewtypeVariant { parent } => return push(parent, out),
+        };
+        out.push(segment);
+    }
+
+    let mut out = Vec::new();
+    push(path, &mut out);
+    out
+}
+
+#[cfg(all(test, feature = "format-json", feature = "unknown-keys"))]
+mod test {
+    use serde::{Deserialize, Serialize};
+
+    use crate::{ErrorKind, Vow};
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        log_level: String,
+        server: Server,
+        proxy: Option<Server>,
+        mirrors: Vec<Server>,
+    }
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct Server {
+        port: u16,
+    }
+
+    const TYPOS: &str = r#"{"log_levle":"debug","log_level":"info","server":{"prot":1,"port":80},"proxy":{"port":81,"hots":"x"},"mirrors":[{"port":82},{"port":83,"url":"y"}]}"#;
+
+    #[test]
+    fn test_vow() {
+        let path = std::env::temp_dir().join("vow-unknown.json");
+        std::fs::write(&path, TYPOS).unwrap();
+
+        // Reported, and loaded anyway
+        let mut vow = Vow::open(&path).default(Config::default()).build().unwrap();
+        assert_eq!(vow.server.port, 80);
+        assert_eq!(
+            vow.unknown_keys(),
+            ["log_levle", "server.prot", "proxy.hots", "mirrors.1.url"]
+        );
+
+        // Updated on reload
+        std::fs::write(
+            &path,
+            r#"{"log_level":"info","server":{"port":80},"proxy":null,"mirrors":[],"extra":1}"#,
+        )
+        .unwrap();
+        vow.force_reload().unwrap();
+        assert_eq!(vow.unknown_keys(), ["extra"]);
+        vow.set(Config::default()).unwrap();
+        vow.force_reload().unwrap();
+        assert!(vow.unknown_keys().is_empty());
+
+        // Strict mode: rejected, and left untouched
+        std::fs::write(&path, TYPOS).unwrap();
+        let err = Vow::open(&path)
+            .deny_unknown_keys()
+            .default(Config::default())
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::UnknownKeys);
+        assert!(!err.is_invalid_data());
+        assert_eq!(
+            err.inner().to_string(),
+            "Unknown keys: log_levle, server.prot, proxy.hots, mirrors.1.url"
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), TYPOS);
+    }
+
+    #[cfg(feature = "versioning")]
+    #[test]
+    fn test_versioning() {
+        let path = std::env::temp_dir().join("vow-unknown-versioning.json");
+        std::fs::write(
+            &path,
+            r#"{"version":1,"log_level":"info","server":{"port":80},"proxy":null,"mirrors":[]}"#,
+        )
+        .unwrap();
+        let vow = Vow::open(&path)
+            .schema_version(1)
+            .deny_unknown_keys()
+            .default(Config::default())
+            .build()
+            .unwrap();
+        assert!(vow.unknown_keys().is_empty());
+    }
+}
@@ -0,0 +1,309 @@
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+pub use serde_value::Value;
+
+use crate::{Error, VowResult};
+
+/// Key the schema version is stored under, unless changed with
+/// [`VowBuilder::version_key`](crate::VowBuilder::version_key).
+pub const VERSION_KEY: &str = "version";
+
+type Step = Arc<dyn Fn(Value) -> Result<Value, String> + Send + Sync>;
+
+fn migrate_error(msg: impl Display) -> Error {
+    Error::Migrate(msg.to_string())
+}
+
+/// Current schema version and the migrations leading to it, see
+/// [`VowBuilder::schema_version`](crate::VowBuilder::schema_version).
+///
+/// The version is stored as a top-level key of the file, and files without it are version `0`.
+#[derive(Clone)]
+pub(crate) struct Versioning {
+    pub version: u32,
+    pub key: &'static str,
+    /// Migration from each version to the next one
+    steps: BTreeMap<u32, Step>,
+    /// Back up the file before migrating
+    pub backup: bool,
+    /// Path of the file, once known
+    path: Option<PathBuf>,
+}
+
+impl fmt::Debug for Versioning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Versioning")
+            .field("version", &self.version)
+            .field("key", &self.key)
+            .field("steps", &self.steps.keys().collect::<Vec<_>>())
+            .field("backup", &self.backup)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Versioning {
+    pub fn new() -> Self {
+        Self {
+            version: 0,
+            key: VERSION_KEY,
+            steps: BTreeMap::new(),
+            backup: false,
+            path: None,
+        }
+    }
+
+    /// Register the migration from version `from` to `from + 1`.
+    pub fn step<F, E>(&mut self, from: u32, f: F)
+    where
+        F: Fn(Value) -> Result<Value, E> + Send + Sync + 'static,
+        E: Display,
+    {
+        self.steps.insert(
+            from,
+            Arc::new(move |value| f(value).map_err(|e| e.to_string())),
+        );
+    }
+
+    /// Where the content of version `from` is backed up before migrating, if enabled.
+    pub fn backup_path(&self, from: u32) -> Option<PathBuf> {
+        if !self.backup {
+            return None;
+        }
+        let path = self.path.as_deref()?;
+        let mut name = path.file_name()?.to_owned();
+        name.push(format!(".v{from}.bak"));
+        Some(path.with_file_name(name))
+    }
+
+    /// Check that the backup can be made, i.e. the file is opened by path.
+    pub fn resolve_backup(&mut self, path: Option<&Path>) -> VowResult<()> {
+        if self.backup {
+            let path = path.ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "backing up before migrating requires the file to be opened by path",
+                )
+            })?;
+            self.path = Some(path.to_path_buf());
+        }
+        Ok(())
+    }
+
+    fn version_of(&self, tree: &Value) -> VowResult<u32> {
+        let Value::Map(map) = tree else {
+            return Err(migrate_error("the content is not a map of keys to values"));
+        };
+        let version = match map.get(&Value::String(self.key.to_owned())) {
+            None => return Ok(0),
+            Some(Value::U8(v)) => Some(u32::from(*v)),
+            Some(Value::U16(v)) => Some(u32::from(*v)),
+            Some(Value::U32(v)) => Some(*v),
+            Some(Value::U64(v)) => u32::try_from(*v).ok(),
+            Some(Value::I8(v)) => u32::try_from(*v).ok(),
+            Some(Value::I16(v)) => u32::try_from(*v).ok(),
+            Some(Value::I32(v)) => u32::try_from(*v).ok(),
+            Some(Value::I64(v)) => u32::try_from(*v).ok(),
+            // Formats like ini only have strings
+            Some(Value::String(v)) => v.parse().ok(),
+            Some(_) => None,
+        };
+        version.ok_or_else(|| migrate_error(format!("invalid `{}`", self.key)))
+    }
+
+    /// Whether `tree` is of the current version, so that it won't be upgraded.
+    #[cfg(feature = "schema")]
+    pub fn is_current(&self, tree: &Value) -> bool {
+        self.version_of(tree)
+            .is_ok_and(|version| version == self.version)
+    }
+
+    /// Upgrade `tree` if it's from an older version. Returns the tree of the current version,
+    /// without the version key if upgraded, and the version it was upgraded from.
+    pub fn upgrade(&self, tree: Value) -> VowResult<(Value, Option<u32>)> {
+        let from = self.version_of(&tree)?;
+        match from.cmp(&self.version) {
+            Ordering::Equal => Ok((tree, None)),
+            Ordering::Greater => Err(migrate_error(format!(
+                "the file is from version {from}, newer than {}",
+                self.version
+            ))),
+            Ordering::Less => {
+                let Value::Map(mut map) = tree else {
+                    unreachable!("checked by `version_of`")
+                };
+                map.remove(&Value::String(self.key.to_owned()));
+                let mut tree = Value::Map(map);
+                for version in from..self.version {
+                    let step = self.steps.get(&version).ok_or_else(|| {
+                        migrate_error(format!("no migration from version {version}"))
+                    })?;
+                    tree = step(tree)
+                        .map_err(|e| migrate_error(format!("from version {version}: {e}")))?;
+                }
+                Ok((tree, Some(from)))
+            }
+        }
+    }
+}
+
+/// Error for an upgraded tree that doesn't deserialize into the current type.
+pub(crate) fn mismatch_error(err: impl Display) -> Error {
+    migrate_error(format!(
+        "the migrated value doesn't match the current version: {err}"
+    ))
+}
+
+#[cfg(all(test, feature = "format-json"))]
+mod test {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::Value;
+    use crate::{tree::Layered, ErrorKind, Vow};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct V0 {
+        name: String,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct V1 {
+        first: String,
+        last: String,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct V2 {
+        first: String,
+        last: String,
+        age: u8,
+    }
+
+    #[test]
+    fn test_versioned() {
+        let value = V1 {
+            first: "Ada".to_owned(),
+            last: "Lovelace".to_owned(),
+        };
+        let versioned = Layered {
+            value: &value,
+            #[cfg(feature = "schema")]
+            reference: None,
+            version: Some(("version", 1)),
+            #[cfg(feature = "preserve")]
+            previous: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&versioned).unwrap(),
+            r#"{"version":1,"first":"Ada","last":"Lovelace"}"#
+        );
+        let map = BTreeMap::from([("b", 2), ("a", 1)]);
+        let versioned = Layered {
+            value: &Some(map),
+            #[cfg(feature = "schema")]
+            reference: None,
+            version: Some(("v", 3)),
+            #[cfg(feature = "preserve")]
+            previous: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&versioned).unwrap(),
+            r#"{"v":3,"a":1,"b":2}"#
+        );
+        let versioned = Layered {
+            value: &[1],
+            #[cfg(feature = "schema")]
+            reference: None,
+            version: Some(("v", 3)),
+            #[cfg(feature = "preserve")]
+            previous: None,
+        };
+        assert!(serde_json::to_string(&versioned).is_err());
+    }
+
+    fn open(path: &std::path::Path) -> crate::VowResult<Vow<V2, std::fs::File>> {
+        Vow::open(path)
+            .schema_version(2)
+            .typed_migration(0, |old: V0| {
+                let (first, last) = old.name.split_once(' ').unwrap_or((&old.name, ""));
+                V1 {
+                    first: first.to_owned(),
+                    last: last.to_owned(),
+                }
+            })
+            .migration(1, |value| {
+                let Value::Map(mut map) = value else {
+                    return Err("not a map");
+                };
+                map.insert(Value::String("age".to_owned()), Value::U8(36));
+                Ok(Value::Map(map))
+            })
+            .migration_backup()
+            .default(V2 {
+                first: String::new(),
+                last: String::new(),
+                age: 0,
+            })
+            .build()
+    }
+
+    #[test]
+    fn test_vow() {
+        let dir = std::env::temp_dir().join("vow-versioning");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("person.json");
+        let ada = V2 {
+            first: "Ada".to_owned(),
+            last: "Lovelace".to_owned(),
+            age: 36,
+        };
+
+        // Upgraded through the whole chain, written back and backed up
+        std::fs::write(&path, r#"{"name":"Ada Lovelace"}"#).unwrap();
+        let vow = open(&path).unwrap();
+        assert_eq!(*vow, ada);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            r#"{"version":2,"first":"Ada","last":"Lovelace","age":36}"#
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("person.json.v0.bak")).unwrap(),
+            r#"{"name":"Ada Lovelace"}"#
+        );
+
+        // Only the missing steps run
+        std::fs::write(&path, r#"{"version":1,"first":"Ada","last":"Lovelace"}"#).unwrap();
+        assert_eq!(*open(&path).unwrap(), ada);
+        assert!(dir.join("person.json.v1.bak").exists());
+
+        // Never overwritten when it can't be migrated
+        for (content, msg) in [
+            (
+                r#"{"version":3,"first":"Ada"}"#,
+                "the file is from version 3, newer than 2",
+            ),
+            (r#"{"version":"x"}"#, "invalid `version`"),
+            ("[1, 2]", "the content is not a map of keys to values"),
+        ] {
+            std::fs::write(&path, content).unwrap();
+            let err = open(&path).err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::Migrate);
+            assert_eq!(err.inner().to_string(), format!("Migration error: {msg}"));
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), content);
+        }
+        std::fs::write(&path, r#"{"nickname":"Ada"}"#).unwrap();
+        let err = open(&path).err().unwrap();
+        assert_eq!(
+            err.inner().to_string(),
+            "Migration error: from version 0: Missing field name"
+        );
+    }
+}